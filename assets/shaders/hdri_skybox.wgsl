@@ -0,0 +1,27 @@
+#import bevy_pbr::mesh_view_bindings
+
+@group(1) @binding(0)
+var cube_map: texture_cube<f32>;
+@group(1) @binding(1)
+var cube_map_sampler: sampler;
+@group(1) @binding(2)
+var<uniform> exposure: f32;
+@group(1) @binding(3)
+var<uniform> rotation: f32;
+
+@fragment
+fn fragment(
+    #import bevy_pbr::mesh_vertex_output
+) -> @location(0) vec4<f32> {
+    let view_direction = normalize(world_position.xyz - view.world_position);
+    // Turn the skybox around the vertical axis
+    let c = cos(rotation);
+    let s = sin(rotation);
+    let direction = vec3<f32>(
+        c * view_direction.x + s * view_direction.z,
+        view_direction.y,
+        c * view_direction.z - s * view_direction.x,
+    );
+    let color = textureSample(cube_map, cube_map_sampler, direction).rgb;
+    return vec4<f32>(color * exposure, 1.0);
+}
@@ -0,0 +1,69 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader
+#import bevy_core_pipeline::tonemapping
+
+struct PostProcessEffect {
+    // Color of the fog
+    color: vec4<f32>,
+    // Opacity of the fog, exposure and dithering of the tonemapping, or strength of the grading
+    values: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> effect: PostProcessEffect;
+@group(0) @binding(3)
+var lut_texture: texture_2d<f32>;
+@group(0) @binding(4)
+var lut_sampler: sampler;
+
+// The LUT is 16 slices of 16x16 texels side by side, one slice per level of blue
+let LUT_SIZE: f32 = 16.0;
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+// The LUT is indexed with sRGB colors, and its texels are converted back to linear when sampled
+fn sample_lut(color: vec3<f32>) -> vec3<f32> {
+    let scaled = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)) * (LUT_SIZE - 1.0);
+    let slice = floor(scaled.b);
+    let next_slice = min(slice + 1.0, LUT_SIZE - 1.0);
+    // Sample the center of the texels, the filtering blends red and green
+    let x = (scaled.r + 0.5) / (LUT_SIZE * LUT_SIZE);
+    let y = (scaled.g + 0.5) / LUT_SIZE;
+    let low = textureSample(lut_texture, lut_sampler, vec2<f32>(x + slice / LUT_SIZE, y)).rgb;
+    let high = textureSample(lut_texture, lut_sampler, vec2<f32>(x + next_slice / LUT_SIZE, y)).rgb;
+    return mix(low, high, scaled.b - slice);
+}
+
+@fragment
+fn fog(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, in.uv);
+    return vec4<f32>(mix(color.rgb, effect.color.rgb, effect.values.x), color.a);
+}
+
+// The same as the tonemapping of Bevy, with an exposure
+@fragment
+fn tonemap(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, in.uv);
+    var output_rgb = reinhard_luminance(color.rgb * effect.values.x);
+    if (effect.values.y > 0.5) {
+        // Dithered in sRGB, the output stays linear until it is shown
+        output_rgb = pow(output_rgb, vec3<f32>(1.0 / 2.2));
+        output_rgb = output_rgb + screen_space_dither(in.position.xy);
+        output_rgb = pow(output_rgb, vec3<f32>(2.2));
+    }
+    return vec4<f32>(output_rgb, color.a);
+}
+
+@fragment
+fn color_grade(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, in.uv);
+    let graded = sample_lut(linear_to_srgb(color.rgb));
+    return vec4<f32>(mix(color.rgb, graded, effect.values.x), color.a);
+}
@@ -0,0 +1,13 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader
+
+@group(0) @binding(0)
+var source_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+// The target is sRGB, the colors are encoded when written
+@fragment
+fn copy(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source_texture, source_sampler, in.uv);
+    return vec4<f32>(color.rgb, 1.0);
+}
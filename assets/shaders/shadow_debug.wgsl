@@ -0,0 +1,14 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader
+
+@group(0) @binding(0)
+var shadow_map: texture_depth_2d;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(shadow_map));
+    let texel = vec2<i32>(in.uv * size);
+    // Bevy uses a reversed depth, cleared to 0 on the far plane: what is close to the light is
+    // bright, and where nothing casts a shadow is black
+    let depth = textureLoad(shadow_map, texel, 0);
+    return vec4<f32>(vec3<f32>(depth), 1.0);
+}
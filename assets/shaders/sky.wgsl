@@ -0,0 +1,61 @@
+#import bevy_pbr::mesh_view_bindings
+
+@group(1) @binding(0)
+var<uniform> sun_direction: vec3<f32>;
+@group(1) @binding(1)
+var<uniform> sun_illuminance: f32;
+@group(1) @binding(2)
+var<uniform> rayleigh_coefficient: vec3<f32>;
+@group(1) @binding(3)
+var<uniform> mie_coefficient: f32;
+
+let PI: f32 = 3.14159265;
+// Heights under which most of the air and of the aerosols are, in meters
+let RAYLEIGH_HEIGHT: f32 = 8000.0;
+let MIE_HEIGHT: f32 = 1200.0;
+// Asymmetry of the Mie scattering, forward towards the sun
+let MIE_G: f32 = 0.76;
+
+// Relative length of the path through the atmosphere, 1 at the zenith and longer near the horizon
+// Kasten-Young formula
+fn air_mass(up: f32) -> f32 {
+    let height = max(up, 0.0);
+    return 1.0 / (height + 0.15 * pow(93.885 - degrees(acos(height)), -1.253));
+}
+
+fn rayleigh_phase(mu: f32) -> f32 {
+    return 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+}
+
+// Cornette-Shanks approximation
+fn mie_phase(mu: f32) -> f32 {
+    let g2 = MIE_G * MIE_G;
+    let denominator = (2.0 + g2) * pow(1.0 + g2 - 2.0 * MIE_G * mu, 1.5);
+    return 3.0 / (8.0 * PI) * (1.0 - g2) * (1.0 + mu * mu) / denominator;
+}
+
+@fragment
+fn fragment(
+    #import bevy_pbr::mesh_vertex_output
+) -> @location(0) vec4<f32> {
+    let view_direction = normalize(world_position.xyz - view.world_position);
+    let sun = normalize(sun_direction);
+    let mu = dot(view_direction, sun);
+
+    let rayleigh = rayleigh_coefficient * RAYLEIGH_HEIGHT;
+    let mie = vec3<f32>(mie_coefficient * MIE_HEIGHT);
+    let extinction = rayleigh + mie;
+
+    // Sunlight reaching the air, reddened when the sun is low
+    let sunlight = sun_illuminance * exp(-extinction * air_mass(sun.y));
+    // Fraction of it scattered towards the camera along the view ray
+    let view_extinction = exp(-extinction * air_mass(view_direction.y));
+    let scattering = (rayleigh * rayleigh_phase(mu) + mie * mie_phase(mu)) / extinction;
+    var color = sunlight * scattering * (1.0 - view_extinction);
+
+    // The ground hides the sky below the horizon
+    color = color * smoothstep(-0.1, 0.0, view_direction.y);
+    // Lower bound at night, the old clear color
+    let night_sky = vec3<f32>(0.01, 0.01, 0.163);
+    return vec4<f32>(max(color, night_sky), 1.0);
+}
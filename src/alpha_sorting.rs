@@ -0,0 +1,94 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::VertexAttributeValues, primitives::Aabb},
+    utils::HashMap,
+};
+
+use crate::loading::{ScenesReady, ScenesReadySystem};
+
+// Offset from the origin below which a mesh is already centered, in mesh units
+const CENTERED_DISTANCE: f32 = 1e-3;
+
+/// Fixes the back-to-front order of the [`AlphaMode::Blend`] meshes of the scenes.
+///
+/// Bevy sorts transparent meshes every frame by the distance from the camera to their origin. The
+/// meshes of the scenes are moved instead of their entities, so their origins are all at the
+/// center of the scene and their order is arbitrary. Once the scenes are fixed, the vertices of the
+/// blended meshes are moved around their center, and every entity using them is moved back by the
+/// same offset, each time the scenes become [`ScenesReady`].
+pub struct AlphaSortingPlugin;
+
+impl Plugin for AlphaSortingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            recenter_blended_meshes
+                .label(AlphaSortingSystem)
+                .after(ScenesReadySystem),
+        );
+    }
+}
+
+/// Label of the system moving the vertices of the blended meshes, systems reading their positions
+/// should run before it.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AlphaSortingSystem;
+
+#[allow(clippy::type_complexity)]
+fn recenter_blended_meshes(
+    mut commands: Commands,
+    ready: Res<ScenesReady>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<Assets<StandardMaterial>>,
+    mut entities: Query<(
+        Entity,
+        &Handle<Mesh>,
+        Option<&Handle<StandardMaterial>>,
+        &mut Transform,
+    )>,
+) {
+    if !ready.is_changed() || !ready.0 {
+        return;
+    }
+    // Materials are made transparent while the scenes are fixed. Done again when the exterior is
+    // streamed back in, the meshes already centered are skipped
+    // Offset removed from the vertices of each recentered mesh
+    let mut recentered: HashMap<Handle<Mesh>, Vec3> = HashMap::default();
+    for (_, mesh_handle, material, _) in entities.iter() {
+        let is_blended = material
+            .and_then(|material| materials.get(material))
+            .is_some_and(|material| material.alpha_mode == AlphaMode::Blend);
+        if !is_blended || recentered.contains_key(mesh_handle) {
+            continue;
+        }
+        let mesh = match meshes.get_mut(mesh_handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let center = match mesh.compute_aabb() {
+            Some(aabb) => Vec3::from(aabb.center),
+            None => continue,
+        };
+        if center.length() < CENTERED_DISTANCE {
+            continue;
+        }
+        if let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+        {
+            for position in positions.iter_mut() {
+                *position = (Vec3::from(*position) - center).to_array();
+            }
+            recentered.insert(mesh_handle.clone(), center);
+        }
+    }
+
+    // Move the entities using a recentered mesh back to where the mesh was, blended or not
+    for (entity, mesh_handle, _, mut transform) in entities.iter_mut() {
+        if let Some(offset) = recentered.get(mesh_handle) {
+            let offset = transform.rotation * (transform.scale * *offset);
+            transform.translation += offset;
+            // Bounds are computed again from the moved vertices
+            commands.entity(entity).remove::<Aabb>();
+        }
+    }
+    info!("recentered {} blended meshes", recentered.len());
+}
@@ -0,0 +1,89 @@
+use bevy::{audio::AudioSink, prelude::*};
+
+use crate::plugins::sky::SunLight;
+
+// Above this sun illuminance, in lux, the day ambience plays
+const DAY_ILLUMINANCE: f32 = 5000.0;
+// Duration of the crossfade between the day and night ambiences, in seconds
+const CROSSFADE_DURATION: f32 = 5.0;
+const AMBIENCE_VOLUME: f32 = 0.6;
+const WIND_VOLUME: f32 = 0.15;
+
+/// Day and night ambient sounds following the sun, with wind playing all the time.
+///
+/// Needs the `audio` feature, and the sounds in `assets/audio`.
+pub struct AmbientAudioPlugin;
+
+impl Plugin for AmbientAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_ambient_audio)
+            .add_system(crossfade_ambient_audio);
+    }
+}
+
+#[derive(Resource)]
+struct AmbientSounds {
+    day: Handle<AudioSource>,
+    night: Handle<AudioSource>,
+    wind: Handle<AudioSource>,
+    // Sinks once the sounds started, the wind sink is kept to keep it playing
+    sinks: Option<AmbientSinks>,
+    // From 0 at night to 1 during the day
+    day_weight: f32,
+}
+
+struct AmbientSinks {
+    day: Handle<AudioSink>,
+    night: Handle<AudioSink>,
+    _wind: Handle<AudioSink>,
+}
+
+fn setup_ambient_audio(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AmbientSounds {
+        day: asset_server.load("audio/day_ambience.ogg"),
+        night: asset_server.load("audio/night_ambience.ogg"),
+        wind: asset_server.load("audio/wind.ogg"),
+        sinks: None,
+        day_weight: 1.0,
+    });
+}
+
+fn crossfade_ambient_audio(
+    time: Res<Time>,
+    audio: Res<Audio>,
+    mut sounds: ResMut<AmbientSounds>,
+    sinks: Res<Assets<AudioSink>>,
+    sun: Query<&DirectionalLight, With<SunLight>>,
+) {
+    let is_day = sun.iter().any(|light| light.illuminance > DAY_ILLUMINANCE);
+    let step = time.delta_seconds() / CROSSFADE_DURATION;
+    sounds.day_weight = if is_day {
+        (sounds.day_weight + step).min(1.0)
+    } else {
+        (sounds.day_weight - step).max(0.0)
+    };
+
+    if sounds.sinks.is_none() {
+        // Start every sound at once so that they loop together, the volumes are set below
+        let play = |sound: &Handle<AudioSource>, volume: f32| {
+            sinks.get_handle(
+                audio.play_with_settings(sound.clone(), PlaybackSettings::LOOP.with_volume(volume)),
+            )
+        };
+        sounds.sinks = Some(AmbientSinks {
+            day: play(&sounds.day, 0.0),
+            night: play(&sounds.night, 0.0),
+            _wind: play(&sounds.wind, WIND_VOLUME),
+        });
+    }
+
+    if let Some(ambient) = &sounds.sinks {
+        // Sinks only exist once their sound is loaded and playing
+        if let Some(sink) = sinks.get(&ambient.day) {
+            sink.set_volume(AMBIENCE_VOLUME * sounds.day_weight);
+        }
+        if let Some(sink) = sinks.get(&ambient.night) {
+            sink.set_volume(AMBIENCE_VOLUME * (1.0 - sounds.day_weight));
+        }
+    }
+}
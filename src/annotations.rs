@@ -0,0 +1,139 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    render::primitives::Aabb,
+    utils::{BoxedFuture, HashMap},
+};
+
+use crate::{
+    loading::{ScenesReady, ScenesReadySystem},
+    plugins::camera::MainCamera,
+};
+
+pub const ANNOTATIONS_PATH: &str = "annotations.json";
+// Annotated entities closer than this to the camera are printed with `I`, in meters
+const NEARBY_DISTANCE: f32 = 5.0;
+
+/// Attaches the properties in `assets/annotations.json` to the named entities of the scenes.
+///
+/// The file maps entity names to properties, and is loaded as an asset so that it is fetched over
+/// HTTP on the web. The properties are attached as a [`SceneAnnotation`] once both scenes are
+/// fixed.
+pub struct AnnotationsPlugin;
+
+impl Plugin for AnnotationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SceneAnnotations>()
+            .add_asset_loader(SceneAnnotationsLoader)
+            .add_startup_system(load_annotations)
+            .add_system(apply_annotations.after(ScenesReadySystem))
+            .add_system(annotations_info.after(apply_annotations));
+    }
+}
+
+/// Properties of a named entity, like `"material_override": "glass"`.
+#[derive(Component, Debug, Clone)]
+pub struct SceneAnnotation {
+    pub properties: HashMap<String, String>,
+}
+
+// Properties of each entity, by name
+#[derive(TypeUuid)]
+#[uuid = "0b6e3f5a-8d41-4c27-9a3e-5f1d2c7b9e84"]
+struct SceneAnnotations(HashMap<String, HashMap<String, String>>);
+
+struct SceneAnnotationsLoader;
+
+impl AssetLoader for SceneAnnotationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let annotations = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(SceneAnnotations(annotations)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+#[derive(Resource)]
+struct SceneAnnotationsHandle(Handle<SceneAnnotations>);
+
+fn load_annotations(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SceneAnnotationsHandle(asset_server.load(ANNOTATIONS_PATH)));
+}
+
+fn apply_annotations(
+    mut commands: Commands,
+    ready: Res<ScenesReady>,
+    asset_server: Res<AssetServer>,
+    handle: Res<SceneAnnotationsHandle>,
+    annotations: Res<Assets<SceneAnnotations>>,
+    named_entities: Query<(Entity, &Name)>,
+) {
+    if !ready.is_changed() || !ready.0 {
+        return;
+    }
+    // Entities are renamed or despawned while the scenes are fixed. Done again when the exterior
+    // is streamed back in. The file is preloaded, it is loaded or failed by then
+    let annotations = match annotations.get(&handle.0) {
+        Some(annotations) => annotations,
+        None => {
+            // The asset server logs why the file could not be loaded
+            if asset_server.get_load_state(&handle.0) == LoadState::Failed {
+                warn!("could not load {ANNOTATIONS_PATH}, no annotations");
+            }
+            return;
+        }
+    };
+    let mut count = 0;
+    for (entity, name) in named_entities.iter() {
+        if let Some(properties) = annotations.0.get(name.as_str()) {
+            commands.entity(entity).insert(SceneAnnotation {
+                properties: properties.clone(),
+            });
+            count += 1;
+        }
+    }
+    info!("annotated {count} entities");
+}
+
+fn annotations_info(
+    input: Res<Input<KeyCode>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    annotated: Query<(&Name, &SceneAnnotation, &GlobalTransform, Option<&Children>)>,
+    bounds: Query<(&Aabb, &GlobalTransform)>,
+) {
+    if !input.just_pressed(KeyCode::I) {
+        return;
+    }
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    for (name, annotation, transform, children) in annotated.iter() {
+        // The meshes are moved instead of the entities, use the center of the first one
+        let position = children
+            .and_then(|children| children.iter().find_map(|child| bounds.get(*child).ok()))
+            .map_or(transform.translation(), |(aabb, transform)| {
+                transform.transform_point(aabb.center.into())
+            });
+        if position.distance(camera) > NEARBY_DISTANCE {
+            continue;
+        }
+        let mut properties: Vec<_> = annotation.properties.iter().collect();
+        properties.sort();
+        let properties: Vec<_> = properties
+            .into_iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+        info!("Annotation {name} | {}", properties.join(" - "));
+    }
+}
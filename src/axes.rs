@@ -0,0 +1,156 @@
+use bevy::{
+    core_pipeline::{clear_color::ClearColorConfig, tonemapping::Tonemapping},
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        camera::{CameraUpdateSystem, Viewport},
+        view::RenderLayers,
+    },
+    transform::TransformSystem,
+};
+
+use crate::{camera_controller::CameraRotationSystem, plugins::camera::MainCamera};
+
+// Size of the corner showing the axes, in physical pixels
+pub const AXES_VIEWPORT_SIZE: u32 = 120;
+// Layer only seen by the axes camera
+const AXES_LAYER: u8 = 3;
+
+/// World X/Y/Z axes in the bottom left corner of the window, following the camera rotation.
+///
+/// Toggled with `X`, as `A` is used to move the camera. `Shift+X` is left to the ghost mode.
+pub struct AxesPlugin;
+
+impl Plugin for AxesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowAxes>()
+            .add_startup_system(setup_axes)
+            .add_system(toggle_axes)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                // Before the viewport is used to update the projection
+                update_axes_camera
+                    .after(CameraRotationSystem)
+                    .before(TransformSystem::TransformPropagate)
+                    .before(CameraUpdateSystem),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct ShowAxes {
+    pub enabled: bool,
+}
+
+impl Default for ShowAxes {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Component)]
+struct AxesCamera;
+
+fn setup_axes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (direction, color) in [
+        (Vec3::X, Color::RED),
+        (Vec3::Y, Color::GREEN),
+        (Vec3::Z, Color::BLUE),
+    ] {
+        let thickness = Vec3::splat(0.05);
+        // Shaft from the origin along the axis, with a thicker tip
+        let shaft = box_from_corners(-thickness, direction + thickness);
+        let tip = box_from_corners(
+            direction * 0.8 - thickness * 2.0,
+            direction + thickness * 2.0,
+        );
+        let material = materials.add(StandardMaterial {
+            base_color: color,
+            unlit: true,
+            ..default()
+        });
+        for mesh in [shaft, tip] {
+            commands.spawn((
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(mesh)),
+                    material: material.clone(),
+                    ..default()
+                },
+                RenderLayers::layer(AXES_LAYER),
+                // Shadows ignore render layers
+                NotShadowCaster,
+                NotShadowReceiver,
+            ));
+        }
+    }
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Drawn after the main camera, in the same HDR texture
+                priority: 1,
+                hdr: true,
+                ..default()
+            },
+            camera_3d: Camera3d {
+                // Keep the scene, but clear the depth so that the axes are always on top
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            // The main camera already tonemapped the texture
+            tonemapping: Tonemapping::Disabled,
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        RenderLayers::layer(AXES_LAYER),
+        AxesCamera,
+    ));
+}
+
+fn box_from_corners(min: Vec3, max: Vec3) -> shape::Box {
+    shape::Box {
+        min_x: min.x,
+        max_x: max.x,
+        min_y: min.y,
+        max_y: max.y,
+        min_z: min.z,
+        max_z: max.z,
+    }
+}
+
+fn toggle_axes(input: Res<Input<KeyCode>>, mut show: ResMut<ShowAxes>) {
+    if input.just_pressed(KeyCode::X) && !input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        show.enabled = !show.enabled;
+        info!("axes: {}", show.enabled);
+    }
+}
+
+fn update_axes_camera(
+    show: Res<ShowAxes>,
+    windows: Res<Windows>,
+    main_camera: Query<&Transform, (With<MainCamera>, Without<AxesCamera>)>,
+    mut axes_camera: Query<(&mut Camera, &mut Transform), With<AxesCamera>>,
+) {
+    let rotation = main_camera
+        .get_single()
+        .map(|transform| transform.rotation)
+        .ok();
+    let window_height = windows.get_primary().map(|window| window.physical_height());
+    for (mut camera, mut transform) in axes_camera.iter_mut() {
+        camera.is_active = show.enabled;
+        if let (Some(rotation), Some(window_height)) = (rotation, window_height) {
+            // Look at the origin with the same orientation as the main camera
+            *transform =
+                Transform::from_translation(rotation * Vec3::Z * 3.5).with_rotation(rotation);
+            camera.viewport = Some(Viewport {
+                physical_position: UVec2::new(0, window_height.saturating_sub(AXES_VIEWPORT_SIZE)),
+                physical_size: UVec2::splat(AXES_VIEWPORT_SIZE.min(window_height.max(1))),
+                ..default()
+            });
+        }
+    }
+}
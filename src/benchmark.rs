@@ -0,0 +1,109 @@
+use bevy::{app::AppExit, input::InputSystem, prelude::*};
+
+use crate::{
+    camera_controller::CameraController, cinematic::sample_path, loading::GameState, platform,
+    plugins::camera::MainCamera,
+};
+
+const BENCHMARK_RESULTS_PATH: &str = "benchmark_results.csv";
+
+// Camera path followed during the benchmark, as (position, looking at, time)
+const BENCHMARK_PATH: [(Vec3, Vec3, f32); 5] = [
+    (Vec3::new(-16.0, 6.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 0.0),
+    (Vec3::new(-10.0, 2.0, 8.0), Vec3::new(0.0, 1.5, 0.0), 7.5),
+    (Vec3::new(-4.0, 1.8, 0.0), Vec3::new(5.0, 1.5, 0.0), 15.0),
+    (Vec3::new(-10.0, 2.0, -8.0), Vec3::new(0.0, 1.5, 0.0), 22.5),
+    (Vec3::new(-16.0, 6.0, 1.0), Vec3::new(0.0, 1.0, 0.0), 30.0),
+];
+
+/// Runs the camera along a fixed path once the scenes are loaded, recording frame times, then
+/// writes them to `benchmark_results.csv` and exits. User input is ignored meanwhile.
+pub struct BenchmarkPlugin;
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BenchmarkMode>()
+            .add_startup_system_to_stage(StartupStage::PostStartup, disable_camera_controller)
+            .add_system_to_stage(CoreStage::PreUpdate, ignore_user_input.after(InputSystem))
+            // Only measuring once everything is loaded
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(benchmark))
+            .add_system_to_stage(CoreStage::Last, write_benchmark_results);
+    }
+}
+
+#[derive(Resource)]
+pub struct BenchmarkMode {
+    pub duration: f32,
+    pub elapsed: f32,
+    pub frame_times: Vec<f32>,
+}
+
+impl Default for BenchmarkMode {
+    fn default() -> Self {
+        Self {
+            duration: BENCHMARK_PATH[BENCHMARK_PATH.len() - 1].2,
+            elapsed: 0.0,
+            frame_times: Vec::new(),
+        }
+    }
+}
+
+// Removing the controller also keeps the camera position of the benchmark from being saved
+fn disable_camera_controller(mut commands: Commands, cameras: Query<Entity, With<Camera>>) {
+    for entity in cameras.iter() {
+        commands.entity(entity).remove::<CameraController>();
+    }
+}
+
+fn ignore_user_input(mut keys: ResMut<Input<KeyCode>>, mut buttons: ResMut<Input<MouseButton>>) {
+    keys.reset_all();
+    buttons.reset_all();
+}
+
+fn benchmark(
+    time: Res<Time>,
+    mut mode: ResMut<BenchmarkMode>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    if mode.elapsed >= mode.duration {
+        app_exit.send(AppExit);
+        return;
+    }
+    let keyframes: Vec<_> = BENCHMARK_PATH
+        .iter()
+        .map(|(position, looking_at, time)| {
+            (
+                Transform::from_translation(*position).looking_at(*looking_at, Vec3::Y),
+                *time,
+            )
+        })
+        .collect();
+    if let Some(sampled) = sample_path(&keyframes, mode.elapsed) {
+        for mut transform in camera.iter_mut() {
+            *transform = sampled;
+        }
+    }
+    mode.elapsed += time.delta_seconds();
+    // The first update has no delta, it would be written as an infinite fps
+    if time.delta_seconds() > 0.0 {
+        mode.frame_times.push(time.delta_seconds());
+    }
+}
+
+fn write_benchmark_results(mut app_exit: EventReader<AppExit>, mode: Res<BenchmarkMode>) {
+    if app_exit.iter().last().is_none() {
+        return;
+    }
+    let mut csv = String::from("frame_index,delta_ms,fps\n");
+    for (index, delta) in mode.frame_times.iter().enumerate() {
+        csv.push_str(&format!("{index},{},{}\n", delta * 1000.0, 1.0 / delta));
+    }
+    match platform::write(BENCHMARK_RESULTS_PATH, csv) {
+        Ok(()) => info!(
+            "wrote {} frame times to {BENCHMARK_RESULTS_PATH}",
+            mode.frame_times.len()
+        ),
+        Err(err) => error!("could not write {BENCHMARK_RESULTS_PATH}: {err}"),
+    }
+}
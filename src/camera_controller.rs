@@ -0,0 +1,664 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::primitives::Aabb,
+    transform::TransformSystem,
+    window::CursorGrabMode,
+};
+
+use crate::{keybinds::keycode_name, mesh_utils::world_bounds, platform};
+
+/// Adds the [`CameraController`] fly camera.
+///
+/// The controller system is labeled with [`CameraControllerSystem`] so that other systems can be
+/// ordered relative to it. See [`CameraController`] for the inputs it consumes.
+///
+/// The controller only updates its `pitch` and `yaw`, the rotation of the camera is set from them
+/// at the end of the frame, by the system labeled with [`CameraRotationSystem`]. Systems reading
+/// the direction of the camera should run after it.
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CameraController>()
+            .register_type::<CollisionAvoidance>()
+            .register_type::<MouseLookMode>()
+            .add_system(camera_controller.label(CameraControllerSystem))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_camera_rotation
+                    .label(CameraRotationSystem)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraControllerSystem;
+
+/// Label of the system setting the rotation of the cameras from their [`CameraController`], in
+/// [`CoreStage::PostUpdate`].
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraRotationSystem;
+
+/// A fly camera controller, to add on an entity with a [`Camera`].
+///
+/// Inputs consumed by [`camera_controller`], with their default bindings:
+/// * `key_forward` / `key_back` / `key_left` / `key_right` (`W` / `S` / `A` / `D`): move horizontally
+/// * `key_up` / `key_down` (`E` / `Q`): move vertically, or jump with `key_up` in walk mode
+/// * the movement keys are ignored while [`platform::SHORTCUT_MODIFIER`] is held, unless it is
+///   also `key_run`
+/// * `key_run` (`LShift`): move at `run_speed` instead of `walk_speed`
+/// * `key_enable_mouse` (`MouseButton::Left`): [`MouseMotion`] events rotate the camera while it
+///   is held, or after clicking it once in [`MouseLookMode::ToggleLock`]
+/// * `key_walk_mode` (`V`): switch between flying and walking on the floor below the camera
+/// * [`MouseWheel`] events zoom by changing `zoom_fov`, the field of view of the
+///   [`PerspectiveProjection`], unless [`platform::SHORTCUT_MODIFIER`] or `Alt` is held
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct CameraController {
+    pub enabled: bool,
+    pub initialized: bool,
+    pub sensitivity: f32,
+    // Ratio applied to `sensitivity` for vertical mouse motion
+    pub vertical_sensitivity_ratio: f32,
+    pub key_forward: KeyCode,
+    pub key_back: KeyCode,
+    pub key_left: KeyCode,
+    pub key_right: KeyCode,
+    pub key_up: KeyCode,
+    pub key_down: KeyCode,
+    pub key_run: KeyCode,
+    pub key_enable_mouse: MouseButton,
+    pub mouse_look_mode: MouseLookMode,
+    // Whether the cursor is grabbed, in `MouseLookMode::ToggleLock`
+    pub cursor_locked: bool,
+    pub key_walk_mode: KeyCode,
+    pub walk_speed: f32,
+    pub run_speed: f32,
+    pub friction: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub velocity: Vec3,
+    // Keep the camera at `eye_height` above the floor, with gravity
+    pub walk_mode: bool,
+    // In meters
+    pub eye_height: f32,
+    pub jump_height: f32,
+    // In meters per second squared
+    pub gravity: f32,
+    pub vertical_velocity: f32,
+    // Field of view the projection moves towards, in degrees
+    pub zoom_fov: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initialized: false,
+            sensitivity: 0.5,
+            vertical_sensitivity_ratio: 0.5,
+            key_forward: KeyCode::W,
+            key_back: KeyCode::S,
+            key_left: KeyCode::A,
+            key_right: KeyCode::D,
+            key_up: KeyCode::E,
+            key_down: KeyCode::Q,
+            key_run: KeyCode::LShift,
+            key_enable_mouse: MouseButton::Left,
+            mouse_look_mode: MouseLookMode::HoldToLook,
+            cursor_locked: false,
+            key_walk_mode: KeyCode::V,
+            walk_speed: 5.0,
+            run_speed: 15.0,
+            friction: 0.5,
+            pitch: 0.0,
+            yaw: 0.0,
+            velocity: Vec3::ZERO,
+            walk_mode: false,
+            eye_height: 1.7,
+            jump_height: 1.0,
+            gravity: 9.81,
+            vertical_velocity: 0.0,
+            zoom_fov: 45.0,
+        }
+    }
+}
+
+impl CameraController {
+    /// The bindings of the controller, as (action, key name) pairs.
+    pub fn key_bindings(&self) -> Vec<(String, String)> {
+        let mut bindings = [
+            ("move forward", self.key_forward),
+            ("move back", self.key_back),
+            ("move left", self.key_left),
+            ("move right", self.key_right),
+            ("move up, or jump when walking", self.key_up),
+            ("move down", self.key_down),
+            ("run", self.key_run),
+            ("switch between flying and walking", self.key_walk_mode),
+        ]
+        .into_iter()
+        .map(|(action, key)| {
+            let name = match keycode_name(key) {
+                "" => format!("{key:?}"),
+                name => name.to_string(),
+            };
+            (action.to_string(), name)
+        })
+        .collect::<Vec<_>>();
+        bindings.push((
+            "hold to look around, or click to lock the cursor (see camera panel)".to_string(),
+            format!("{:?} click", self.key_enable_mouse).to_lowercase(),
+        ));
+        bindings
+    }
+
+    /// Rotation of the camera from `pitch` and `yaw`, without roll.
+    pub fn rotation(&self) -> Quat {
+        Quat::from_euler(EulerRot::ZYX, 0.0, self.yaw, self.pitch)
+    }
+}
+
+/// How the mouse rotates a [`CameraController`].
+#[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MouseLookMode {
+    /// Look around while `key_enable_mouse` is held
+    #[default]
+    HoldToLook,
+    /// Clicking `key_enable_mouse` hides and locks the cursor to look around, clicking again
+    /// releases it
+    ToggleLock,
+}
+
+/// Stops a [`CameraController`] from flying through the scene geometry, to add on the same entity.
+///
+/// The camera is a sphere of `radius` that slides along the bounding boxes of the meshes. Meshes
+/// that don't cast shadows, like the light fixtures or the rain, are ignored.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct CollisionAvoidance {
+    pub radius: f32,
+}
+
+impl Default for CollisionAvoidance {
+    fn default() -> Self {
+        Self { radius: 0.3 }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn camera_controller(
+    time: Res<Time>,
+    mut mouse_events: EventReader<MouseMotion>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    key_input: Res<Input<KeyCode>>,
+    mut windows: ResMut<Windows>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut CameraController,
+            Option<&CollisionAvoidance>,
+            Option<&mut Projection>,
+        ),
+        With<Camera>,
+    >,
+    colliders: Query<(&Aabb, &GlobalTransform), (Without<Camera>, Without<NotShadowCaster>)>,
+) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("camera_controller");
+    let dt = time.delta_seconds();
+
+    // Only the enabled controllers move their camera
+    for (mut transform, mut options, collision, projection) in query.iter_mut() {
+        if !options.initialized {
+            let (_roll, yaw, pitch) = transform.rotation.to_euler(EulerRot::ZYX);
+            options.yaw = yaw;
+            options.pitch = pitch;
+            if let Some(Projection::Perspective(perspective)) = projection.as_deref() {
+                options.zoom_fov = perspective.fov.to_degrees();
+            }
+            options.initialized = true;
+        }
+        if !options.enabled {
+            continue;
+        }
+
+        if key_input.just_pressed(options.key_walk_mode) {
+            options.walk_mode = !options.walk_mode;
+            options.vertical_velocity = 0.0;
+            info!("camera walk mode: {}", options.walk_mode);
+        }
+
+        // Handle key input. The shortcuts with the modifier don't move the camera, unless the
+        // modifier is also the run key, as shift on the web
+        let mut axis_input = Vec3::ZERO;
+        let shortcut = key_input.any_pressed(platform::SHORTCUT_MODIFIER)
+            && !platform::SHORTCUT_MODIFIER.contains(&options.key_run);
+        if !shortcut {
+            if key_input.pressed(options.key_forward) {
+                axis_input.z += 1.0;
+            }
+            if key_input.pressed(options.key_back) {
+                axis_input.z -= 1.0;
+            }
+            if key_input.pressed(options.key_right) {
+                axis_input.x += 1.0;
+            }
+            if key_input.pressed(options.key_left) {
+                axis_input.x -= 1.0;
+            }
+            if !options.walk_mode {
+                if key_input.pressed(options.key_up) {
+                    axis_input.y += 1.0;
+                }
+                if key_input.pressed(options.key_down) {
+                    axis_input.y -= 1.0;
+                }
+            }
+        }
+
+        // Apply movement update
+        if axis_input != Vec3::ZERO {
+            let max_speed = if key_input.pressed(options.key_run) {
+                options.run_speed
+            } else {
+                options.walk_speed
+            };
+            options.velocity = axis_input.normalize() * max_speed;
+        } else {
+            let friction = options.friction.clamp(0.0, 1.0);
+            options.velocity *= 1.0 - friction;
+            if options.velocity.length_squared() < 1e-6 {
+                options.velocity = Vec3::ZERO;
+            }
+        }
+        // The rotation of the transform is only updated at the end of the frame
+        let rotation = options.rotation();
+        let (forward, right) = (rotation * Vec3::NEG_Z, rotation * Vec3::X);
+        let (forward, right) = if options.walk_mode {
+            // Looking up or down doesn't move a walker off the floor
+            let flatten =
+                |direction: Vec3| (direction * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero();
+            (flatten(forward), flatten(right))
+        } else {
+            (forward, right)
+        };
+        let motion = options.velocity.x * dt * right
+            + options.velocity.y * dt * Vec3::Y
+            + options.velocity.z * dt * forward;
+        transform.translation = match collision {
+            Some(collision) if motion != Vec3::ZERO => {
+                let boxes: Vec<_> = colliders
+                    .iter()
+                    .map(|(aabb, transform)| world_bounds(aabb, transform, collision.radius))
+                    .collect();
+                slide(transform.translation, motion, &boxes)
+            }
+            _ => transform.translation + motion,
+        };
+
+        if options.walk_mode {
+            let boxes: Vec<_> = colliders
+                .iter()
+                .map(|(aabb, transform)| world_bounds(aabb, transform, 0.0))
+                .collect();
+            let ground = floor_height(transform.translation - Vec3::Y * options.eye_height, &boxes)
+                + options.eye_height;
+            let on_ground = transform.translation.y <= ground + 1e-3;
+            if on_ground && !shortcut && key_input.just_pressed(options.key_up) {
+                options.vertical_velocity = (2.0 * options.gravity * options.jump_height).sqrt();
+            }
+            options.vertical_velocity -= options.gravity * dt;
+            transform.translation.y += options.vertical_velocity * dt;
+            if transform.translation.y <= ground {
+                transform.translation.y = ground;
+                options.vertical_velocity = 0.0;
+            }
+        }
+
+        // Handle mouse input
+        let lock = match options.mouse_look_mode {
+            MouseLookMode::HoldToLook => false,
+            MouseLookMode::ToggleLock => {
+                options.cursor_locked ^ mouse_button_input.just_pressed(options.key_enable_mouse)
+            }
+        };
+        if lock != options.cursor_locked {
+            options.cursor_locked = lock;
+            if let Some(window) = windows.get_primary_mut() {
+                window.set_cursor_grab_mode(if lock {
+                    CursorGrabMode::Locked
+                } else {
+                    CursorGrabMode::None
+                });
+                window.set_cursor_visibility(!lock);
+            }
+        }
+        let looking = match options.mouse_look_mode {
+            MouseLookMode::HoldToLook => mouse_button_input.pressed(options.key_enable_mouse),
+            MouseLookMode::ToggleLock => options.cursor_locked,
+        };
+        let mut mouse_delta = Vec2::ZERO;
+        if looking {
+            for mouse_event in mouse_events.iter() {
+                mouse_delta += mouse_event.delta;
+            }
+        }
+
+        if mouse_delta != Vec2::ZERO {
+            // Apply look update
+            let (pitch, yaw) = (
+                (options.pitch
+                    - mouse_delta.y
+                        * options.vertical_sensitivity_ratio
+                        * options.sensitivity
+                        * dt)
+                    .clamp(
+                        -0.99 * std::f32::consts::FRAC_PI_2,
+                        0.99 * std::f32::consts::FRAC_PI_2,
+                    ),
+                options.yaw - mouse_delta.x * options.sensitivity * dt,
+            );
+            options.pitch = pitch;
+            options.yaw = yaw;
+        }
+
+        // Handle zoom, scrolling up narrows the field of view. With the modifier, the minimap is
+        // zoomed instead, and with alt the exposure is changed
+        let scroll: f32 = wheel_events
+            .iter()
+            .filter(|_| {
+                !key_input.any_pressed(platform::SHORTCUT_MODIFIER)
+                    && !key_input.any_pressed([KeyCode::LAlt, KeyCode::RAlt])
+            })
+            .map(|event| match event.unit {
+                MouseScrollUnit::Line => event.y,
+                // Roughly the height of a line
+                MouseScrollUnit::Pixel => event.y / 20.0,
+            })
+            .sum();
+        if scroll != 0.0 {
+            let zoom_fov = (options.zoom_fov - scroll * FOV_PER_LINE).clamp(MIN_FOV, MAX_FOV);
+            if zoom_fov != options.zoom_fov {
+                options.zoom_fov = zoom_fov;
+                info!("camera fov: {zoom_fov:.0}°");
+            }
+        }
+        if let Some(mut projection) = projection {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                let target = options.zoom_fov.to_radians();
+                if (perspective.fov - target).abs() > 1e-4 {
+                    let t = (FOV_SMOOTHING * dt).min(1.0);
+                    perspective.fov += (target - perspective.fov) * t;
+                }
+            }
+        }
+    }
+}
+
+// Rotate the cameras from the pitch and yaw of their controller. Disabled controllers leave the
+// camera to other systems, like the cinematic path, and pick up its rotation when enabled again
+fn apply_camera_rotation(mut cameras: Query<(&mut Transform, &CameraController)>) {
+    for (mut transform, controller) in cameras.iter_mut() {
+        if !controller.enabled || !controller.initialized {
+            continue;
+        }
+        let rotation = controller.rotation();
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+// Range of the field of view, in degrees
+const MIN_FOV: f32 = 20.0;
+const MAX_FOV: f32 = 120.0;
+// Degrees of field of view per line scrolled
+const FOV_PER_LINE: f32 = 5.0;
+// Fraction of the remaining field of view change done per second
+const FOV_SMOOTHING: f32 = 8.0;
+
+// Height a walker can step up onto, in meters
+const STEP_HEIGHT: f32 = 0.5;
+
+// Top of the highest box below the feet, found by casting a ray down. The ground is at 0 otherwise
+fn floor_height(feet: Vec3, boxes: &[(Vec3, Vec3)]) -> f32 {
+    boxes
+        .iter()
+        .filter(|(min, max)| {
+            feet.x >= min.x
+                && feet.x <= max.x
+                && feet.z >= min.z
+                && feet.z <= max.z
+                && max.y <= feet.y + STEP_HEIGHT
+        })
+        .map(|(_, max)| max.y)
+        .fold(0.0, f32::max)
+}
+
+// Move from `start` by `motion`, sliding along the boxes that are hit on the way
+fn slide(start: Vec3, motion: Vec3, boxes: &[(Vec3, Vec3)]) -> Vec3 {
+    let mut position = start;
+    let mut remaining = motion;
+    // One iteration per axis is enough to slide into a corner
+    for _ in 0..3 {
+        if remaining == Vec3::ZERO {
+            break;
+        }
+        let hit = boxes
+            .iter()
+            // Ignore boxes the camera is already in, so that it can't get stuck in large meshes
+            .filter(|(min, max)| !(position.cmpgt(*min).all() && position.cmplt(*max).all()))
+            .filter_map(|(min, max)| ray_box(position, remaining, *min, *max))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match hit {
+            Some((t, normal)) => {
+                // Stop just before the surface, and keep the motion along it
+                position += remaining * t + normal * 1e-3;
+                remaining *= 1.0 - t;
+                remaining -= normal * remaining.dot(normal);
+            }
+            None => {
+                position += remaining;
+                break;
+            }
+        }
+    }
+    position
+}
+
+// Fraction of `motion` after which the ray enters the box, with the normal of the face hit
+fn ray_box(origin: Vec3, motion: Vec3, min: Vec3, max: Vec3) -> Option<(f32, Vec3)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = 1.0_f32;
+    let mut normal = Vec3::ZERO;
+    for axis in 0..3 {
+        if motion[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let (near, far, sign) = if motion[axis] > 0.0 {
+            (min[axis], max[axis], -1.0)
+        } else {
+            (max[axis], min[axis], 1.0)
+        };
+        let t_near = (near - origin[axis]) / motion[axis];
+        let t_far = (far - origin[axis]) / motion[axis];
+        if t_near > t_enter {
+            t_enter = t_near;
+            normal = Vec3::ZERO;
+            normal[axis] = sign;
+        }
+        t_exit = t_exit.min(t_far);
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+    // Entering behind the origin, it started in the box. A ray starting on a face hits it at 0
+    (t_enter >= 0.0 && normal != Vec3::ZERO).then_some((t_enter, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNIT_BOX: (Vec3, Vec3) = (Vec3::ZERO, Vec3::ONE);
+
+    #[test]
+    fn ray_enters_box() {
+        let (min, max) = UNIT_BOX;
+        let hit = ray_box(
+            Vec3::new(-1.0, 0.5, 0.5),
+            Vec3::new(2.0, 0.0, 0.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, Some((0.5, Vec3::NEG_X)));
+        let hit = ray_box(
+            Vec3::new(0.5, 3.0, 0.5),
+            Vec3::new(0.0, -4.0, 0.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, Some((0.5, Vec3::Y)));
+    }
+
+    #[test]
+    fn ray_stopping_before_box() {
+        let (min, max) = UNIT_BOX;
+        let hit = ray_box(
+            Vec3::new(-1.0, 0.5, 0.5),
+            Vec3::new(0.5, 0.0, 0.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn ray_going_away_from_box() {
+        let (min, max) = UNIT_BOX;
+        let hit = ray_box(
+            Vec3::new(-1.0, 0.5, 0.5),
+            Vec3::new(-1.0, 0.0, 0.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn ray_parallel_to_box() {
+        let (min, max) = UNIT_BOX;
+        // Beside the box, along its faces
+        let hit = ray_box(
+            Vec3::new(-1.0, 2.0, 0.5),
+            Vec3::new(3.0, 0.0, 0.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, None);
+        let hit = ray_box(
+            Vec3::new(0.5, 0.5, -1.0),
+            Vec3::new(0.0, 0.0, 3.0),
+            min,
+            max,
+        );
+        assert_eq!(hit, Some((1.0 / 3.0, Vec3::NEG_Z)));
+    }
+
+    #[test]
+    fn ray_starting_inside_box() {
+        let (min, max) = UNIT_BOX;
+        let hit = ray_box(Vec3::splat(0.5), Vec3::new(1.0, 0.0, 0.0), min, max);
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn ray_starting_on_face() {
+        let (min, max) = UNIT_BOX;
+        let hit = ray_box(Vec3::new(0.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0), min, max);
+        assert_eq!(hit, Some((0.0, Vec3::NEG_X)));
+    }
+
+    #[test]
+    fn zero_length_ray() {
+        let (min, max) = UNIT_BOX;
+        assert_eq!(ray_box(Vec3::splat(-1.0), Vec3::ZERO, min, max), None);
+        assert_eq!(ray_box(Vec3::splat(0.5), Vec3::ZERO, min, max), None);
+    }
+
+    #[test]
+    fn slide_without_boxes() {
+        let start = Vec3::new(1.0, 2.0, 3.0);
+        let motion = Vec3::new(0.5, 0.0, -1.0);
+        assert_eq!(slide(start, motion, &[]), start + motion);
+    }
+
+    #[test]
+    fn slide_along_wall() {
+        let wall = (Vec3::new(1.0, 0.0, -5.0), Vec3::new(2.0, 3.0, 5.0));
+        let end = slide(Vec3::new(0.0, 1.0, 0.0), Vec3::new(2.0, 0.0, 1.0), &[wall]);
+        // Stopped just before the wall, the motion along it is kept
+        assert!(end.abs_diff_eq(Vec3::new(1.0 - 1e-3, 1.0, 1.0), 1e-5));
+    }
+
+    #[test]
+    fn slide_into_corner() {
+        let walls = [
+            (Vec3::new(1.0, 0.0, -5.0), Vec3::new(2.0, 3.0, 5.0)),
+            (Vec3::new(-5.0, 0.0, 1.0), Vec3::new(5.0, 3.0, 2.0)),
+        ];
+        let end = slide(Vec3::new(0.0, 1.0, 0.0), Vec3::new(2.0, 0.0, 2.0), &walls);
+        // Stopped by the second wall, on which the first one left it
+        assert!(end.abs_diff_eq(Vec3::new(1.0 - 1e-3, 1.0, 1.0 - 1e-3), 1e-5));
+    }
+
+    #[test]
+    fn slide_out_of_box() {
+        // Boxes the camera is in are ignored
+        let end = slide(
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            &[UNIT_BOX],
+        );
+        assert_eq!(end, Vec3::new(1.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn slide_without_motion() {
+        let start = Vec3::new(-1.0, 0.5, 0.5);
+        assert_eq!(slide(start, Vec3::ZERO, &[UNIT_BOX]), start);
+    }
+
+    #[test]
+    fn floor_on_the_ground() {
+        assert_eq!(floor_height(Vec3::new(0.5, 0.0, 0.5), &[]), 0.0);
+        // Next to the box
+        let feet = Vec3::new(2.0, 0.0, 0.5);
+        assert_eq!(floor_height(feet, &[(Vec3::ZERO, Vec3::splat(0.4))]), 0.0);
+    }
+
+    #[test]
+    fn floor_on_step() {
+        let steps = [
+            (Vec3::ZERO, Vec3::new(1.0, 0.2, 1.0)),
+            (Vec3::ZERO, Vec3::new(1.0, 0.4, 1.0)),
+        ];
+        assert_eq!(floor_height(Vec3::new(0.5, 0.0, 0.5), &steps), 0.4);
+    }
+
+    #[test]
+    fn floor_under_step_too_high() {
+        let table = (Vec3::ZERO, Vec3::new(1.0, 0.8, 1.0));
+        assert_eq!(floor_height(Vec3::new(0.5, 0.0, 0.5), &[table]), 0.0);
+        // Standing on it
+        assert_eq!(floor_height(Vec3::new(0.5, 0.8, 0.5), &[table]), 0.8);
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::{core_pipeline::bloom::BloomSettings, prelude::*};
+
+use crate::{camera_controller::CameraController, plugins::camera::MainCamera};
+
+// Names of the camera slots, by index
+pub const CAMERA_SLOT_NAMES: [&str; 3] = ["Street", "Bar", "Overhead"];
+
+/// Cycles between the cameras with a [`CameraSlot`] with `Tab`, showing the current one in the
+/// bottom right corner.
+///
+/// Only the active camera renders and has its [`CameraController`] enabled. The
+/// [`MainCamera`] marker moves to it, with its bloom and UI settings.
+pub struct CameraSlotsPlugin;
+
+impl Plugin for CameraSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PostStartup, setup_camera_slot_label)
+            .add_system(switch_camera_slot)
+            .add_system(update_camera_slot_label.after(switch_camera_slot));
+    }
+}
+
+/// Index of a camera in [`CAMERA_SLOT_NAMES`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CameraSlot(pub u8);
+
+impl CameraSlot {
+    pub fn name(&self) -> &'static str {
+        CAMERA_SLOT_NAMES
+            .get(self.0 as usize)
+            .copied()
+            .unwrap_or("Camera")
+    }
+}
+
+#[derive(Component)]
+struct CameraSlotLabel;
+
+fn setup_camera_slot_label(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            )
+        },
+        CameraSlotLabel,
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+fn switch_camera_slot(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut cameras: Query<(
+        Entity,
+        &CameraSlot,
+        &mut Camera,
+        Option<&mut CameraController>,
+        Option<&UiCameraConfig>,
+        Option<&BloomSettings>,
+        Option<&MainCamera>,
+    )>,
+) {
+    if !input.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let mut slots: Vec<_> = cameras
+        .iter()
+        .map(|(entity, slot, .., main)| (*slot, entity, main.is_some()))
+        .collect();
+    slots.sort_by_key(|(slot, ..)| slot.0);
+    let current = match slots.iter().position(|(.., main)| *main) {
+        Some(current) => current,
+        None => return,
+    };
+    let next = slots[(current + 1) % slots.len()].1;
+    let current = slots[current].1;
+    if next == current {
+        return;
+    }
+
+    let [mut from, mut to] = match cameras.get_many_mut([current, next]) {
+        Ok(cameras) => cameras,
+        Err(_) => return,
+    };
+    from.2.is_active = false;
+    to.2.is_active = true;
+    if let (Some(from_controller), Some(to_controller)) = (from.3.as_mut(), to.3.as_mut()) {
+        // Stays disabled during the cinematic
+        to_controller.enabled = from_controller.enabled;
+        from_controller.enabled = false;
+    }
+    let mut new_main = commands.entity(next);
+    new_main.insert(MainCamera);
+    match from.4 {
+        Some(ui) => new_main.insert(ui.clone()),
+        None => new_main.remove::<UiCameraConfig>(),
+    };
+    match from.5 {
+        Some(bloom) => new_main.insert(bloom.clone()),
+        None => new_main.remove::<BloomSettings>(),
+    };
+    commands
+        .entity(current)
+        .remove::<MainCamera>()
+        .remove::<BloomSettings>();
+    info!("camera: {} {}", to.1 .0 + 1, to.1.name());
+}
+
+fn update_camera_slot_label(
+    cameras: Query<&CameraSlot, Added<MainCamera>>,
+    mut labels: Query<&mut Text, With<CameraSlotLabel>>,
+) {
+    for slot in cameras.iter() {
+        for mut text in labels.iter_mut() {
+            text.sections[0].value = format!("Camera {}: {}", slot.0 + 1, slot.name());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{camera_controller::CameraController, plugins::camera::MainCamera};
+
+pub const CINEMATIC_PATH: &str = "cinematic.path.ron";
+
+/// Fly-through of the scene along a path of keyframes loaded from `cinematic.path.ron`.
+///
+/// Toggled with `C`. While active, the [`CameraController`] is disabled.
+pub struct CinematicPlugin;
+
+impl Plugin for CinematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<CinematicPathDescription>()
+            .add_asset_loader(CinematicPathLoader)
+            .init_resource::<CinematicPath>()
+            .init_resource::<CinematicState>()
+            .add_startup_system(load_cinematic_path)
+            .add_system(update_cinematic_path)
+            .add_system(toggle_cinematic)
+            .add_system(cinematic_camera);
+    }
+}
+
+/// Keyframes of the camera path, with the time in seconds at which each one is reached.
+#[derive(Resource, Default)]
+pub struct CinematicPath {
+    pub keyframes: Vec<(Transform, f32)>,
+}
+
+#[derive(Resource, Default)]
+pub struct CinematicState {
+    pub active: bool,
+    pub elapsed: f32,
+}
+
+#[derive(Deserialize)]
+struct KeyframeDescription {
+    translation: [f32; 3],
+    looking_at: [f32; 3],
+    time: f32,
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "ba3a1fcb-987a-4374-bc80-baf1bed38fbe"]
+struct CinematicPathDescription {
+    keyframes: Vec<KeyframeDescription>,
+}
+
+struct CinematicPathLoader;
+
+impl AssetLoader for CinematicPathLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let description = ron::de::from_bytes::<CinematicPathDescription>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(description));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["path.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct CinematicPathHandle(Handle<CinematicPathDescription>);
+
+fn load_cinematic_path(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CinematicPathHandle(asset_server.load(CINEMATIC_PATH)));
+}
+
+fn update_cinematic_path(
+    mut events: EventReader<AssetEvent<CinematicPathDescription>>,
+    descriptions: Res<Assets<CinematicPathDescription>>,
+    handle: Res<CinematicPathHandle>,
+    mut path: ResMut<CinematicPath>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: updated } | AssetEvent::Modified { handle: updated }
+                if *updated == handle.0 =>
+            {
+                if let Some(description) = descriptions.get(updated) {
+                    let mut keyframes: Vec<_> = description
+                        .keyframes
+                        .iter()
+                        .map(|keyframe| {
+                            (
+                                Transform::from_translation(keyframe.translation.into())
+                                    .looking_at(keyframe.looking_at.into(), Vec3::Y),
+                                keyframe.time,
+                            )
+                        })
+                        .collect();
+                    keyframes.sort_by(|a, b| a.1.total_cmp(&b.1));
+                    path.keyframes = keyframes;
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn toggle_cinematic(
+    input: Res<Input<KeyCode>>,
+    path: Res<CinematicPath>,
+    mut state: ResMut<CinematicState>,
+    mut controllers: Query<&mut CameraController, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::C) {
+        return;
+    }
+    if !state.active && path.keyframes.is_empty() {
+        warn!("no cinematic path loaded from {CINEMATIC_PATH}");
+        return;
+    }
+    state.active = !state.active;
+    state.elapsed = 0.0;
+    info!("cinematic mode: {}", state.active);
+    for mut controller in controllers.iter_mut() {
+        controller.enabled = !state.active;
+        // Pick up pitch and yaw from where the path left the camera
+        controller.initialized = false;
+    }
+}
+
+fn cinematic_camera(
+    time: Res<Time>,
+    path: Res<CinematicPath>,
+    mut state: ResMut<CinematicState>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !state.active {
+        return;
+    }
+    let duration = path.keyframes.last().map_or(0.0, |(_, time)| *time);
+    state.elapsed += time.delta_seconds();
+    if duration > 0.0 {
+        state.elapsed %= duration;
+    }
+    if let Some(sampled) = sample_path(&path.keyframes, state.elapsed) {
+        for mut transform in camera.iter_mut() {
+            *transform = sampled;
+        }
+    }
+}
+
+// Velocity at a keyframe, estimated from its neighbours
+fn keyframe_velocity(keyframes: &[(Transform, f32)], index: usize) -> Vec3 {
+    let previous = index.saturating_sub(1);
+    let next = (index + 1).min(keyframes.len() - 1);
+    let dt = keyframes[next].1 - keyframes[previous].1;
+    if dt <= 0.0 {
+        Vec3::ZERO
+    } else {
+        (keyframes[next].0.translation - keyframes[previous].0.translation) / dt
+    }
+}
+
+// Cubic Hermite interpolation of the translation, spherical interpolation of the rotation
+pub fn sample_path(keyframes: &[(Transform, f32)], time: f32) -> Option<Transform> {
+    match keyframes.len() {
+        0 => return None,
+        1 => return Some(keyframes[0].0),
+        _ => (),
+    }
+    let index = keyframes
+        .windows(2)
+        .position(|window| time < window[1].1)
+        .unwrap_or(keyframes.len() - 2);
+    let (start, start_time) = keyframes[index];
+    let (end, end_time) = keyframes[index + 1];
+    let dt = end_time - start_time;
+    let u = if dt > 0.0 {
+        ((time - start_time) / dt).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+
+    let (u2, u3) = (u * u, u * u * u);
+    let h00 = 2.0 * u3 - 3.0 * u2 + 1.0;
+    let h10 = u3 - 2.0 * u2 + u;
+    let h01 = -2.0 * u3 + 3.0 * u2;
+    let h11 = u3 - u2;
+    let translation = h00 * start.translation
+        + h10 * dt * keyframe_velocity(keyframes, index)
+        + h01 * end.translation
+        + h11 * dt * keyframe_velocity(keyframes, index + 1);
+
+    Some(Transform {
+        translation,
+        rotation: start.rotation.slerp(end.rotation, u),
+        scale: Vec3::ONE,
+    })
+}
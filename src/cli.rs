@@ -0,0 +1,25 @@
+// Minimal command line parsing, good enough for the few options of this demo
+
+use crate::platform;
+
+/// Value of the option `name`, given either as `--name value` or `--name=value`.
+pub fn value(name: &str) -> Option<String> {
+    let mut args = platform::args().into_iter();
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Whether the flag `name` was given.
+pub fn flag(name: &str) -> bool {
+    platform::args().iter().any(|arg| arg == name)
+}
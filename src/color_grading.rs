@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::post_processing::PostProcessingStack;
+
+/// Color grading with a 3D lookup table, cycled between none, warm and cold with `L`.
+///
+/// The grading is applied by the [`PostProcessingStack`], at the place of its
+/// [`ColorGrade`](crate::post_processing::PostProcessEffect::ColorGrade) effect.
+pub struct ColorGradingPlugin;
+
+impl Plugin for ColorGradingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_color_grading_luts)
+            .add_system(cycle_color_grading);
+    }
+}
+
+/// LUTs are 256x16 images: 16 slices of 16x16 texels side by side, indexed with red and green,
+/// one slice per level of blue.
+pub struct ColorGradingSettings {
+    pub lut_texture: Option<Handle<Image>>,
+    // Blend between the original colors at 0 and the graded colors at 1
+    pub strength: f32,
+}
+
+impl Default for ColorGradingSettings {
+    fn default() -> Self {
+        Self {
+            lut_texture: None,
+            strength: 1.0,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ColorGradingLuts {
+    warm: Handle<Image>,
+    cold: Handle<Image>,
+}
+
+fn load_color_grading_luts(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ColorGradingLuts {
+        warm: asset_server.load("luts/warm.png"),
+        cold: asset_server.load("luts/cold.png"),
+    });
+}
+
+fn cycle_color_grading(
+    input: Res<Input<KeyCode>>,
+    luts: Res<ColorGradingLuts>,
+    mut stack: ResMut<PostProcessingStack>,
+) {
+    if !input.just_pressed(KeyCode::L) {
+        return;
+    }
+    let settings = match stack.color_grading_mut() {
+        Some(settings) => settings,
+        None => {
+            info!("no color grading in the post processing stack");
+            return;
+        }
+    };
+    let (lut, name) = match &settings.lut_texture {
+        None => (Some(luts.warm.clone()), "warm"),
+        Some(lut) if *lut == luts.warm => (Some(luts.cold.clone()), "cold"),
+        Some(_) => (None, "none"),
+    };
+    settings.lut_texture = lut;
+    info!("color grading: {name}");
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+/// Keeps the color of lights with a [`ColorTemperature`] in sync with it.
+pub struct ColorTemperaturePlugin;
+
+impl Plugin for ColorTemperaturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PostUpdate, apply_color_temperature);
+    }
+}
+
+/// Color temperature of a light, in Kelvin.
+#[derive(Component, Clone, Copy, PartialEq)]
+pub struct ColorTemperature(pub f32);
+
+pub fn apply_color_temperature(
+    mut lights: Query<(&ColorTemperature, &mut PointLight), Changed<ColorTemperature>>,
+) {
+    for (temperature, mut light) in lights.iter_mut() {
+        light.color = kelvin_to_rgb(temperature.0);
+    }
+}
+
+/// Approximate color of a black body at the given temperature, from Tanner Helland's fit.
+///
+/// Valid from 1000 K to 40000 K.
+pub fn kelvin_to_rgb(k: f32) -> Color {
+    let temperature = k.clamp(1000.0, 40000.0) / 100.0;
+
+    let red = if temperature <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (temperature - 60.0).powf(-0.13320476)
+    };
+    let green = if temperature <= 66.0 {
+        99.4708 * temperature.ln() - 161.11957
+    } else {
+        288.12216 * (temperature - 60.0).powf(-0.075514846)
+    };
+    let blue = if temperature >= 66.0 {
+        255.0
+    } else if temperature <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (temperature - 10.0).ln() - 305.0448
+    };
+
+    Color::rgb(
+        red.clamp(0.0, 255.0) / 255.0,
+        green.clamp(0.0, 255.0) / 255.0,
+        blue.clamp(0.0, 255.0) / 255.0,
+    )
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+use crate::{camera_controller::CameraController, platform, plugins::camera::MainCamera};
+
+/// Lists the controls on screen, toggled with `F2`: the [`shortcuts`] and the bindings of the
+/// [`CameraController`] of the [`MainCamera`], which can be changed in `keybinds.toml`.
+pub struct ControlsOverlayPlugin;
+
+impl Plugin for ControlsOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_controls_overlay)
+            .add_system(toggle_controls_overlay);
+    }
+}
+
+/// The keyboard and mouse shortcuts handled by the plugins, as (action, key name) pairs.
+pub fn shortcuts() -> Vec<(String, String)> {
+    // Ctrl on native, shift on the web
+    let modifier = platform::SHORTCUT_MODIFIER_NAME;
+    [
+        ("toggle point light shadows", "spacebar".to_string()),
+        ("toggle sun and moon shadows", "shift + spacebar".to_string()),
+        ("toggle bloom", "b".to_string()),
+        ("enable / disable the ceiling lights", "1".to_string()),
+        ("enable / disable the wall lights", "2".to_string()),
+        ("enable / disable the lanterns", "3".to_string()),
+        ("enable / disable the streetlights", "4".to_string()),
+        ("enable / disable the sign spotlight", "5".to_string()),
+        ("show / hide the wine glasses", "6".to_string()),
+        ("pause / resume the day/night cycle", "t".to_string()),
+        ("slow down / speed up the day/night cycle", "[ / ]".to_string()),
+        ("snap to the night preset, all lights on", "n".to_string()),
+        ("snap to the day preset, all lights off", "shift + n".to_string()),
+        ("start / stop the cinematic fly-through", "c".to_string()),
+        (
+            "switch between the street, bar and overhead cameras",
+            "tab".to_string(),
+        ),
+        ("show / hide the HUD", "h".to_string()),
+        (
+            "switch between the procedural sky and the HDRI skybox",
+            format!("{modifier} + h"),
+        ),
+        ("start / stop the rain", "r".to_string()),
+        (
+            "switch between the day/night cycle and the real sun position in Paris",
+            format!("{modifier} + r"),
+        ),
+        ("cycle between material presets", "m".to_string()),
+        ("show / hide the minimap", "shift + m".to_string()),
+        ("zoom the minimap", format!("{modifier} + mouse wheel")),
+        ("show / hide the point light gizmos", "g".to_string()),
+        ("show / hide the lens flares", "f".to_string()),
+        ("show / hide the camera and light settings panels", "p".to_string()),
+        ("zoom in / out", "mouse wheel".to_string()),
+        (
+            "raise / lower the exposure, shown as an EV100",
+            "alt + mouse wheel".to_string(),
+        ),
+        ("show / hide the world axes", "x".to_string()),
+        ("see through the walls of the bistro", "shift + x".to_string()),
+        (
+            "cycle between no color grading, warm and cold",
+            "l".to_string(),
+        ),
+        ("show / hide the security camera", "k".to_string()),
+        (
+            "grow / shrink the sun and moon shadow area",
+            format!("{modifier} + up / down"),
+        ),
+        (
+            "show / hide the sun and moon shadow maps, and the shadow volumes of the lights",
+            format!("{modifier} + d"),
+        ),
+        ("save a camera bookmark", format!("{modifier} + 1..9")),
+        ("restore a camera bookmark", "alt + 1..9".to_string()),
+        ("show / hide the entity inspector", "f1".to_string()),
+        ("show / hide this list of the controls", "f2".to_string()),
+        ("save a screenshot to the screenshots folder", "f12".to_string()),
+        (
+            "show / hide the ambient occlusion baked in the vertex colors",
+            format!("{modifier} + a"),
+        ),
+        (
+            "get informations on the lights and the meshes they light, the scene bounds, the nearby annotations and the quality tier",
+            "i".to_string(),
+        ),
+    ]
+    .into_iter()
+    .map(|(action, key)| (action.to_string(), key))
+    .collect()
+}
+
+// One control per line, as printed in the log at startup
+pub fn format_controls(controls: &[(String, String)]) -> Vec<String> {
+    controls
+        .iter()
+        .map(|(action, key)| format!("{key} - {action}"))
+        .collect()
+}
+
+#[derive(Component)]
+struct ControlsOverlay;
+
+#[derive(Component)]
+struct ControlsOverlayText;
+
+fn setup_controls_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            ControlsOverlay,
+        ))
+        .with_children(|overlay| {
+            overlay
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                    ..default()
+                })
+                .with_children(|panel| {
+                    panel.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                            },
+                        ),
+                        ControlsOverlayText,
+                    ));
+                });
+        });
+}
+
+// The list is built again each time it is shown, with the current camera bindings
+fn toggle_controls_overlay(
+    input: Res<Input<KeyCode>>,
+    mut overlay: Query<&mut Visibility, With<ControlsOverlay>>,
+    mut text: Query<&mut Text, With<ControlsOverlayText>>,
+    controllers: Query<&CameraController, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::F2) {
+        return;
+    }
+    for mut visibility in overlay.iter_mut() {
+        visibility.is_visible = !visibility.is_visible;
+        if !visibility.is_visible {
+            continue;
+        }
+        let mut lines = vec!["Controls".to_string()];
+        lines.extend(format_controls(&shortcuts()));
+        if let Ok(controller) = controllers.get_single() {
+            lines.push(String::new());
+            lines.push("Camera".to_string());
+            lines.extend(format_controls(&controller.key_bindings()));
+        }
+        for mut text in text.iter_mut() {
+            text.sections[0].value = lines.join("\n");
+        }
+    }
+}
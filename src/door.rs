@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::camera::MainCamera,
+    simulation_clock::{ShouldSkipSimulation, SimulationClock, SimulationClockSystem},
+};
+
+/// Opens the [`Door`]s when the camera gets close to them, and closes them once it leaves, at the
+/// pace of the [`SimulationClock`].
+pub struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(door_animation.after(SimulationClockSystem));
+    }
+}
+
+/// A door from the scenes, rotating around its hinge.
+///
+/// The hinge and the center are in the local space of the door, as the meshes of the scenes are
+/// not transformed to their place.
+#[derive(Component)]
+pub struct Door {
+    pub hinge: Vec3,
+    // Normalized
+    pub hinge_axis: Vec3,
+    // In radians
+    pub open_angle: f32,
+    // Fraction of the opening done per second
+    pub speed: f32,
+    // Distance from the camera to the center of the door below which it opens, in meters
+    pub trigger_distance: f32,
+    pub center: Vec3,
+    // From 0 when closed to 1 when open
+    pub openness: f32,
+    // Transform of the door when closed, read on the first update
+    closed: Option<Transform>,
+}
+
+impl Door {
+    pub fn new(hinge: Vec3, center: Vec3) -> Self {
+        Self {
+            hinge,
+            hinge_axis: Vec3::Y,
+            open_angle: std::f32::consts::FRAC_PI_2,
+            speed: 1.0,
+            trigger_distance: 3.0,
+            center,
+            openness: 0.0,
+            closed: None,
+        }
+    }
+}
+
+fn door_animation(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut doors: Query<(&mut Door, &mut Transform, &GlobalTransform)>,
+) {
+    if skip.0 {
+        return;
+    }
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    for (mut door, mut transform, global_transform) in doors.iter_mut() {
+        let closed = *door.closed.get_or_insert(*transform);
+        let distance = global_transform
+            .transform_point(door.center)
+            .distance(camera);
+        let step = door.speed * clock.delta;
+        let openness = if distance < door.trigger_distance {
+            (door.openness + step).min(1.0)
+        } else {
+            (door.openness - step).max(0.0)
+        };
+        if openness == door.openness {
+            continue;
+        }
+        door.openness = openness;
+
+        // Rotate around the hinge, in the local space of the closed door
+        let rotation = Quat::from_axis_angle(door.hinge_axis, door.open_angle * openness);
+        *transform = closed.mul_transform(Transform {
+            translation: door.hinge - rotation * door.hinge,
+            rotation,
+            scale: Vec3::ONE,
+        });
+    }
+}
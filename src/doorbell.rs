@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+
+use crate::triggers::{DoorBell, TriggerEvent, TriggerSystem};
+
+/// Plays `assets/audio/bell.ogg` when the camera enters a [`DoorBell`] trigger volume.
+///
+/// Needs the `audio` feature.
+pub struct DoorBellPlugin;
+
+impl Plugin for DoorBellPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_doorbell)
+            .add_system(doorbell_listener.after(TriggerSystem));
+    }
+}
+
+#[derive(Resource)]
+struct DoorBellSound(Handle<AudioSource>);
+
+fn setup_doorbell(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DoorBellSound(asset_server.load("audio/bell.ogg")));
+}
+
+fn doorbell_listener(
+    mut events: EventReader<TriggerEvent>,
+    audio: Res<Audio>,
+    sound: Res<DoorBellSound>,
+    bells: Query<(), With<DoorBell>>,
+) {
+    for TriggerEvent(entity) in events.iter() {
+        if bells.contains(*entity) {
+            audio.play(sound.0.clone());
+        }
+    }
+}
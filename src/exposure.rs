@@ -0,0 +1,86 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+use crate::post_processing::PostProcessingStack;
+
+// Change of the EV100 per line scrolled
+const EV100_PER_LINE: f32 = 0.5;
+const MIN_EV100: f32 = 6.0;
+const MAX_EV100: f32 = 20.0;
+// EV100 at which the colors are tonemapped unscaled, the look the lights were tuned with
+const NEUTRAL_EV100: f32 = 12.0;
+
+/// Exposure of the main camera as an EV100, changed by scrolling with `Alt`, scrolling up giving
+/// a brighter image. The wheel alone zooms the camera.
+///
+/// Bevy 0.9 has no camera exposure, the EV100 is mapped to the exposure of the tonemapping of the
+/// [`PostProcessingStack`], each step of 1 halving or doubling the light. At 6, the street at
+/// noon is blown out to white and the night looks like dusk. At 20, noon looks like an overcast
+/// evening and at night only the lamps themselves can be seen.
+pub struct ExposurePlugin;
+
+impl Plugin for ExposurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExposureSettings>()
+            .add_system(exposure_input)
+            .add_system(apply_exposure.after(exposure_input));
+    }
+}
+
+#[derive(Resource)]
+pub struct ExposureSettings {
+    // From 6 to 20, higher is darker
+    pub ev100: f32,
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            ev100: NEUTRAL_EV100,
+        }
+    }
+}
+
+impl ExposureSettings {
+    /// Factor the colors are multiplied with before being tonemapped.
+    pub fn exposure(&self) -> f32 {
+        2.0_f32.powf(NEUTRAL_EV100 - self.ev100)
+    }
+}
+
+fn exposure_input(
+    input: Res<Input<KeyCode>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut settings: ResMut<ExposureSettings>,
+) {
+    if !input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]) {
+        return;
+    }
+    let scroll: f32 = wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Roughly the height of a line
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if scroll == 0.0 {
+        return;
+    }
+    let ev100 = (settings.ev100 - scroll * EV100_PER_LINE).clamp(MIN_EV100, MAX_EV100);
+    if ev100 != settings.ev100 {
+        settings.ev100 = ev100;
+        info!("exposure: EV100 {ev100:.1}");
+    }
+}
+
+fn apply_exposure(settings: Res<ExposureSettings>, mut stack: ResMut<PostProcessingStack>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(tonemap) = stack.tonemap_mut() {
+        tonemap.exposure = settings.exposure();
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::{
+    pbr::ClusterConfig,
+    prelude::*,
+    render::primitives::{Frustum, Sphere},
+};
+
+use crate::plugins::camera::MainCamera;
+
+/// Keeps the nearest point lights outside of the view of the main camera in the lights sent to
+/// the GPU, so that they don't pop in when the camera turns around.
+///
+/// Bevy only keeps the lights that are in a cluster of a camera, and the list of visible lights
+/// can't be changed from outside of `bevy_pbr`. Each kept light gets a "ghost" camera instead:
+/// never active, with no visible entities and a single cluster, looking from the main camera at
+/// the light, which then stays in a cluster.
+pub struct GhostLightsPlugin;
+
+impl Plugin for GhostLightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostLightSettings>()
+            .add_system(spawn_ghost_light_views)
+            .add_system(update_ghost_light_views.after(spawn_ghost_light_views));
+    }
+}
+
+#[derive(Resource)]
+pub struct GhostLightSettings {
+    // Number of lights outside of the view that are kept, each has its own ghost camera
+    pub count: usize,
+}
+
+impl Default for GhostLightSettings {
+    fn default() -> Self {
+        Self { count: 4 }
+    }
+}
+
+// A camera that is never rendered, making Bevy think a light is in view
+#[derive(Component)]
+struct GhostLightView;
+
+fn spawn_ghost_light_views(
+    mut commands: Commands,
+    settings: Res<GhostLightSettings>,
+    views: Query<Entity, With<GhostLightView>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for entity in views.iter() {
+        commands.entity(entity).despawn();
+    }
+    for _ in 0..settings.count {
+        commands.spawn((
+            Camera {
+                is_active: false,
+                ..default()
+            },
+            Projection::from(PerspectiveProjection::default()),
+            Frustum::default(),
+            TransformBundle::default(),
+            // Without cluster config, the view has no lights
+            ClusterConfig::None,
+            GhostLightView,
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_ghost_light_views(
+    settings: Res<GhostLightSettings>,
+    main_camera: Query<(&GlobalTransform, &Frustum), With<MainCamera>>,
+    lights: Query<(Entity, &GlobalTransform, &PointLight, &ComputedVisibility)>,
+    mut views: Query<
+        (&mut Transform, &mut Projection, &mut ClusterConfig),
+        (With<GhostLightView>, Without<MainCamera>),
+    >,
+    mut nearest: Local<Vec<(f32, Vec3)>>,
+) {
+    let (eye, frustum) = match main_camera.get_single() {
+        Ok((transform, frustum)) => (transform.translation(), frustum),
+        Err(_) => return,
+    };
+
+    // Lights that are on, and whose range doesn't reach into the view
+    nearest.clear();
+    nearest.extend(
+        lights
+            .iter()
+            .filter(|(_, _, light, visibility)| visibility.is_visible() && light.intensity > 0.0)
+            .filter_map(|(_, transform, light, _)| {
+                let position = transform.translation();
+                let sphere = Sphere {
+                    center: position.into(),
+                    radius: light.range,
+                };
+                (!frustum.intersects_sphere(&sphere, true))
+                    .then(|| (position.distance(eye), position))
+            }),
+    );
+    nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+    nearest.truncate(settings.count);
+
+    let mut lights = nearest.iter();
+    for (mut transform, mut projection, mut config) in views.iter_mut() {
+        match lights.next() {
+            Some((distance, position)) => {
+                // A narrow view ending just past the light, so that it doesn't keep other lights
+                *transform = Transform::from_translation(eye).looking_at(*position, Vec3::Y);
+                *projection = Projection::from(PerspectiveProjection {
+                    fov: (2.0 * (0.5 / distance.max(0.5)).atan()).max(1.0_f32.to_radians()),
+                    aspect_ratio: 1.0,
+                    near: 0.1,
+                    far: distance + 0.5,
+                });
+                if !matches!(*config, ClusterConfig::Single) {
+                    *config = ClusterConfig::Single;
+                }
+            }
+            None => {
+                if !matches!(*config, ClusterConfig::None) {
+                    *config = ClusterConfig::None;
+                }
+            }
+        }
+    }
+}
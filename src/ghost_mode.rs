@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+// Alpha of the walls while they are see-through
+const GHOST_ALPHA: f32 = 0.15;
+
+/// Makes the walls matched by the `Ghost` rules of `bistro.fixes.ron` see-through with `Shift+X`,
+/// to show the layout of the interior from outside. `X` is left to the axes.
+///
+/// The materials of the walls are changed, so other meshes sharing them are see-through too.
+pub struct GhostModePlugin;
+
+impl Plugin for GhostModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GhostMode>()
+            .add_system(toggle_ghost_mode)
+            .add_system(apply_ghost_mode.after(toggle_ghost_mode));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GhostMode {
+    pub enabled: bool,
+}
+
+/// Alpha of the material of an opaque wall in the scene, restored when leaving the ghost mode.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct OriginalAlpha(pub f32);
+
+fn toggle_ghost_mode(input: Res<Input<KeyCode>>, mut mode: ResMut<GhostMode>) {
+    if input.just_pressed(KeyCode::X) && input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        mode.enabled = !mode.enabled;
+        info!("ghost mode: {}", mode.enabled);
+    }
+}
+
+// Every wall when toggled, and the walls spawned since while enabled, as when the exterior is
+// streamed back in
+fn apply_ghost_mode(
+    mode: Res<GhostMode>,
+    walls: Query<(
+        &Handle<StandardMaterial>,
+        &OriginalAlpha,
+        ChangeTrackers<OriginalAlpha>,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (handle, original, tracker) in walls.iter() {
+        if !(mode.is_changed() || mode.enabled && tracker.is_added()) {
+            continue;
+        }
+        let material = match materials.get_mut(handle) {
+            Some(material) => material,
+            None => continue,
+        };
+        if mode.enabled {
+            material.base_color.set_a(GHOST_ALPHA);
+            material.alpha_mode = AlphaMode::Blend;
+        } else {
+            material.base_color.set_a(original.0);
+            material.alpha_mode = AlphaMode::Opaque;
+        }
+    }
+}
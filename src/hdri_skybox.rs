@@ -0,0 +1,304 @@
+use bevy::{
+    asset::LoadState,
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, Extent3d, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat, TextureViewDescriptor,
+            TextureViewDimension,
+        },
+        texture::ImageSampler,
+    },
+};
+
+use crate::{
+    platform,
+    plugins::camera::MainCamera,
+    sky::{SkyDome, SKY_RADIUS},
+};
+
+pub const HDRI_SKYBOX_SHADER_PATH: &str = "shaders/hdri_skybox.wgsl";
+// Exposure of the skybox when the sun is down
+pub const HDRI_NIGHT_EXPOSURE: f32 = 0.02;
+// Largest size of the faces of the cube map, in pixels
+const MAX_FACE_SIZE: usize = 1024;
+
+/// Background from an equirectangular `.hdr` image, shown instead of the procedural sky with
+/// `Ctrl+H`.
+///
+/// The image is optional, and converted to a cube map once loaded. Like the procedural sky, it is
+/// drawn from the inside of a sphere following the [`MainCamera`], and its exposure follows the
+/// sun over the day/night cycle.
+pub struct HdriSkyboxPlugin;
+
+impl Plugin for HdriSkyboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<HdriSkyboxMaterial>::default())
+            .init_resource::<HdriSkybox>()
+            .add_startup_system(load_hdri_skybox)
+            .add_system(spawn_hdri_skybox)
+            .add_system(toggle_hdri_skybox)
+            .add_system(update_hdri_skybox.after(toggle_hdri_skybox));
+    }
+}
+
+#[derive(Resource)]
+pub struct HdriSkybox {
+    // In the assets folder
+    pub path: &'static str,
+    // Around the vertical axis
+    pub rotation_degrees: f32,
+    pub enabled: bool,
+    // Set by the day/night cycle
+    pub exposure: f32,
+}
+
+impl Default for HdriSkybox {
+    fn default() -> Self {
+        Self {
+            path: "hdri/sky.hdr",
+            rotation_degrees: 0.0,
+            enabled: false,
+            exposure: 1.0,
+        }
+    }
+}
+
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "beeeb039-b04b-4084-8d6d-926cacf3c6ab"]
+pub struct HdriSkyboxMaterial {
+    // 32 bit floats can't be filtered everywhere
+    #[texture(0, dimension = "cube", filterable = false)]
+    #[sampler(1, sampler_type = "non_filtering")]
+    cube_map: Handle<Image>,
+    #[uniform(2)]
+    exposure: f32,
+    // In radians
+    #[uniform(3)]
+    rotation: f32,
+}
+
+impl Material for HdriSkyboxMaterial {
+    fn fragment_shader() -> ShaderRef {
+        HDRI_SKYBOX_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Seen from the inside
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+#[derive(Component)]
+struct HdriSkyDome;
+
+// The equirectangular image, until it is converted
+#[derive(Resource)]
+struct HdriSkyboxSource(Handle<Image>);
+
+fn load_hdri_skybox(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    skybox: Res<HdriSkybox>,
+) {
+    // Files fetched over HTTP on the web can't be checked, failing to load is logged instead
+    if !cfg!(target_arch = "wasm32")
+        && !asset_server
+            .asset_io()
+            .is_file(std::path::Path::new(skybox.path))
+    {
+        info!("no HDRI skybox in assets/{}", skybox.path);
+        return;
+    }
+    commands.insert_resource(HdriSkyboxSource(asset_server.load(skybox.path)));
+}
+
+fn spawn_hdri_skybox(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    skybox: Res<HdriSkybox>,
+    source: Option<Res<HdriSkyboxSource>>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<HdriSkyboxMaterial>>,
+) {
+    let source = match source {
+        Some(source) => source,
+        None => return,
+    };
+    let image = match images.get(&source.0) {
+        Some(image) => image,
+        None => {
+            if asset_server.get_load_state(&source.0) == LoadState::Failed {
+                commands.remove_resource::<HdriSkyboxSource>();
+            }
+            return;
+        }
+    };
+    // The source is freed with its handle
+    commands.remove_resource::<HdriSkyboxSource>();
+    if image.texture_descriptor.format != TextureFormat::Rgba32Float {
+        warn!("the HDRI skybox in assets/{} is not a .hdr", skybox.path);
+        return;
+    }
+    let cube_map = equirectangular_to_cube_map(image);
+    let cube_map = images.add(cube_map);
+
+    commands.spawn((
+        MaterialMeshBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: SKY_RADIUS,
+                sectors: 64,
+                stacks: 32,
+            })),
+            material: materials.add(HdriSkyboxMaterial {
+                cube_map,
+                exposure: skybox.exposure,
+                rotation: skybox.rotation_degrees.to_radians(),
+            }),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        NotShadowCaster,
+        NotShadowReceiver,
+        Name::new("HDRI skybox"),
+        HdriSkyDome,
+    ));
+    info!("HDRI skybox loaded from assets/{}", skybox.path);
+}
+
+// The faces in the order of the layers of a cube map, +X, -X, +Y, -Y, +Z and -Z. `s` and `t` go
+// from -1 to 1, right and down on the face
+fn cube_face_direction(face: usize, s: f32, t: f32) -> Vec3 {
+    match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    }
+    .normalize()
+}
+
+// Sample the equirectangular image in each direction of the faces, bilinearly
+fn equirectangular_to_cube_map(image: &Image) -> Image {
+    let width = image.texture_descriptor.size.width as usize;
+    let height = image.texture_descriptor.size.height as usize;
+    let texel = |x: usize, y: usize| {
+        let start = (y * width + x) * 16;
+        let channel = |index: usize| {
+            let bytes = &image.data[start + index * 4..start + index * 4 + 4];
+            f32::from_ne_bytes(bytes.try_into().unwrap())
+        };
+        Vec4::new(channel(0), channel(1), channel(2), channel(3))
+    };
+    let sample = |direction: Vec3| {
+        // Longitude wraps around, latitude goes from the top of the image to the bottom
+        let u = 0.5 + direction.x.atan2(-direction.z) / std::f32::consts::TAU;
+        let v = direction.y.clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+        let x = u * width as f32 - 0.5;
+        let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let x0 = (x.floor() as isize).rem_euclid(width as isize) as usize;
+        let x1 = (x0 + 1) % width;
+        let y0 = y.floor() as usize;
+        let y1 = (y0 + 1).min(height - 1);
+        let top = texel(x0, y0).lerp(texel(x1, y0), fx);
+        let bottom = texel(x0, y1).lerp(texel(x1, y1), fx);
+        top.lerp(bottom, fy)
+    };
+
+    let size = (width / 4).clamp(1, MAX_FACE_SIZE);
+    let mut data = Vec::with_capacity(6 * size * size * 16);
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+                let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+                let color = sample(cube_face_direction(face, s, t));
+                for channel in color.to_array() {
+                    data.extend_from_slice(&channel.to_ne_bytes());
+                }
+            }
+        }
+    }
+
+    let mut cube_map = Image::new(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 6,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba32Float,
+    );
+    cube_map.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    cube_map.sampler_descriptor = ImageSampler::nearest();
+    cube_map
+}
+
+fn toggle_hdri_skybox(
+    input: Res<Input<KeyCode>>,
+    mut skybox: ResMut<HdriSkybox>,
+    domes: Query<(), With<HdriSkyDome>>,
+) {
+    if !(input.just_pressed(KeyCode::H) && input.any_pressed(platform::SHORTCUT_MODIFIER)) {
+        return;
+    }
+    if domes.is_empty() {
+        info!("no HDRI skybox loaded from assets/{}", skybox.path);
+        return;
+    }
+    skybox.enabled = !skybox.enabled;
+    info!("HDRI skybox: {}", skybox.enabled);
+}
+
+// Show either the skybox or the procedural sky, and follow the camera
+#[allow(clippy::type_complexity)]
+fn update_hdri_skybox(
+    skybox: Res<HdriSkybox>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut domes: Query<
+        (&mut Transform, &mut Visibility, &Handle<HdriSkyboxMaterial>),
+        With<HdriSkyDome>,
+    >,
+    mut procedural: Query<&mut Visibility, (With<SkyDome>, Without<HdriSkyDome>)>,
+    mut materials: ResMut<Assets<HdriSkyboxMaterial>>,
+) {
+    let shown = skybox.enabled && !domes.is_empty();
+    for mut visibility in procedural.iter_mut() {
+        if visibility.is_visible == shown {
+            visibility.is_visible = !shown;
+        }
+    }
+    let camera = camera.get_single().map(|camera| camera.translation());
+    for (mut transform, mut visibility, handle) in domes.iter_mut() {
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+        if let Ok(camera) = camera {
+            transform.translation = camera;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.exposure = skybox.exposure;
+            material.rotation = skybox.rotation_degrees.to_radians();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    exposure::ExposureSettings,
+    platform,
+    plugins::{
+        camera::MainCamera,
+        lights::{LightGroup, LightGroupKind},
+        sky::SunLight,
+    },
+    shade::ShadeMap,
+};
+
+// Number of frames used to compute the average FPS
+const FPS_HISTORY: usize = 60;
+
+/// On screen display of the simulation state, toggled with `H`.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_hud)
+            .add_system(toggle_hud)
+            .add_system(update_hud);
+    }
+}
+
+#[derive(Component)]
+struct Hud;
+
+#[derive(Component)]
+struct HudText;
+
+fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(5.0),
+                        left: Val::Px(5.0),
+                        ..default()
+                    },
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|hud| {
+            hud.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                HudText,
+            ));
+        });
+}
+
+fn toggle_hud(input: Res<Input<KeyCode>>, mut hud: Query<&mut Visibility, With<Hud>>) {
+    // With the modifier, the HDRI skybox is toggled instead
+    if input.just_pressed(KeyCode::H) && !input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        for mut visibility in hud.iter_mut() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_hud(
+    time: Res<Time>,
+    mut frame_times: Local<VecDeque<f32>>,
+    sun: Query<&Transform, With<SunLight>>,
+    shade_map: Res<ShadeMap>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    lights: Query<(&PointLight, &LightGroup)>,
+    exposure: Res<ExposureSettings>,
+    hud: Query<&Visibility, With<Hud>>,
+    mut text: Query<&mut Text, With<HudText>>,
+) {
+    frame_times.push_back(time.delta_seconds());
+    if frame_times.len() > FPS_HISTORY {
+        frame_times.pop_front();
+    }
+
+    if !hud.iter().any(|visibility| visibility.is_visible) {
+        return;
+    }
+
+    let fps = frame_times.len() as f32 / frame_times.iter().sum::<f32>();
+
+    // The sun rotates around the Z axis with the day/night cycle
+    let sun_angle = sun.get_single().map_or(0.0, |transform| {
+        let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+        angle.to_degrees().rem_euclid(360.0)
+    });
+
+    // Of the ground under the camera
+    let shaded = camera
+        .get_single()
+        .is_ok_and(|transform| shade_map.is_shaded(transform.translation()));
+
+    let mut active = 0;
+    let mut groups = [false; 4];
+    for (light, group) in lights.iter() {
+        if light.intensity == 0.0 {
+            continue;
+        }
+        active += 1;
+        groups[match group.0 {
+            LightGroupKind::Ceiling => 0,
+            LightGroupKind::Wall => 1,
+            LightGroupKind::Lantern => 2,
+            LightGroupKind::StreetLight => 3,
+        }] = true;
+    }
+    let status = |enabled: bool| if enabled { "on" } else { "off" };
+
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!(
+            "Sun angle: {sun_angle:.1}°\nIn the shade: {}\nFPS: {fps:.1}\nEV100: {:.1}\nActive lights: {active}\nCeiling: {} | Wall: {} | Lantern: {} | Streetlight: {}",
+            if shaded { "yes" } else { "no" },
+            exposure.ev100,
+            status(groups[0]),
+            status(groups[1]),
+            status(groups[2]),
+            status(groups[3]),
+        );
+    }
+}
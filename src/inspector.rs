@@ -0,0 +1,208 @@
+use bevy::{
+    pbr::{wireframe::Wireframe, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{primitives::Aabb, view::RenderLayers},
+};
+use bevy_egui::{egui, EguiContext, EguiPlugin};
+
+use crate::plugins::{
+    camera::DEBUG_LAYER,
+    lights::{LightGroup, LightGroupKind},
+    scene::Scenes,
+};
+
+// Size of the outline around entities without bounds, like lights, in meters
+const UNBOUNDED_OUTLINE_SIZE: f32 = 0.2;
+
+/// A window listing the entities of the scenes, toggled with `F1`.
+///
+/// Clicking an entity outlines it with a wireframe box, and logs its components. Bevy 0.9 has no
+/// gizmos, this relies on the wireframe rendering set up by the
+/// [`MaterialPresetPlugin`](crate::material_preset::MaterialPresetPlugin).
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+        app.init_resource::<Inspector>()
+            .add_startup_system(setup_inspector_outline)
+            .add_system(toggle_inspector)
+            .add_system(inspector_panel.after(toggle_inspector))
+            .add_system(update_inspector_outline.after(inspector_panel))
+            .add_system(log_selected_components.after(inspector_panel));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum EntityGroup {
+    Light(LightGroupKind),
+    #[default]
+    Named,
+    Unnamed,
+}
+
+const ENTITY_GROUPS: [EntityGroup; 6] = [
+    EntityGroup::Light(LightGroupKind::Ceiling),
+    EntityGroup::Light(LightGroupKind::Wall),
+    EntityGroup::Light(LightGroupKind::Lantern),
+    EntityGroup::Light(LightGroupKind::StreetLight),
+    EntityGroup::Named,
+    EntityGroup::Unnamed,
+];
+
+#[derive(Resource, Default)]
+struct Inspector {
+    visible: bool,
+    group: EntityGroup,
+    selected: Option<Entity>,
+    // Set when an entity is clicked, its components are logged once
+    log_selected: bool,
+}
+
+#[derive(Component)]
+struct InspectorOutline;
+
+fn setup_inspector_outline(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // Only the wireframe is visible
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::NONE,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+        Wireframe,
+        NotShadowCaster,
+        NotShadowReceiver,
+        RenderLayers::layer(DEBUG_LAYER),
+        InspectorOutline,
+    ));
+}
+
+fn toggle_inspector(input: Res<Input<KeyCode>>, mut inspector: ResMut<Inspector>) {
+    if input.just_pressed(KeyCode::F1) {
+        inspector.visible = !inspector.visible;
+    }
+}
+
+fn inspector_panel(
+    mut inspector: ResMut<Inspector>,
+    mut egui_context: ResMut<EguiContext>,
+    scenes: Option<Res<Scenes>>,
+    scene_spawner: Res<SceneSpawner>,
+    lights: Query<(Entity, &LightGroup)>,
+    names: Query<&Name>,
+) {
+    if !inspector.visible {
+        return;
+    }
+
+    let scene_entities = scenes
+        .iter()
+        .flat_map(|scenes| [scenes.interior, scenes.exterior])
+        .flatten()
+        .flat_map(|instance| scene_spawner.iter_instance_entities(instance));
+    let entities: Vec<Entity> = match inspector.group {
+        EntityGroup::Light(kind) => lights
+            .iter()
+            .filter(|(_, group)| group.0 == kind)
+            .map(|(entity, _)| entity)
+            .collect(),
+        EntityGroup::Named => scene_entities
+            .filter(|entity| names.contains(*entity))
+            .collect(),
+        EntityGroup::Unnamed => scene_entities
+            .filter(|entity| !names.contains(*entity))
+            .collect(),
+    };
+
+    let inspector = &mut *inspector;
+    egui::Window::new("Inspector").show(egui_context.ctx_mut(), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            for group in ENTITY_GROUPS {
+                let label = match group {
+                    EntityGroup::Light(kind) => kind.to_string(),
+                    EntityGroup::Named => "Named".to_string(),
+                    EntityGroup::Unnamed => "Unnamed".to_string(),
+                };
+                ui.selectable_value(&mut inspector.group, group, label);
+            }
+        });
+        ui.separator();
+        ui.label(format!("{} entities", entities.len()));
+        // Only the visible rows are laid out, the scenes have thousands of entities
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea::vertical().show_rows(ui, row_height, entities.len(), |ui, rows| {
+            for &entity in &entities[rows] {
+                let label = match names.get(entity) {
+                    Ok(name) => format!("{entity:?} {name}"),
+                    Err(_) => format!("{entity:?}"),
+                };
+                if ui
+                    .selectable_label(inspector.selected == Some(entity), label)
+                    .clicked()
+                {
+                    inspector.selected = Some(entity);
+                    inspector.log_selected = true;
+                }
+            }
+        });
+    });
+}
+
+// Fit the outline to the bounds of the selected entity
+fn update_inspector_outline(
+    inspector: Res<Inspector>,
+    selected: Query<(&GlobalTransform, Option<&Aabb>), Without<InspectorOutline>>,
+    mut outline: Query<(&mut Transform, &mut Visibility), With<InspectorOutline>>,
+) {
+    let selected = inspector
+        .selected
+        .filter(|_| inspector.visible)
+        .and_then(|entity| selected.get(entity).ok());
+    for (mut transform, mut visibility) in outline.iter_mut() {
+        visibility.is_visible = selected.is_some();
+        if let Some((global_transform, aabb)) = selected {
+            let (scale, rotation, _) = global_transform.to_scale_rotation_translation();
+            *transform = match aabb {
+                Some(aabb) => Transform {
+                    translation: global_transform.transform_point(aabb.center.into()),
+                    rotation,
+                    scale: scale * Vec3::from(aabb.half_extents) * 2.0,
+                },
+                None => Transform::from_translation(global_transform.translation())
+                    .with_scale(Vec3::splat(UNBOUNDED_OUTLINE_SIZE)),
+            };
+        }
+    }
+}
+
+fn log_selected_components(world: &mut World) {
+    let entity = {
+        let mut inspector = world.resource_mut::<Inspector>();
+        if !inspector.log_selected {
+            return;
+        }
+        inspector.log_selected = false;
+        inspector.selected
+    };
+    if let Some(entity) = entity.filter(|entity| world.get_entity(*entity).is_some()) {
+        let components: Vec<&str> = world
+            .inspect_entity(entity)
+            .iter()
+            .map(|info| info.name())
+            .collect();
+        info!("{entity:?} components: {}", components.join(", "));
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{camera_controller::CameraController, platform};
+
+const KEYBINDS_PATH: &str = "keybinds.toml";
+
+/// Overrides the [`CameraController`] bindings with the ones in `keybinds.toml`.
+///
+/// The file is read once at startup, and written with the default bindings if it doesn't exist.
+/// Keys are named like their [`KeyCode`], `"W"`, `"Key1"` or `"LShift"`.
+pub struct KeybindsPlugin;
+
+impl Plugin for KeybindsPlugin {
+    fn build(&self, app: &mut App) {
+        // After the camera is spawned
+        app.add_startup_system_to_stage(StartupStage::PostStartup, load_keybinds);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeybindConfig {
+    pub forward: String,
+    pub back: String,
+    pub left: String,
+    pub right: String,
+    pub up: String,
+    pub down: String,
+    pub run: String,
+    pub walk_mode: String,
+    // "Left", "Right" or "Middle"
+    pub enable_mouse: String,
+}
+
+impl Default for KeybindConfig {
+    fn default() -> Self {
+        let controller = CameraController::default();
+        Self {
+            forward: keycode_name(controller.key_forward).to_string(),
+            back: keycode_name(controller.key_back).to_string(),
+            left: keycode_name(controller.key_left).to_string(),
+            right: keycode_name(controller.key_right).to_string(),
+            up: keycode_name(controller.key_up).to_string(),
+            down: keycode_name(controller.key_down).to_string(),
+            run: keycode_name(controller.key_run).to_string(),
+            walk_mode: keycode_name(controller.key_walk_mode).to_string(),
+            enable_mouse: format!("{:?}", controller.key_enable_mouse),
+        }
+    }
+}
+
+impl KeybindConfig {
+    // Invalid names keep the current binding
+    fn apply(&self, controller: &mut CameraController) {
+        for (name, key) in [
+            (&self.forward, &mut controller.key_forward),
+            (&self.back, &mut controller.key_back),
+            (&self.left, &mut controller.key_left),
+            (&self.right, &mut controller.key_right),
+            (&self.up, &mut controller.key_up),
+            (&self.down, &mut controller.key_down),
+            (&self.run, &mut controller.key_run),
+            (&self.walk_mode, &mut controller.key_walk_mode),
+        ] {
+            match parse_keycode(name) {
+                Some(keycode) => *key = keycode,
+                None => warn!("unknown key {name:?} in {KEYBINDS_PATH}"),
+            }
+        }
+        match parse_mouse_button(&self.enable_mouse) {
+            Some(button) => controller.key_enable_mouse = button,
+            None => warn!(
+                "unknown mouse button {:?} in {KEYBINDS_PATH}",
+                self.enable_mouse
+            ),
+        }
+    }
+}
+
+// Names of the keys that can be bound, matching their `KeyCode` variant
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("Key0", KeyCode::Key0),
+    ("Key1", KeyCode::Key1),
+    ("Key2", KeyCode::Key2),
+    ("Key3", KeyCode::Key3),
+    ("Key4", KeyCode::Key4),
+    ("Key5", KeyCode::Key5),
+    ("Key6", KeyCode::Key6),
+    ("Key7", KeyCode::Key7),
+    ("Key8", KeyCode::Key8),
+    ("Key9", KeyCode::Key9),
+    ("Space", KeyCode::Space),
+    ("Tab", KeyCode::Tab),
+    ("Return", KeyCode::Return),
+    ("Back", KeyCode::Back),
+    ("LShift", KeyCode::LShift),
+    ("RShift", KeyCode::RShift),
+    ("LControl", KeyCode::LControl),
+    ("RControl", KeyCode::RControl),
+    ("LAlt", KeyCode::LAlt),
+    ("RAlt", KeyCode::RAlt),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Comma", KeyCode::Comma),
+    ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash),
+    ("Semicolon", KeyCode::Semicolon),
+    ("Apostrophe", KeyCode::Apostrophe),
+    ("Minus", KeyCode::Minus),
+    ("Equals", KeyCode::Equals),
+];
+
+/// The key named `s`, ignoring case.
+pub fn parse_keycode(s: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()))
+        .map(|(_, keycode)| *keycode)
+}
+
+pub fn keycode_name(keycode: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(_, key)| *key == keycode)
+        .map_or("", |(name, _)| *name)
+}
+
+fn parse_mouse_button(s: &str) -> Option<MouseButton> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "left" => Some(MouseButton::Left),
+        "right" => Some(MouseButton::Right),
+        "middle" => Some(MouseButton::Middle),
+        _ => None,
+    }
+}
+
+// Not watched for changes, the bindings stay the same for the whole session
+pub fn load_keybinds(mut controllers: Query<&mut CameraController>) {
+    let config = match platform::read_to_string(KEYBINDS_PATH) {
+        Ok(content) => match toml::from_str::<KeybindConfig>(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("invalid {KEYBINDS_PATH}, using default keybinds: {err}");
+                return;
+            }
+        },
+        Err(_) => {
+            // Write the defaults as a template to edit
+            match toml::to_string(&KeybindConfig::default()) {
+                Ok(content) => {
+                    if let Err(err) = platform::write(KEYBINDS_PATH, content) {
+                        warn!("could not write {KEYBINDS_PATH}: {err}");
+                    }
+                }
+                Err(err) => warn!("could not serialize keybinds: {err}"),
+            }
+            return;
+        }
+    };
+    for mut controller in controllers.iter_mut() {
+        config.apply(&mut controller);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        for (name, keycode) in KEY_NAMES {
+            assert_eq!(parse_keycode(name), Some(*keycode), "{name}");
+            assert_eq!(keycode_name(*keycode), *name);
+        }
+    }
+
+    #[test]
+    fn key_names_ignore_case() {
+        assert_eq!(parse_keycode("w"), Some(KeyCode::W));
+        assert_eq!(parse_keycode("lshift"), Some(KeyCode::LShift));
+        assert_eq!(parse_keycode("PAGEUP"), Some(KeyCode::PageUp));
+        assert_eq!(parse_keycode(" Space "), Some(KeyCode::Space));
+    }
+
+    #[test]
+    fn unknown_key_names() {
+        assert_eq!(parse_keycode(""), None);
+        assert_eq!(parse_keycode("Shift"), None);
+        assert_eq!(parse_keycode("W W"), None);
+        assert_eq!(parse_keycode("F99"), None);
+    }
+}
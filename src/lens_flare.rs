@@ -0,0 +1,391 @@
+use bevy::{
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+};
+
+use crate::{
+    mesh_utils::world_bounds,
+    plugins::{
+        camera::MainCamera,
+        lights::{LightGroup, LightGroupKind, LightSettings},
+        sky::SunLight,
+    },
+};
+
+// The sun is drawn this far from the camera along its direction, in meters
+const SUN_DISTANCE: f32 = 1000.0;
+// The occlusion ray stops this short of a point light, so that its fixture doesn't hide it
+const LIGHT_CLEARANCE: f32 = 0.3;
+// Size of the largest ghost, in logical pixels
+const GHOST_SIZE: f32 = 120.0;
+// Ghosts fade out over this fraction of the screen when the light gets close to the edges
+const EDGE_FADE: f32 = 0.2;
+// Speed at which the flares fade in and out when the light is hidden or shown, per second
+const OCCLUSION_FADE_SPEED: f32 = 8.0;
+// Size of the generated ghost texture, in pixels
+const GHOST_TEXTURE_SIZE: u32 = 64;
+// Colors of the ghosts in turn, from the coatings of the lens elements
+const GHOST_TINTS: [Color; 3] = [
+    Color::rgb(1.0, 0.8, 0.5),
+    Color::rgb(0.6, 1.0, 0.7),
+    Color::rgb(0.6, 0.7, 1.0),
+];
+
+/// Lens flares of the sun and the streetlights, toggled with `F`.
+///
+/// Each [`LensFlare`] draws a chain of ghosts, the reflections of the light between the elements
+/// of the lens, on the line from the light through the center of the screen. They follow how
+/// bright the light is, and fade out when it is hidden behind a mesh casting shadows.
+pub struct LensFlarePlugin;
+
+impl Plugin for LensFlarePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LensFlare>()
+            .init_resource::<LensFlares>()
+            .add_startup_system(setup_lens_flares)
+            .add_system(toggle_lens_flares)
+            .add_system(attach_lens_flares)
+            .add_system(spawn_flare_ghosts.after(attach_lens_flares))
+            .add_system(render_lens_flares.after(spawn_flare_ghosts));
+    }
+}
+
+#[derive(Resource)]
+pub struct LensFlares {
+    pub enabled: bool,
+}
+
+impl Default for LensFlares {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Lens flare of a light, on an entity with a [`PointLight`] or a [`DirectionalLight`].
+#[derive(Component, Reflect, Clone)]
+#[reflect(Component)]
+pub struct LensFlare {
+    pub flare_texture: Handle<Image>,
+    pub num_ghosts: u8,
+    // Distance between two ghosts, as a ratio of the distance from the light to the center of the
+    // screen
+    pub spacing: f32,
+    // Opacity of the ghosts when the light is at its brightest
+    pub intensity: f32,
+}
+
+impl Default for LensFlare {
+    fn default() -> Self {
+        Self {
+            flare_texture: Handle::default(),
+            num_ghosts: 4,
+            spacing: 0.4,
+            intensity: 0.3,
+        }
+    }
+}
+
+// Where the light is on the screen and how much of it is seen by the camera, eased in and out
+// when it is hidden or shown
+#[derive(Component, Default)]
+struct FlareVisibility {
+    amount: f32,
+    // In logical pixels, from the bottom left of the viewport
+    screen: Vec2,
+    // Fade near the edges of the screen
+    edge: f32,
+    opacity: f32,
+}
+
+#[derive(Component)]
+struct FlareGhost {
+    flare: Entity,
+    index: u8,
+}
+
+#[derive(Component)]
+struct LensFlareRoot;
+
+// The texture shared by the ghosts of the flares added by this plugin
+#[derive(Resource)]
+struct LensFlareAssets {
+    ghost: Handle<Image>,
+}
+
+fn setup_lens_flares(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    commands.insert_resource(LensFlareAssets {
+        ghost: images.add(ghost_texture()),
+    });
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            // Under the rest of the UI
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        LensFlareRoot,
+    ));
+}
+
+// A soft disc with a brighter rim, like the image of the aperture out of focus
+fn ghost_texture() -> Image {
+    let size = GHOST_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let position = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - 1.0;
+            let radius = position.length();
+            let disc = (1.0 - radius).clamp(0.0, 1.0).powf(0.5) * 0.6;
+            let rim = (1.0 - ((radius - 0.85) / 0.1).abs()).clamp(0.0, 1.0) * 0.4;
+            let alpha = ((disc + rim) * 255.0) as u8;
+            data.extend_from_slice(&[255, 255, 255, alpha]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn toggle_lens_flares(input: Res<Input<KeyCode>>, mut flares: ResMut<LensFlares>) {
+    if input.just_pressed(KeyCode::F) {
+        flares.enabled = !flares.enabled;
+        info!("lens flares: {}", flares.enabled);
+    }
+}
+
+// The sun and the streetlights get a flare, also when the exterior is streamed back in
+fn attach_lens_flares(
+    mut commands: Commands,
+    assets: Res<LensFlareAssets>,
+    suns: Query<Entity, Added<SunLight>>,
+    lights: Query<(Entity, &LightGroup), Added<LightGroup>>,
+) {
+    let streetlights = lights
+        .iter()
+        .filter(|(_, group)| group.0 == LightGroupKind::StreetLight)
+        .map(|(entity, _)| (entity, 4, 0.3));
+    // The sun is much brighter, with more ghosts
+    let suns = suns.iter().map(|entity| (entity, 6, 0.5));
+    for (entity, num_ghosts, intensity) in suns.chain(streetlights) {
+        commands.entity(entity).insert(LensFlare {
+            flare_texture: assets.ghost.clone(),
+            num_ghosts,
+            intensity,
+            ..default()
+        });
+    }
+}
+
+// Ghosts are spawned again when the flare changes, to follow its number of ghosts
+fn spawn_flare_ghosts(
+    mut commands: Commands,
+    flares: Query<(Entity, &LensFlare), Changed<LensFlare>>,
+    ghosts: Query<(Entity, &FlareGhost)>,
+    root: Query<Entity, With<LensFlareRoot>>,
+) {
+    let root = match root.get_single() {
+        Ok(root) => root,
+        Err(_) => return,
+    };
+    for (flare_entity, flare) in flares.iter() {
+        for (ghost_entity, ghost) in ghosts.iter() {
+            if ghost.flare == flare_entity {
+                commands.entity(ghost_entity).despawn_recursive();
+            }
+        }
+        commands
+            .entity(flare_entity)
+            .insert(FlareVisibility::default());
+        commands.entity(root).with_children(|root| {
+            for index in 0..flare.num_ghosts {
+                root.spawn((
+                    ImageBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        image: UiImage(flare.flare_texture.clone()),
+                        visibility: Visibility { is_visible: false },
+                        ..default()
+                    },
+                    FlareGhost {
+                        flare: flare_entity,
+                        index,
+                    },
+                ));
+            }
+        });
+    }
+}
+
+// Whether the segment enters the box, boxes around its start are ignored
+fn segment_enters_box(start: Vec3, end: Vec3, min: Vec3, max: Vec3) -> bool {
+    let motion = end - start;
+    if start.cmpge(min).all() && start.cmple(max).all() {
+        return false;
+    }
+    let mut t_enter = 0.0_f32;
+    let mut t_exit = 1.0_f32;
+    for axis in 0..3 {
+        if motion[axis] == 0.0 {
+            if start[axis] < min[axis] || start[axis] > max[axis] {
+                return false;
+            }
+            continue;
+        }
+        let t_min = (min[axis] - start[axis]) / motion[axis];
+        let t_max = (max[axis] - start[axis]) / motion[axis];
+        t_enter = t_enter.max(t_min.min(t_max));
+        t_exit = t_exit.min(t_min.max(t_max));
+        if t_enter > t_exit {
+            return false;
+        }
+    }
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn render_lens_flares(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<LensFlares>,
+    light_settings: Res<LightSettings>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut flares: Query<(
+        &LensFlare,
+        &mut FlareVisibility,
+        &GlobalTransform,
+        Option<&PointLight>,
+        Option<&LightGroup>,
+        Option<(&DirectionalLight, &SunLight)>,
+    )>,
+    mut ghosts: Query<(
+        Entity,
+        &FlareGhost,
+        &mut Style,
+        &mut BackgroundColor,
+        &mut Visibility,
+    )>,
+    occluders: Query<
+        (
+            &GlobalTransform,
+            &Aabb,
+            &ComputedVisibility,
+            Option<&RenderLayers>,
+        ),
+        (With<Handle<Mesh>>, Without<NotShadowCaster>),
+    >,
+) {
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    // The main camera covers the whole window
+    let viewport_size = match camera.logical_viewport_size() {
+        Some(size) => size,
+        None => return,
+    };
+    let camera_position = camera_transform.translation();
+
+    for (flare, mut visibility, transform, point_light, group, sun) in flares.iter_mut() {
+        // How bright the light is compared to when it is fully on
+        let (position, brightness, clearance) = match (point_light, group, sun) {
+            (_, _, Some((light, sun))) => (
+                camera_position + transform.back() * SUN_DISTANCE,
+                light.illuminance / sun.max_illuminance.max(f32::EPSILON),
+                0.0,
+            ),
+            (Some(light), Some(group), _) => (
+                transform.translation(),
+                light.intensity / group.0.intensity(&light_settings).max(f32::EPSILON),
+                LIGHT_CLEARANCE,
+            ),
+            _ => continue,
+        };
+        let screen = camera
+            .world_to_viewport(camera_transform, position)
+            .filter(|screen| screen.cmpge(Vec2::ZERO).all() && screen.cmple(viewport_size).all());
+        // Occluders are only looked for when the light could be seen
+        let seen = match screen {
+            Some(_) if settings.enabled && brightness > 0.0 => {
+                let end = position + (camera_position - position).normalize_or_zero() * clearance;
+                !occluders
+                    .iter()
+                    .any(|(transform, aabb, computed_visibility, layers)| {
+                        let in_scene =
+                            layers.is_none_or(|layers| layers.intersects(&RenderLayers::layer(0)));
+                        if !(in_scene && computed_visibility.is_visible()) {
+                            return false;
+                        }
+                        let (min, max) = world_bounds(aabb, transform, 0.0);
+                        segment_enters_box(camera_position, end, min, max)
+                    })
+            }
+            _ => false,
+        };
+        let step = OCCLUSION_FADE_SPEED * time.delta_seconds();
+        let target = if seen { 1.0 } else { 0.0 };
+        visibility.amount += (target - visibility.amount).clamp(-step, step);
+        if let Some(screen) = screen {
+            visibility.screen = screen;
+            // Faded out before the light leaves the screen
+            let ndc = screen / viewport_size * 2.0 - 1.0;
+            visibility.edge = ((1.0 - ndc.abs().max_element()) / EDGE_FADE).clamp(0.0, 1.0);
+        }
+        visibility.opacity =
+            flare.intensity * brightness.clamp(0.0, 1.0) * visibility.amount * visibility.edge;
+    }
+
+    let center = viewport_size / 2.0;
+    for (entity, ghost, mut style, mut color, mut ghost_visibility) in ghosts.iter_mut() {
+        let (flare, visibility) = match flares.get(ghost.flare) {
+            Ok((flare, visibility, ..)) => (flare, visibility),
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+        let shown = visibility.opacity > 0.001;
+        if ghost_visibility.is_visible != shown {
+            ghost_visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+        // On the line from the light through the center, the first ones between them and the
+        // others mirrored past the center
+        let ratio = 1.0 - (ghost.index as f32 + 1.0) * flare.spacing;
+        let position = center + (visibility.screen - center) * ratio;
+        // Sizes and tints vary between the ghosts, like the reflections on the coatings of the
+        // lens elements
+        let variation = (ghost.index as f32 * 0.618).fract();
+        let size = GHOST_SIZE * (0.3 + 0.7 * variation);
+        let mut tint = GHOST_TINTS[ghost.index as usize % GHOST_TINTS.len()];
+        tint.set_a(visibility.opacity * (1.0 - 0.5 * variation));
+        *color = tint.into();
+        style.size = Size::new(Val::Px(size), Val::Px(size));
+        // The viewport is from the bottom left, the ghost is placed from its center
+        style.position = UiRect {
+            left: Val::Px(position.x - size / 2.0),
+            bottom: Val::Px(position.y - size / 2.0),
+            ..default()
+        };
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::plugins::{camera::MainCamera, lights::LightGroup};
+
+// The audit runs once every this many frames
+const AUDIT_INTERVAL_FRAMES: u32 = 60;
+
+/// Warns when more point lights cast shadows than the shadow maps can comfortably hold.
+///
+/// Every light with shadows takes six layers of the point light shadow maps, even when it is
+/// switched off. The warning lists the light groups with the most shadow casters, and suggests the
+/// group furthest from the camera as the first one to turn off.
+pub struct LightAuditPlugin;
+
+impl Plugin for LightAuditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightAuditSettings>()
+            .add_system(light_audit);
+    }
+}
+
+#[derive(Resource)]
+pub struct LightAuditSettings {
+    // Above this many shadow casting point lights, a warning is logged
+    pub max_shadow_casters: usize,
+}
+
+impl Default for LightAuditSettings {
+    fn default() -> Self {
+        Self {
+            max_shadow_casters: 10,
+        }
+    }
+}
+
+// Shadow casting lights of a group
+#[derive(Default)]
+struct GroupAudit {
+    count: usize,
+    total_distance: f32,
+}
+
+impl GroupAudit {
+    fn average_distance(&self) -> f32 {
+        self.total_distance / self.count as f32
+    }
+}
+
+fn light_audit(
+    settings: Res<LightAuditSettings>,
+    mut frames: Local<u32>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    lights: Query<(
+        &PointLight,
+        &ComputedVisibility,
+        &GlobalTransform,
+        Option<&LightGroup>,
+    )>,
+) {
+    *frames += 1;
+    if *frames < AUDIT_INTERVAL_FRAMES {
+        return;
+    }
+    *frames = 0;
+
+    let camera = camera
+        .get_single()
+        .map_or(Vec3::ZERO, |camera| camera.translation());
+    let mut groups: HashMap<String, GroupAudit> = HashMap::default();
+    let mut total = 0;
+    for (light, visibility, transform, group) in lights.iter() {
+        if !light.shadows_enabled || !visibility.is_visible_in_hierarchy() {
+            continue;
+        }
+        let name = group.map_or_else(|| "Other".to_string(), |group| group.0.to_string());
+        let audit = groups.entry(name).or_default();
+        audit.count += 1;
+        audit.total_distance += transform.translation().distance(camera);
+        total += 1;
+    }
+    if total <= settings.max_shadow_casters {
+        return;
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+    let contributions: Vec<_> = groups
+        .iter()
+        .map(|(name, audit)| format!("{name}: {}", audit.count))
+        .collect();
+    warn!(
+        "{total} point lights cast shadows, more than {} | {}",
+        settings.max_shadow_casters,
+        contributions.join(" - ")
+    );
+    // Shadows of the furthest lights are the least noticeable
+    if let Some((name, audit)) = groups
+        .iter()
+        .max_by(|a, b| a.1.average_distance().total_cmp(&b.1.average_distance()))
+    {
+        warn!(
+            "turn off the {name} group first, {:.1}m from the camera on average",
+            audit.average_distance()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use bevy::{app::AppExit, prelude::*};
+use serde::Serialize;
+
+use crate::{
+    loading::GameState,
+    platform,
+    plugins::lights::{LightGroup, LightLOD, LightSettings},
+    scene_fix::SpawnedFor,
+    streaming::SceneStreamingSettings,
+};
+
+const EXPORTED_LIGHTS_PATH: &str = "exported_lights.json";
+// Frames to wait once playing, the strips spawn their lights on the next frame
+const EXPORT_DELAY_FRAMES: u32 = 2;
+
+/// Writes the point, spot and directional lights to `exported_lights.json` once the scenes are
+/// loaded and fixed, then exits, to bake lightmaps with the lights placed as in the demo.
+///
+/// The lights follow the conventions of the glTF `KHR_lights_punctual` extension, which offline
+/// lightmappers and DCC tools import: linear colors, the intensity of point and spot lights in
+/// candela and of directional lights in lux, and directions pointing where the light shines. The
+/// intensities are those of the lights when exported, before their reduction with the distance to
+/// the camera. The exterior isn't streamed out meanwhile.
+pub struct LightExportPlugin;
+
+impl Plugin for LightExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(pause_scene_streaming)
+            // Once the lights spawned by the fixes have their global transform
+            .add_system_set_to_stage(
+                CoreStage::Last,
+                SystemSet::on_update(GameState::Playing).with_system(export_lights),
+            );
+    }
+}
+
+#[derive(Serialize)]
+struct ExportedLights {
+    lights: Vec<ExportedLight>,
+}
+
+#[derive(Serialize)]
+struct ExportedLight {
+    name: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    color: [f32; 3],
+    intensity: f32,
+    // In meters, for point and spot lights
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    spot: Option<ExportedSpot>,
+    shadows: bool,
+    // Light group of the point lights spawned by the fixes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    // Name of the entity of the scene the light was spawned for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fixture: Option<String>,
+}
+
+// Angles from the axis of the cone, in radians
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedSpot {
+    inner_cone_angle: f32,
+    outer_cone_angle: f32,
+}
+
+fn pause_scene_streaming(mut settings: ResMut<SceneStreamingSettings>) {
+    settings.paused = true;
+}
+
+fn linear_rgb(color: Color) -> [f32; 3] {
+    let [r, g, b, _] = color.as_linear_rgba_f32();
+    [r, g, b]
+}
+
+// Bevy converts the lumens of point and spot lights to candelas over the whole sphere
+fn candelas(lumens: f32) -> f32 {
+    lumens / (4.0 * std::f32::consts::PI)
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn export_lights(
+    light_settings: Res<LightSettings>,
+    mut frames: Local<u32>,
+    point_lights: Query<(
+        Entity,
+        &PointLight,
+        &GlobalTransform,
+        Option<&LightLOD>,
+        Option<&LightGroup>,
+    )>,
+    spot_lights: Query<(Entity, &SpotLight, &GlobalTransform)>,
+    directional_lights: Query<(Entity, &DirectionalLight, &GlobalTransform)>,
+    names: Query<&Name>,
+    spawned_for: Query<&SpawnedFor>,
+    parents: Query<&Parent>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    *frames += 1;
+    if *frames != EXPORT_DELAY_FRAMES {
+        return;
+    }
+
+    let name = |entity: Entity, default: &str| {
+        names.get(entity).map_or_else(
+            |_| format!("{default} {}", entity.index()),
+            |name| name.to_string(),
+        )
+    };
+    // The lights of the strips are spawned under the entity spawned for the fixture
+    let fixture = |entity: Entity| {
+        spawned_for
+            .get(entity)
+            .or_else(|_| spawned_for.get(parents.get(entity)?.get()))
+            .ok()
+            .and_then(|spawned_for| names.get(spawned_for.0).ok())
+            .map(|name| name.to_string())
+    };
+    let mut lights = Vec::new();
+    for (entity, light, transform, lod, group) in point_lights.iter() {
+        // Before the level of detail
+        let (intensity, range) = match lod {
+            Some(lod) => (
+                lod.nominal_intensity,
+                lod.nominal_intensity / light_settings.range_ratio,
+            ),
+            None => (light.intensity, light.range),
+        };
+        lights.push(ExportedLight {
+            name: name(entity, "Point light"),
+            kind: "point",
+            color: linear_rgb(light.color),
+            intensity: candelas(intensity),
+            range: Some(range),
+            position: Some(transform.translation().to_array()),
+            direction: None,
+            spot: None,
+            shadows: light.shadows_enabled,
+            group: group.map(|group| group.0.to_string()),
+            fixture: fixture(entity),
+        });
+    }
+    for (entity, light, transform) in spot_lights.iter() {
+        lights.push(ExportedLight {
+            name: name(entity, "Spot light"),
+            kind: "spot",
+            color: linear_rgb(light.color),
+            intensity: candelas(light.intensity),
+            range: Some(light.range),
+            position: Some(transform.translation().to_array()),
+            direction: Some(transform.forward().to_array()),
+            spot: Some(ExportedSpot {
+                inner_cone_angle: light.inner_angle,
+                outer_cone_angle: light.outer_angle,
+            }),
+            shadows: light.shadows_enabled,
+            group: None,
+            fixture: None,
+        });
+    }
+    for (entity, light, transform) in directional_lights.iter() {
+        lights.push(ExportedLight {
+            name: name(entity, "Directional light"),
+            kind: "directional",
+            color: linear_rgb(light.color),
+            intensity: light.illuminance,
+            range: None,
+            position: None,
+            direction: Some(transform.forward().to_array()),
+            spot: None,
+            shadows: light.shadows_enabled,
+            group: None,
+            fixture: None,
+        });
+    }
+
+    let count = lights.len();
+    match serde_json::to_string_pretty(&ExportedLights { lights }) {
+        Ok(json) => match platform::write(EXPORTED_LIGHTS_PATH, json) {
+            Ok(()) => info!("wrote {count} lights to {EXPORTED_LIGHTS_PATH}"),
+            Err(err) => error!("could not write {EXPORTED_LIGHTS_PATH}: {err}"),
+        },
+        Err(err) => error!("could not serialize the lights: {err}"),
+    }
+    app_exit.send(AppExit);
+}
@@ -0,0 +1,167 @@
+use bevy::{
+    pbr::{wireframe::Wireframe, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::view::RenderLayers,
+};
+
+use crate::plugins::{
+    camera::{MainCamera, DEBUG_LAYER},
+    lights::LightGroup,
+};
+
+/// Shows the range of each point light as a wireframe sphere with a label, toggled with `G`.
+///
+/// Bevy 0.9 has no gizmos, this relies on the wireframe rendering set up by the
+/// [`MaterialPresetPlugin`](crate::material_preset::MaterialPresetPlugin).
+pub struct LightGizmosPlugin;
+
+impl Plugin for LightGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowLightGizmos>()
+            .add_startup_system(setup_light_gizmos)
+            .add_system(toggle_light_gizmos)
+            .add_system(spawn_light_gizmos)
+            .add_system(update_light_gizmos.after(spawn_light_gizmos));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ShowLightGizmos {
+    pub enabled: bool,
+}
+
+// Sphere of radius 1, scaled to the range of the light
+#[derive(Component)]
+struct LightGizmo {
+    light: Entity,
+}
+
+#[derive(Component)]
+struct LightGizmoLabel {
+    light: Entity,
+}
+
+#[derive(Resource)]
+struct LightGizmoAssets {
+    mesh: Handle<Mesh>,
+    font: Handle<Font>,
+}
+
+fn setup_light_gizmos(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(LightGizmoAssets {
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 16,
+            stacks: 8,
+        })),
+        font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+    });
+}
+
+fn toggle_light_gizmos(input: Res<Input<KeyCode>>, mut show: ResMut<ShowLightGizmos>) {
+    if input.just_pressed(KeyCode::G) {
+        show.enabled = !show.enabled;
+        info!("light gizmos: {}", show.enabled);
+    }
+}
+
+fn spawn_light_gizmos(
+    mut commands: Commands,
+    assets: Res<LightGizmoAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    lights: Query<(Entity, &PointLight, Option<&LightGroup>), Added<PointLight>>,
+) {
+    for (entity, light, group) in lights.iter() {
+        let tag = group.map_or_else(|| "Point light".to_string(), |group| group.0.to_string());
+        let mut color = light.color;
+        color.set_a(0.05);
+        commands.spawn((
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            Wireframe,
+            NotShadowCaster,
+            NotShadowReceiver,
+            RenderLayers::layer(DEBUG_LAYER),
+            LightGizmo { light: entity },
+        ));
+        commands.spawn((
+            TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..TextBundle::from_section(
+                    tag,
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 14.0,
+                        color: light.color,
+                    },
+                )
+            },
+            LightGizmoLabel { light: entity },
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_light_gizmos(
+    mut commands: Commands,
+    show: Res<ShowLightGizmos>,
+    lights: Query<(&PointLight, &GlobalTransform)>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut gizmos: Query<(Entity, &LightGizmo, &mut Transform, &mut Visibility)>,
+    mut labels: Query<(Entity, &LightGizmoLabel, &mut Style, &mut Visibility), Without<LightGizmo>>,
+) {
+    for (entity, gizmo, mut transform, mut visibility) in gizmos.iter_mut() {
+        match lights.get(gizmo.light) {
+            Ok((light, light_transform)) => {
+                // Lights that are off have no gizmo
+                visibility.is_visible = show.enabled && light.intensity != 0.0;
+                if visibility.is_visible {
+                    *transform = Transform::from_translation(light_transform.translation())
+                        .with_scale(Vec3::splat(light.range));
+                }
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    for (entity, label, mut style, mut visibility) in labels.iter_mut() {
+        match lights.get(label.light) {
+            Ok((light, light_transform)) => {
+                let position = camera
+                    .world_to_viewport(camera_transform, light_transform.translation())
+                    .filter(|_| show.enabled && light.intensity != 0.0);
+                visibility.is_visible = position.is_some();
+                if let Some(position) = position {
+                    // Viewport coordinates start from the bottom left corner
+                    style.position = UiRect {
+                        left: Val::Px(position.x),
+                        bottom: Val::Px(position.y),
+                        ..default()
+                    };
+                }
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+}
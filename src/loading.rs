@@ -0,0 +1,253 @@
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{
+    annotations::ANNOTATIONS_PATH,
+    cinematic::CINEMATIC_PATH,
+    hdri_skybox::HDRI_SKYBOX_SHADER_PATH,
+    plugins::{
+        lights::LIGHT_SETTINGS_PATH,
+        scene::{SceneLoadStage, Scenes},
+    },
+    post_processing::POST_PROCESSING_SHADER_PATH,
+    scene_fix::SCENE_FIX_MANIFEST_PATH,
+    screenshot::SCREENSHOT_SHADER_PATH,
+    shadow_debug::SHADOW_DEBUG_SHADER_PATH,
+    sky::SKY_SHADER_PATH,
+    streaming::SCENE_STREAMING_PATH,
+};
+
+// Rough number of named entities in each scene, to estimate the progress
+pub const INTERIOR_NAMED_ENTITIES: usize = 900;
+pub const EXTERIOR_NAMED_ENTITIES: usize = 2800;
+
+// Assets used by the plugins, loaded before the loading screen is closed. The scenes are added by
+// the scene plugin, depending on the command line
+const PRELOADED_ASSETS: [&str; 13] = [
+    LIGHT_SETTINGS_PATH,
+    SCENE_FIX_MANIFEST_PATH,
+    CINEMATIC_PATH,
+    ANNOTATIONS_PATH,
+    "fonts/DejaVuSansMono.ttf",
+    "luts/warm.png",
+    "luts/cold.png",
+    POST_PROCESSING_SHADER_PATH,
+    SKY_SHADER_PATH,
+    SHADOW_DEBUG_SHADER_PATH,
+    HDRI_SKYBOX_SHADER_PATH,
+    SCREENSHOT_SHADER_PATH,
+    SCENE_STREAMING_PATH,
+];
+
+/// Fullscreen loading screen with a progress bar, until both scenes are spawned and fixed, and
+/// all the preloaded assets are loaded. The assets still loading are listed under the bar.
+///
+/// The [`GameState`] goes to [`GameState::Playing`] when the loading screen is closed, and
+/// [`ScenesReady`] is set.
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(GameState::Loading)
+            .init_resource::<LoadingState>()
+            .init_resource::<ScenesReady>()
+            .add_startup_system(setup_loading_screen)
+            .add_startup_system(preload_assets)
+            .add_system(update_loading_screen.label(ScenesReadySystem));
+    }
+}
+
+/// Whether the loading screen is closed. It stays [`GameState::Playing`] while the exterior is
+/// streamed out and back in, see [`ScenesReady`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum GameState {
+    Loading,
+    Playing,
+}
+
+/// Whether the scenes are spawned and fixed. The scene streaming clears it while the exterior is
+/// unloaded, and sets it again once the exterior is fixed, so that the systems fixing up the
+/// scenes run again when it changes.
+#[derive(Resource, Default)]
+pub struct ScenesReady(pub bool);
+
+/// Label of the systems setting [`ScenesReady`], systems reacting to it should run after them.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScenesReadySystem;
+
+/// Named entities processed by the scene update systems, out of an estimated total, and the assets
+/// that must be loaded before the loading screen is closed.
+#[derive(Resource, Default)]
+pub struct LoadingState {
+    pub processed_entities: usize,
+    pub estimated_entities: usize,
+    // Kept alive until the loading screen is closed, with their path
+    preloaded: Vec<(String, HandleUntyped)>,
+}
+
+impl LoadingState {
+    pub fn preload(&mut self, asset_server: &AssetServer, path: &str) {
+        self.preloaded
+            .push((path.to_string(), asset_server.load_untyped(path)));
+    }
+
+    // Assets not loaded yet. Failed assets are not waited for, their plugin logs the error
+    fn pending_assets<'a>(
+        &'a self,
+        asset_server: &'a AssetServer,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.preloaded
+            .iter()
+            .filter(|(_, handle)| {
+                !matches!(
+                    asset_server.get_load_state(handle),
+                    LoadState::Loaded | LoadState::Failed
+                )
+            })
+            .map(|(path, _)| path.as_str())
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.estimated_entities == 0 {
+            return 0.0;
+        }
+        (self.processed_entities as f32 / self.estimated_entities as f32).min(1.0)
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct ProgressBar;
+
+#[derive(Component)]
+struct PendingAssetsText;
+
+fn preload_assets(asset_server: Res<AssetServer>, mut state: ResMut<LoadingState>) {
+    for path in PRELOADED_ASSETS {
+        state.preload(&asset_server, path);
+    }
+}
+
+fn setup_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/DejaVuSansMono.ttf");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.1).into(),
+                ..default()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|screen| {
+            screen.spawn(
+                TextBundle::from_section(
+                    "Loading the Bistro...",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                }),
+            );
+            screen
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Px(20.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                    ..default()
+                })
+                .with_children(|bar| {
+                    bar.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::rgb(1.0, 0.9, 0.4).into(),
+                            ..default()
+                        },
+                        ProgressBar,
+                    ));
+                });
+            screen.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 16.0,
+                        color: Color::GRAY,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(10.0)),
+                    ..default()
+                }),
+                PendingAssetsText,
+            ));
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenes: Option<Res<Scenes>>,
+    load_stage: Res<SceneLoadStage>,
+    mut state: ResMut<LoadingState>,
+    mut game_state: ResMut<State<GameState>>,
+    mut ready: ResMut<ScenesReady>,
+    screen: Query<Entity, With<LoadingScreen>>,
+    mut bar: Query<&mut Style, With<ProgressBar>>,
+    mut text: Query<&mut Text, With<PendingAssetsText>>,
+) {
+    let scenes = match scenes {
+        Some(scenes) => scenes,
+        None => return,
+    };
+    if screen.is_empty() {
+        return;
+    }
+    let pending = state.pending_assets(&asset_server).collect::<Vec<_>>();
+    // Scene update systems clear the instances once the scenes are ready
+    if scenes.interior.is_none()
+        && scenes.exterior.is_none()
+        && load_stage.interior_ready
+        && load_stage.exterior_ready
+        && pending.is_empty()
+    {
+        for entity in screen.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        // The plugins keep their own handles to the assets they use
+        state.preloaded.clear();
+        ready.0 = true;
+        if let Err(err) = game_state.set(GameState::Playing) {
+            warn!("could not start playing: {err:?}");
+        }
+        return;
+    }
+    let pending = pending.join("\n");
+    for mut text in text.iter_mut() {
+        if text.sections[0].value != pending {
+            text.sections[0].value = pending.clone();
+        }
+    }
+    for mut style in bar.iter_mut() {
+        style.size.width = Val::Percent(state.progress() * 100.0);
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::{
+    asset::HandleId,
+    pbr::wireframe::{WireframeConfig, WireframePlugin},
+    prelude::*,
+    utils::HashMap,
+};
+
+/// Switch all materials between presets with `M`, to inspect the geometry. `Shift+M` is left to the
+/// minimap. Materials added later, like the exterior streamed back in, get the current preset too.
+///
+/// Requires the `POLYGON_MODE_LINE` wgpu feature for the wireframe preset, which is skipped on the
+/// web.
+pub struct MaterialPresetPlugin;
+
+impl Plugin for MaterialPresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(WireframePlugin)
+            .init_resource::<RenderSettings>()
+            .init_resource::<OriginalMaterials>()
+            .add_system(cycle_material_preset)
+            .add_system(apply_material_preset.after(cycle_material_preset));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MaterialPreset {
+    // Materials as loaded from the scenes
+    #[default]
+    Realistic,
+    // Flat, non reflective materials
+    Cel,
+    // Unlit materials with wireframes on top
+    Wireframe,
+}
+
+impl MaterialPreset {
+    fn next(self) -> Self {
+        match self {
+            MaterialPreset::Realistic => MaterialPreset::Cel,
+            // WebGL2 can't draw lines
+            MaterialPreset::Cel if cfg!(target_arch = "wasm32") => MaterialPreset::Realistic,
+            MaterialPreset::Cel => MaterialPreset::Wireframe,
+            MaterialPreset::Wireframe => MaterialPreset::Realistic,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RenderSettings {
+    pub current_preset: MaterialPreset,
+}
+
+// Material values from the scenes, to restore them when going back to the realistic preset
+#[derive(Resource, Default)]
+struct OriginalMaterials(HashMap<HandleId, (f32, f32, bool)>);
+
+fn cycle_material_preset(input: Res<Input<KeyCode>>, mut settings: ResMut<RenderSettings>) {
+    if input.just_pressed(KeyCode::M) && !input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        settings.current_preset = settings.current_preset.next();
+        info!("material preset: {:?}", settings.current_preset);
+    }
+}
+
+// On the materials added since, like the exterior streamed back in or the helpers spawned, when
+// the preset doesn't change
+fn apply_material_preset(
+    settings: Res<RenderSettings>,
+    mut originals: ResMut<OriginalMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut wireframe: ResMut<WireframeConfig>,
+    mut events: EventReader<AssetEvent<StandardMaterial>>,
+) {
+    let mut created = Vec::new();
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } => created.push(handle.clone_weak()),
+            AssetEvent::Removed { handle } => {
+                originals.0.remove(&handle.id());
+            }
+            AssetEvent::Modified { .. } => {}
+        }
+    }
+    let preset = settings.current_preset;
+    if settings.is_changed() {
+        wireframe.global = preset == MaterialPreset::Wireframe;
+        for (id, material) in materials.iter_mut() {
+            set_material_preset(preset, id, material, &mut originals);
+        }
+    } else if preset != MaterialPreset::Realistic {
+        for handle in created {
+            if let Some(material) = materials.get_mut(&handle) {
+                set_material_preset(preset, handle.id(), material, &mut originals);
+            }
+        }
+    }
+}
+
+fn set_material_preset(
+    preset: MaterialPreset,
+    id: HandleId,
+    material: &mut StandardMaterial,
+    originals: &mut OriginalMaterials,
+) {
+    let (metallic, perceptual_roughness, unlit) = *originals.0.entry(id).or_insert((
+        material.metallic,
+        material.perceptual_roughness,
+        material.unlit,
+    ));
+    match preset {
+        MaterialPreset::Realistic => {
+            material.metallic = metallic;
+            material.perceptual_roughness = perceptual_roughness;
+            material.unlit = unlit;
+        }
+        MaterialPreset::Cel => {
+            material.metallic = 0.0;
+            material.perceptual_roughness = 1.0;
+            material.unlit = unlit;
+        }
+        MaterialPreset::Wireframe => {
+            material.metallic = metallic;
+            material.perceptual_roughness = perceptual_roughness;
+            material.unlit = true;
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        primitives::Aabb,
+        render_resource::PrimitiveTopology,
+    },
+    utils::HashMap,
+};
+
+// How far the new vertices are pushed towards the surface described by the normals
+const PHONG_SHAPE_FACTOR: f32 = 0.75;
+
+/// Scale of the Bistro scenes: their root node scales the vertex positions by this factor to
+/// get them in meters.
+///
+/// Some meshes (lights, doors, ...) are not transformed to their place in the scene, their
+/// vertices are, so their position in the world is their vertex positions scaled by this factor.
+pub const BISTRO_SCALE: f32 = 0.016;
+
+/// Center of the vertices of the mesh, scaled by `scale`.
+///
+/// Returns `None` if the mesh has no vertex, or no `Float32x3` position attribute.
+pub fn mesh_center_world(mesh: &Mesh, scale: f32) -> Option<Vec3> {
+    let positions = mesh_positions(mesh)?;
+    if positions.is_empty() {
+        return None;
+    }
+    let sum = positions
+        .iter()
+        .fold(Vec3::ZERO, |acc, v| acc + Vec3::from(*v));
+    Some(sum / positions.len() as f32 * scale)
+}
+
+/// Size of the box containing the vertices of the mesh, scaled by `scale`.
+///
+/// Returns `None` if the mesh has no vertex, or no `Float32x3` position attribute.
+pub fn mesh_size_world(mesh: &Mesh, scale: f32) -> Option<Vec3> {
+    let positions = mesh_positions(mesh)?;
+    if positions.is_empty() {
+        return None;
+    }
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), v| (min.min(Vec3::from(*v)), max.max(Vec3::from(*v))),
+    );
+    Some((max - min) * scale)
+}
+
+/// Bounds of a mesh in world space, as min and max corners, grown by `margin` on every side.
+pub fn world_bounds(aabb: &Aabb, transform: &GlobalTransform, margin: f32) -> (Vec3, Vec3) {
+    let affine = transform.affine();
+    let center = Vec3::from(affine.transform_point3a(aabb.center));
+    let half_extents = affine.matrix3.x_axis.abs() * aabb.half_extents.x
+        + affine.matrix3.y_axis.abs() * aabb.half_extents.y
+        + affine.matrix3.z_axis.abs() * aabb.half_extents.z;
+    let half_extents = Vec3::from(half_extents) + Vec3::splat(margin);
+    (center - half_extents, center + half_extents)
+}
+
+/// Splits each triangle of the mesh in four, with the new vertices in the middle of the edges.
+///
+/// All the attributes are interpolated. When the mesh has normals, the new vertices are moved
+/// towards the curved surface they describe (Phong tessellation), so that curved surfaces get
+/// smoother instead of only denser.
+///
+/// Returns `None` if the mesh is not a triangle list, has no `Float32x3` position attribute, or
+/// has an attribute that is not made of floats.
+pub fn subdivide(mesh: &Mesh) -> Option<Mesh> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let positions = mesh_positions(mesh)?.clone();
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) => Some(normals.clone()),
+        _ => None,
+    };
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(indices) => indices.iter().map(|index| index as u32).collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    // New vertex in the middle of each edge, shared by the triangles on both sides
+    let mut midpoints: HashMap<(u32, u32), u32> = HashMap::default();
+    let mut edges = Vec::new();
+    let mut midpoint = |a: u32, b: u32| {
+        *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            edges.push((a, b));
+            (positions.len() + edges.len() - 1) as u32
+        })
+    };
+    let mut new_indices = Vec::with_capacity(indices.len() * 4);
+    for triangle in indices.chunks_exact(3) {
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+        new_indices.extend([a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+    }
+
+    if mesh
+        .attributes()
+        .any(|(_, values)| values.len() != positions.len())
+    {
+        return None;
+    }
+    let mut subdivided = mesh.clone();
+    for (id, values) in subdivided.attributes_mut() {
+        let normalize = id == Mesh::ATTRIBUTE_NORMAL.id || id == Mesh::ATTRIBUTE_TANGENT.id;
+        match values {
+            VertexAttributeValues::Float32x2(values) => {
+                extend_with_midpoints(values, &edges, |a, b| {
+                    ((Vec2::from(*a) + Vec2::from(*b)) / 2.0).to_array()
+                });
+            }
+            VertexAttributeValues::Float32x3(values) => {
+                extend_with_midpoints(values, &edges, |a, b| {
+                    let middle = (Vec3::from(*a) + Vec3::from(*b)) / 2.0;
+                    if normalize {
+                        middle.normalize_or_zero().to_array()
+                    } else {
+                        middle.to_array()
+                    }
+                });
+            }
+            VertexAttributeValues::Float32x4(values) => {
+                extend_with_midpoints(values, &edges, |a, b| {
+                    let middle = (Vec4::from(*a) + Vec4::from(*b)) / 2.0;
+                    if normalize {
+                        // Keep the handedness of the tangent in `w`
+                        middle
+                            .truncate()
+                            .normalize_or_zero()
+                            .extend(a[3])
+                            .to_array()
+                    } else {
+                        middle.to_array()
+                    }
+                });
+            }
+            _ => return None,
+        }
+    }
+
+    if let (Some(normals), Some(VertexAttributeValues::Float32x3(new_positions))) =
+        (normals, subdivided.attribute_mut(Mesh::ATTRIBUTE_POSITION))
+    {
+        for (k, (a, b)) in edges.iter().enumerate() {
+            let (a, b) = (*a as usize, *b as usize);
+            let (position_a, position_b) = (Vec3::from(positions[a]), Vec3::from(positions[b]));
+            let middle = (position_a + position_b) / 2.0;
+            // Project the middle on the tangent planes at both ends of the edge
+            let project =
+                |position: Vec3, normal: Vec3| middle - (middle - position).dot(normal) * normal;
+            let curved = (project(position_a, Vec3::from(normals[a]))
+                + project(position_b, Vec3::from(normals[b])))
+                / 2.0;
+            new_positions[positions.len() + k] = middle.lerp(curved, PHONG_SHAPE_FACTOR).to_array();
+        }
+    }
+    subdivided.set_indices(Some(Indices::U32(new_indices)));
+    Some(subdivided)
+}
+
+fn extend_with_midpoints<T: Copy>(
+    values: &mut Vec<T>,
+    edges: &[(u32, u32)],
+    middle: impl Fn(&T, &T) -> T,
+) {
+    values.reserve(edges.len());
+    for (a, b) in edges {
+        let value = middle(&values[*a as usize], &values[*b as usize]);
+        values.push(value);
+    }
+}
+
+fn mesh_positions(mesh: &Mesh) -> Option<&Vec<[f32; 3]>> {
+    match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => Some(positions),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_with_positions(positions: Vec<[f32; 3]>) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh
+    }
+
+    #[test]
+    fn center_of_empty_mesh() {
+        let mesh = mesh_with_positions(vec![]);
+        assert_eq!(mesh_center_world(&mesh, 1.0), None);
+    }
+
+    #[test]
+    fn center_without_positions() {
+        let mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        assert_eq!(mesh_center_world(&mesh, 1.0), None);
+    }
+
+    #[test]
+    fn center_of_single_vertex() {
+        let mesh = mesh_with_positions(vec![[1.0, -2.0, 3.0]]);
+        assert_eq!(
+            mesh_center_world(&mesh, 2.0),
+            Some(Vec3::new(2.0, -4.0, 6.0))
+        );
+    }
+
+    #[test]
+    fn center_at_bistro_scale() {
+        let mesh = mesh_with_positions(vec![
+            [0.0, 0.0, 0.0],
+            [100.0, 0.0, 0.0],
+            [0.0, 100.0, 0.0],
+            [100.0, 100.0, 200.0],
+        ]);
+        let center = mesh_center_world(&mesh, BISTRO_SCALE).unwrap();
+        assert!(center.abs_diff_eq(Vec3::new(0.8, 0.8, 0.8), 1e-6));
+    }
+}
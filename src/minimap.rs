@@ -0,0 +1,419 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        mesh::Indices,
+        render_resource::{
+            Extent3d, PrimitiveTopology, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        view::RenderLayers,
+    },
+    transform::TransformSystem,
+    utils::HashSet,
+};
+
+use crate::{
+    axes::AXES_VIEWPORT_SIZE,
+    camera_controller::CameraRotationSystem,
+    platform,
+    plugins::{
+        camera::{MainCamera, MINIMAP_LAYER},
+        lights::{LightGroup, LightGroupKind},
+    },
+};
+
+// Size of the minimap, in pixels
+const MINIMAP_SIZE: UVec2 = UVec2::new(256, 256);
+// Range of the area shown, in meters
+const MIN_AREA_SIZE: f32 = 10.0;
+const MAX_AREA_SIZE: f32 = 400.0;
+// Zoom factor per line scrolled
+const ZOOM_PER_LINE: f32 = 1.1;
+// The minimap camera is this far above the main camera, and doesn't see what is higher, so that
+// the roofs don't hide the interior
+const CAMERA_HEIGHT: f32 = 1.0;
+// Size of the markers, as a fraction of the area shown
+const ARROW_SIZE: f32 = 0.04;
+const LIGHT_DOT_SIZE: f32 = 0.012;
+
+/// A top-down view around the main camera in the bottom left corner of the window, with an arrow
+/// for the camera and a dot for each point light, colored by group and grayed when off.
+///
+/// Toggled with `Shift+M`, as `M` cycles the material presets. The area shown is zoomed with
+/// `Ctrl+Scroll`. The arrow and the dots are on their own layer, only seen by the minimap.
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MinimapCamera>()
+            .init_resource::<Minimap>()
+            .add_system(minimap_input)
+            .add_system(spawn_minimap.after(minimap_input))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_minimap
+                    .after(CameraRotationSystem)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct Minimap {
+    pub enabled: bool,
+    // Height of the area shown, in meters
+    pub area_size: f32,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            area_size: 40.0,
+        }
+    }
+}
+
+/// The camera rendering the minimap.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct MinimapCamera;
+
+#[derive(Component)]
+struct MinimapArrow;
+
+#[derive(Component)]
+struct MinimapLightDot {
+    light: Entity,
+}
+
+// Entities spawned while enabled, with the image of the camera
+#[derive(Default)]
+struct MinimapEntities {
+    camera: Option<Entity>,
+    picture: Option<Entity>,
+    arrow: Option<Entity>,
+    image: Option<Handle<Image>>,
+}
+
+fn minimap_input(
+    input: Res<Input<KeyCode>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    mut minimap: ResMut<Minimap>,
+) {
+    if input.just_pressed(KeyCode::M) && input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        minimap.enabled = !minimap.enabled;
+        info!("minimap: {}", minimap.enabled);
+    }
+    // Without the modifier, scrolling zooms the main camera
+    if !minimap.enabled || !input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        return;
+    }
+    let scroll: f32 = wheel_events
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // Roughly the height of a line
+            MouseScrollUnit::Pixel => event.y / 20.0,
+        })
+        .sum();
+    if scroll != 0.0 {
+        // Scrolling up shows a smaller area
+        minimap.area_size =
+            (minimap.area_size / ZOOM_PER_LINE.powf(scroll)).clamp(MIN_AREA_SIZE, MAX_AREA_SIZE);
+    }
+}
+
+// A flat arrow pointing along -Z, seen from above
+fn arrow_mesh() -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        vec![
+            [0.0, 0.0, -0.5],
+            [-0.35, 0.0, 0.5],
+            [0.0, 0.0, 0.25],
+            [0.35, 0.0, 0.5],
+        ],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 4]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; 4]);
+    mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3])));
+    mesh
+}
+
+fn spawn_minimap(
+    mut commands: Commands,
+    minimap: Res<Minimap>,
+    mut spawned: Local<MinimapEntities>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    dots: Query<Entity, With<MinimapLightDot>>,
+) {
+    if !minimap.is_changed() {
+        return;
+    }
+    if !minimap.enabled {
+        for entity in [
+            spawned.camera.take(),
+            spawned.picture.take(),
+            spawned.arrow.take(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(dots.iter())
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+        // Free the texture now, without waiting for the handles to be dropped with the entities
+        if let Some(image) = spawned.image.take() {
+            images.remove(image);
+        }
+        return;
+    }
+    if spawned.camera.is_some() {
+        return;
+    }
+
+    let size = Extent3d {
+        width: MINIMAP_SIZE.x,
+        height: MINIMAP_SIZE.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("minimap"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+    let image = images.add(image);
+
+    spawned.camera = Some(
+        commands
+            .spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        // Before the main camera, so that the picture is ready when the UI is drawn
+                        priority: -1,
+                        target: RenderTarget::Image(image.clone()),
+                        ..default()
+                    },
+                    projection: OrthographicProjection {
+                        scaling_mode: ScalingMode::FixedVertical(2.0),
+                        scale: minimap.area_size / 2.0,
+                        ..default()
+                    }
+                    .into(),
+                    ..default()
+                },
+                UiCameraConfig { show_ui: false },
+                RenderLayers::layer(0).with(MINIMAP_LAYER),
+                MinimapCamera,
+            ))
+            .id(),
+    );
+    spawned.picture = Some(
+        commands
+            .spawn(ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    // Right of the axes
+                    position: UiRect {
+                        bottom: Val::Px(10.0),
+                        left: Val::Px(AXES_VIEWPORT_SIZE as f32 + 10.0),
+                        ..default()
+                    },
+                    size: Size::new(
+                        Val::Px(MINIMAP_SIZE.x as f32),
+                        Val::Px(MINIMAP_SIZE.y as f32),
+                    ),
+                    ..default()
+                },
+                image: UiImage(image.clone()),
+                ..default()
+            })
+            .id(),
+    );
+    spawned.arrow = Some(
+        commands
+            .spawn((
+                PbrBundle {
+                    mesh: meshes.add(arrow_mesh()),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::WHITE,
+                        unlit: true,
+                        cull_mode: None,
+                        ..default()
+                    }),
+                    ..default()
+                },
+                NotShadowCaster,
+                NotShadowReceiver,
+                RenderLayers::layer(MINIMAP_LAYER),
+                MinimapArrow,
+            ))
+            .id(),
+    );
+    spawned.image = Some(image);
+}
+
+fn group_color(kind: LightGroupKind) -> Color {
+    match kind {
+        LightGroupKind::Ceiling => Color::YELLOW,
+        LightGroupKind::Wall => Color::ORANGE,
+        LightGroupKind::Lantern => Color::ORANGE_RED,
+        LightGroupKind::StreetLight => Color::CYAN,
+    }
+}
+
+// Follow the main camera, and keep a dot on every light
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn update_minimap(
+    mut commands: Commands,
+    minimap: Res<Minimap>,
+    main_camera: Query<&Transform, (With<MainCamera>, Without<MinimapCamera>)>,
+    mut minimap_camera: Query<(&mut Transform, &mut Projection), With<MinimapCamera>>,
+    mut arrow: Query<
+        &mut Transform,
+        (
+            With<MinimapArrow>,
+            Without<MainCamera>,
+            Without<MinimapCamera>,
+        ),
+    >,
+    mut dots: Query<
+        (
+            Entity,
+            &MinimapLightDot,
+            &mut Transform,
+            &mut Handle<StandardMaterial>,
+        ),
+        (
+            Without<MinimapArrow>,
+            Without<MainCamera>,
+            Without<MinimapCamera>,
+        ),
+    >,
+    lights: Query<(Entity, &GlobalTransform, &PointLight, &LightGroup)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    // Dot mesh, and the materials of each group when on, or of all the lights when off
+    mut dot_assets: Local<
+        Option<(
+            Handle<Mesh>,
+            Vec<(Option<LightGroupKind>, Handle<StandardMaterial>)>,
+        )>,
+    >,
+) {
+    if !minimap.enabled {
+        return;
+    }
+    let eye = match main_camera.get_single() {
+        Ok(transform) => *transform,
+        Err(_) => return,
+    };
+    let top = eye.translation + Vec3::Y * CAMERA_HEIGHT;
+    for (mut transform, mut projection) in minimap_camera.iter_mut() {
+        // North up
+        *transform = Transform::from_translation(top).looking_at(eye.translation, Vec3::NEG_Z);
+        if let Projection::Orthographic(orthographic) = projection.as_mut() {
+            if orthographic.scale != minimap.area_size / 2.0 {
+                orthographic.scale = minimap.area_size / 2.0;
+            }
+        }
+    }
+
+    // Markers are just below the minimap camera, over everything it sees
+    let marker_height = top.y - 0.1;
+    let forward = eye.forward();
+    let yaw = (-forward.x).atan2(-forward.z);
+    for mut transform in arrow.iter_mut() {
+        *transform = Transform::from_xyz(eye.translation.x, marker_height, eye.translation.z)
+            .with_rotation(Quat::from_rotation_y(yaw))
+            .with_scale(Vec3::splat(minimap.area_size * ARROW_SIZE));
+    }
+
+    let (dot_mesh, dot_materials) = dot_assets.get_or_insert_with(|| {
+        let dot_materials = [
+            Some(LightGroupKind::Ceiling),
+            Some(LightGroupKind::Wall),
+            Some(LightGroupKind::Lantern),
+            Some(LightGroupKind::StreetLight),
+            None,
+        ]
+        .into_iter()
+        .map(|kind| {
+            let material = materials.add(StandardMaterial {
+                base_color: kind.map_or(Color::DARK_GRAY, group_color),
+                unlit: true,
+                ..default()
+            });
+            (kind, material)
+        })
+        .collect();
+        let mesh = meshes.add(Mesh::from(shape::UVSphere {
+            radius: 0.5,
+            sectors: 8,
+            stacks: 4,
+        }));
+        (mesh, dot_materials)
+    });
+    let dot_transform = |light: &GlobalTransform| {
+        let position = light.translation();
+        Transform::from_xyz(position.x, marker_height, position.z)
+            .with_scale(Vec3::splat(minimap.area_size * LIGHT_DOT_SIZE))
+    };
+    let dot_material = |light: &PointLight, group: &LightGroup| {
+        let kind = (light.intensity > 0.0).then_some(group.0);
+        dot_materials
+            .iter()
+            .find(|(material_kind, _)| *material_kind == kind)
+            .map(|(_, material)| material.clone())
+            .unwrap_or_default()
+    };
+
+    let mut with_dot = HashSet::new();
+    for (entity, dot, mut transform, mut material) in dots.iter_mut() {
+        match lights.get(dot.light) {
+            Ok((_, light_transform, light, group)) => {
+                *transform = dot_transform(light_transform);
+                let wanted = dot_material(light, group);
+                if *material != wanted {
+                    *material = wanted;
+                }
+                with_dot.insert(dot.light);
+            }
+            Err(_) => commands.entity(entity).despawn(),
+        }
+    }
+    for (entity, light_transform, light, group) in lights.iter() {
+        if with_dot.contains(&entity) {
+            continue;
+        }
+        commands.spawn((
+            PbrBundle {
+                mesh: dot_mesh.clone(),
+                material: dot_material(light, group),
+                transform: dot_transform(light_transform),
+                ..default()
+            },
+            NotShadowCaster,
+            NotShadowReceiver,
+            RenderLayers::layer(MINIMAP_LAYER),
+            MinimapLightDot { light: entity },
+        ));
+    }
+}
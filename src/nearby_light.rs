@@ -0,0 +1,127 @@
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use crate::plugins::lights::{LightGroup, LightGroupKind};
+
+// The lights are checked again every this many frames
+const UPDATE_FRAMES: u32 = 30;
+
+/// Keeps a [`NearbyLightInfo`] on every mesh lit by a point light, with the light giving it the
+/// most illuminance. A summary is printed with `I`.
+///
+/// The illuminance at the center of the bounds of the mesh is the luminous intensity of the light
+/// divided by the squared distance, zero past its range or when it is off.
+pub struct NearbyLightPlugin;
+
+impl Plugin for NearbyLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_nearby_lights)
+            .add_system(nearby_lights_info.after(update_nearby_lights));
+    }
+}
+
+/// The point light giving a mesh the most illuminance, in lux.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct NearbyLightInfo {
+    pub strongest_light: Entity,
+    pub illuminance: f32,
+}
+
+#[allow(clippy::type_complexity)]
+fn update_nearby_lights(
+    mut commands: Commands,
+    mut frames: Local<u32>,
+    lights: Query<(Entity, &PointLight, &GlobalTransform, &ComputedVisibility)>,
+    mut meshes: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Aabb,
+            Option<&mut NearbyLightInfo>,
+        ),
+        (With<Handle<Mesh>>, Without<PointLight>),
+    >,
+) {
+    *frames += 1;
+    if *frames < UPDATE_FRAMES {
+        return;
+    }
+    *frames = 0;
+
+    // Position, range and luminous intensity in candelas of the lights that are on
+    let lights = lights
+        .iter()
+        .filter(|(_, light, _, visibility)| light.intensity > 0.0 && visibility.is_visible())
+        .map(|(entity, light, transform, _)| {
+            (
+                entity,
+                transform.translation(),
+                light.range,
+                light.intensity / (4.0 * std::f32::consts::PI),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, transform, aabb, info) in meshes.iter_mut() {
+        // The meshes of the scenes are moved in their vertices, not in their transform
+        let center = transform.transform_point(aabb.center.into());
+        let strongest = lights
+            .iter()
+            .filter_map(|(light, position, range, candelas)| {
+                let distance_squared = position.distance_squared(center);
+                (distance_squared < range * range)
+                    .then(|| (*light, candelas / distance_squared.max(0.0001)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match (strongest, info) {
+            (Some((strongest_light, illuminance)), Some(mut info)) => {
+                info.strongest_light = strongest_light;
+                info.illuminance = illuminance;
+            }
+            (Some((strongest_light, illuminance)), None) => {
+                commands.entity(entity).insert(NearbyLightInfo {
+                    strongest_light,
+                    illuminance,
+                });
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<NearbyLightInfo>();
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+fn nearby_lights_info(
+    input: Res<Input<KeyCode>>,
+    meshes: Query<&NearbyLightInfo>,
+    groups: Query<&LightGroup>,
+) {
+    if !input.just_pressed(KeyCode::I) {
+        return;
+    }
+    info!("{} meshes are lit by a point light", meshes.iter().count());
+    for kind in [
+        LightGroupKind::Ceiling,
+        LightGroupKind::Wall,
+        LightGroupKind::Lantern,
+        LightGroupKind::StreetLight,
+    ] {
+        let lit = meshes
+            .iter()
+            .filter(|info| {
+                groups
+                    .get(info.strongest_light)
+                    .is_ok_and(|group| group.0 == kind)
+            })
+            .map(|info| info.illuminance)
+            .collect::<Vec<_>>();
+        if lit.is_empty() {
+            continue;
+        }
+        let brightest = lit.iter().copied().fold(0.0, f32::max);
+        info!(
+            "{kind} | strongest light of {} meshes, up to {brightest:.0} lux",
+            lit.len()
+        );
+    }
+}
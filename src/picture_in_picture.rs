@@ -0,0 +1,155 @@
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+    },
+};
+
+use crate::plugins::camera::SCENE_CAMERA_LAYERS;
+
+// Size of the picture, in pixels
+const PICTURE_SIZE: UVec2 = UVec2::new(384, 216);
+
+/// A security camera in a corner of the bistro, shown in the top right corner of the window.
+///
+/// Toggled with `K`, as `V` switches the camera walk mode. The main camera is not affected.
+pub struct PictureInPicturePlugin;
+
+impl Plugin for PictureInPicturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PictureInPicture>()
+            .add_system(toggle_picture_in_picture)
+            .add_system(update_picture_in_picture.after(toggle_picture_in_picture));
+    }
+}
+
+#[derive(Resource)]
+pub struct PictureInPicture {
+    pub enabled: bool,
+    // High in a corner of the interior, looking at the tables
+    pub transform: Transform,
+}
+
+impl Default for PictureInPicture {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            transform: Transform::from_xyz(0.5, 2.8, -1.5)
+                .looking_at(Vec3::new(6.0, 0.8, 2.0), Vec3::Y),
+        }
+    }
+}
+
+// Camera and picture spawned while enabled, with the image they share
+#[derive(Default)]
+struct PictureInPictureEntities {
+    camera: Option<Entity>,
+    picture: Option<Entity>,
+    image: Option<Handle<Image>>,
+}
+
+fn toggle_picture_in_picture(input: Res<Input<KeyCode>>, mut pip: ResMut<PictureInPicture>) {
+    if input.just_pressed(KeyCode::K) {
+        pip.enabled = !pip.enabled;
+        info!("security camera: {}", pip.enabled);
+    }
+}
+
+fn update_picture_in_picture(
+    mut commands: Commands,
+    pip: Res<PictureInPicture>,
+    mut spawned: Local<PictureInPictureEntities>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !pip.is_changed() {
+        return;
+    }
+    if !pip.enabled {
+        for entity in [spawned.camera.take(), spawned.picture.take()]
+            .into_iter()
+            .flatten()
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+        // Free the texture now, without waiting for the handles to be dropped with the entities
+        if let Some(image) = spawned.image.take() {
+            images.remove(image);
+        }
+        return;
+    }
+    if let Some(camera) = spawned.camera {
+        commands.entity(camera).insert(pip.transform);
+        return;
+    }
+
+    let size = Extent3d {
+        width: PICTURE_SIZE.x,
+        height: PICTURE_SIZE.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("picture_in_picture"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+    let image = images.add(image);
+
+    spawned.camera = Some(
+        commands
+            .spawn((
+                Camera3dBundle {
+                    camera: Camera {
+                        // Before the main camera, so that the picture is ready when the UI is drawn
+                        priority: -1,
+                        target: RenderTarget::Image(image.clone()),
+                        ..default()
+                    },
+                    projection: PerspectiveProjection {
+                        fov: 60.0_f32.to_radians(),
+                        ..default()
+                    }
+                    .into(),
+                    transform: pip.transform,
+                    ..default()
+                },
+                UiCameraConfig { show_ui: false },
+                SCENE_CAMERA_LAYERS,
+            ))
+            .id(),
+    );
+    spawned.picture = Some(
+        commands
+            .spawn(ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(10.0),
+                        right: Val::Px(10.0),
+                        ..default()
+                    },
+                    size: Size::new(
+                        Val::Px(PICTURE_SIZE.x as f32),
+                        Val::Px(PICTURE_SIZE.y as f32),
+                    ),
+                    ..default()
+                },
+                image: UiImage(image.clone()),
+                ..default()
+            })
+            .id(),
+    );
+    spawned.image = Some(image);
+}
@@ -0,0 +1,99 @@
+// Differences between the native and the web builds
+//
+// The web build needs the `wasm32-unknown-unknown` target and trunk, and is served with the assets
+// from the root of the repository:
+//
+//     rustup target add wasm32-unknown-unknown
+//     cargo install trunk
+//     trunk serve --release
+//
+// Command line options are read from the query string of the page instead, like
+// `?interior-only&point-shadow-size=1024`. Files written next to the executable natively are kept
+// in the local storage of the browser.
+
+use bevy::prelude::*;
+
+/// Modifier of the shortcuts that use ctrl natively. Browsers already use ctrl + r, ctrl + a and
+/// ctrl + 1..9, so shift is used instead on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub const SHORTCUT_MODIFIER: [KeyCode; 2] = [KeyCode::LControl, KeyCode::RControl];
+#[cfg(target_arch = "wasm32")]
+pub const SHORTCUT_MODIFIER: [KeyCode; 2] = [KeyCode::LShift, KeyCode::RShift];
+
+/// Name of [`SHORTCUT_MODIFIER`], for the help messages.
+#[cfg(not(target_arch = "wasm32"))]
+pub const SHORTCUT_MODIFIER_NAME: &str = "ctrl";
+#[cfg(target_arch = "wasm32")]
+pub const SHORTCUT_MODIFIER_NAME: &str = "shift";
+
+/// Window settings, rendering in the `#bevy` canvas of `index.html` on the web.
+pub fn window_plugin() -> WindowPlugin {
+    #[cfg(target_arch = "wasm32")]
+    {
+        WindowPlugin {
+            window: WindowDescriptor {
+                canvas: Some("#bevy".to_string()),
+                fit_canvas_to_parent: true,
+                ..default()
+            },
+            ..default()
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    WindowPlugin::default()
+}
+
+/// Arguments of the program, from the query string of the page on the web.
+pub fn args() -> Vec<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let query = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        query
+            .trim_start_matches('?')
+            .split('&')
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| format!("--{arg}"))
+            .collect()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    std::env::args().skip(1).collect()
+}
+
+/// Content of a file in the working directory, or in the local storage on the web.
+pub fn read_to_string(path: &str) -> std::io::Result<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        local_storage()?
+            .get_item(path)
+            .map_err(js_error)?
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    std::fs::read_to_string(path)
+}
+
+/// Write a file in the working directory, or in the local storage on the web.
+pub fn write(path: &str, content: impl AsRef<str>) -> std::io::Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        local_storage()?
+            .set_item(path, content.as_ref())
+            .map_err(js_error)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    std::fs::write(path, content.as_ref())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> std::io::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Unsupported, "no local storage"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(err: wasm_bindgen::JsValue) -> std::io::Error {
+    std::io::Error::other(format!("{err:?}"))
+}
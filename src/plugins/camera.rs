@@ -0,0 +1,347 @@
+use bevy::{
+    app::AppExit,
+    core_pipeline::{bloom, tonemapping::Tonemapping},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera_controller::{CameraController, CameraControllerPlugin, CollisionAvoidance},
+    camera_slots::CameraSlot,
+    cli, platform,
+    plugins::scene::SceneMode,
+    post_processing::PostProcessingStack,
+};
+
+/// Spawns the street, bar and overhead cameras with their [`CameraController`], and their bloom
+/// toggled with `B`.
+///
+/// The position of the [`MainCamera`] is restored at startup and saved on exit. Camera bookmarks
+/// are saved with `Ctrl+1..9` and restored with `Alt+1..9`.
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(CameraControllerPlugin)
+            .register_type::<MainCamera>()
+            .add_startup_system(setup_cameras)
+            .add_startup_system(load_camera_bookmarks)
+            .add_startup_system_to_stage(StartupStage::PostStartup, load_camera_state)
+            .add_startup_system_to_stage(StartupStage::PostStartup, setup_bloom)
+            .add_system(toggle_bloom)
+            .add_system(camera_bookmarks)
+            .add_system(camera_info)
+            .add_system_to_stage(CoreStage::Last, save_camera_state);
+    }
+}
+
+const CAMERA_STATE_PATH: &str = "camera_state.toml";
+
+#[derive(Serialize, Deserialize)]
+struct CameraState {
+    translation: [f32; 3],
+    rotation: [f32; 4],
+}
+
+impl From<&Transform> for CameraState {
+    fn from(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+        }
+    }
+}
+
+impl CameraState {
+    fn apply(&self, transform: &mut Transform) {
+        transform.translation = Vec3::from(self.translation);
+        transform.rotation = Quat::from_array(self.rotation).normalize();
+    }
+}
+
+// Restore the camera position saved on last exit
+fn load_camera_state(
+    scene_mode: Res<SceneMode>,
+    mut camera: Query<&mut Transform, (With<CameraController>, With<MainCamera>)>,
+) {
+    // The saved position could be in the scene that is not loaded
+    if *scene_mode != SceneMode::Both {
+        return;
+    }
+    let state = match platform::read_to_string(CAMERA_STATE_PATH) {
+        Ok(content) => match toml::from_str::<CameraState>(&content) {
+            Ok(state) => state,
+            Err(err) => {
+                warn!("invalid {CAMERA_STATE_PATH}, using default camera position: {err}");
+                return;
+            }
+        },
+        Err(err) => {
+            warn!("could not read {CAMERA_STATE_PATH}, using default camera position: {err}");
+            return;
+        }
+    };
+    for mut transform in camera.iter_mut() {
+        state.apply(&mut transform);
+    }
+}
+
+// Save the camera position when the app exits
+fn save_camera_state(
+    mut app_exit: EventReader<AppExit>,
+    camera: Query<&Transform, (With<CameraController>, With<MainCamera>)>,
+) {
+    if app_exit.iter().last().is_none() {
+        return;
+    }
+    if let Ok(transform) = camera.get_single() {
+        match toml::to_string(&CameraState::from(transform)) {
+            Ok(content) => {
+                if let Err(err) = platform::write(CAMERA_STATE_PATH, content) {
+                    warn!("could not write {CAMERA_STATE_PATH}: {err}");
+                }
+            }
+            Err(err) => warn!("could not serialize camera state: {err}"),
+        }
+    }
+}
+
+const CAMERA_BOOKMARKS_PATH: &str = "camera_bookmarks.json";
+
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+// Named camera positions, saved with ctrl + 1..9 and restored with alt + 1..9
+#[derive(Resource, Default)]
+struct CameraBookmarks(HashMap<String, Transform>);
+
+impl CameraBookmarks {
+    fn load() -> Self {
+        let content = match platform::read_to_string(CAMERA_BOOKMARKS_PATH) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        match serde_json::from_str::<HashMap<String, CameraState>>(&content) {
+            Ok(states) => Self(
+                states
+                    .into_iter()
+                    .map(|(name, state)| {
+                        let mut transform = Transform::default();
+                        state.apply(&mut transform);
+                        (name, transform)
+                    })
+                    .collect(),
+            ),
+            Err(err) => {
+                warn!("invalid {CAMERA_BOOKMARKS_PATH}, ignoring saved bookmarks: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let states: HashMap<_, _> = self
+            .0
+            .iter()
+            .map(|(name, transform)| (name, CameraState::from(transform)))
+            .collect();
+        match serde_json::to_string_pretty(&states) {
+            Ok(content) => {
+                if let Err(err) = platform::write(CAMERA_BOOKMARKS_PATH, content) {
+                    warn!("could not write {CAMERA_BOOKMARKS_PATH}: {err}");
+                }
+            }
+            Err(err) => warn!("could not serialize camera bookmarks: {err}"),
+        }
+    }
+}
+
+fn load_camera_bookmarks(mut commands: Commands) {
+    commands.insert_resource(CameraBookmarks::load());
+}
+
+fn camera_bookmarks(
+    input: Res<Input<KeyCode>>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut camera: Query<(&mut Transform, &mut CameraController), With<MainCamera>>,
+) {
+    let ctrl = input.any_pressed(platform::SHORTCUT_MODIFIER);
+    let alt = input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    if !ctrl && !alt {
+        return;
+    }
+    for (index, key) in BOOKMARK_KEYS.iter().enumerate() {
+        if !input.just_pressed(*key) {
+            continue;
+        }
+        let slot = format!("{}", index + 1);
+        for (mut transform, mut controller) in camera.iter_mut() {
+            if ctrl {
+                info!("saving camera bookmark {slot}");
+                bookmarks.0.insert(slot.clone(), *transform);
+                bookmarks.save();
+            } else if let Some(bookmark) = bookmarks.0.get(&slot) {
+                info!("restoring camera bookmark {slot}");
+                *transform = *bookmark;
+                // Pick up pitch and yaw from the restored rotation
+                controller.initialized = false;
+            }
+        }
+    }
+}
+
+// The camera the scene is viewed from, other cameras only draw overlays
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct MainCamera;
+
+// Layer of the debug and helper meshes. Lights only see the default layer, so those meshes never
+// cast shadows
+pub const DEBUG_LAYER: u8 = 1;
+// Layer of the portal meshes, hidden from the portal cameras
+pub const PORTAL_LAYER: u8 = 4;
+// Layer of the markers drawn over the minimap
+pub const MINIMAP_LAYER: u8 = 5;
+// Layers seen by the cameras showing the scenes
+pub const SCENE_CAMERA_LAYERS: RenderLayers =
+    RenderLayers::layer(0).with(DEBUG_LAYER).with(PORTAL_LAYER);
+
+/// Bloom of the [`PostProcessingStack`], rendered by Bevy on the [`MainCamera`].
+pub struct BloomSettings {
+    pub enabled: bool,
+    // Brightness above which pixels bloom
+    pub threshold: f32,
+    // Bloom intensity at night, lowered during the day
+    pub intensity: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 1.0,
+            intensity: 0.3,
+        }
+    }
+}
+
+impl BloomSettings {
+    fn component(&self) -> bloom::BloomSettings {
+        bloom::BloomSettings {
+            threshold: self.threshold,
+            intensity: self.intensity,
+            ..default()
+        }
+    }
+}
+
+fn setup_bloom(
+    mut commands: Commands,
+    stack: Res<PostProcessingStack>,
+    camera: Query<Entity, With<MainCamera>>,
+) {
+    if let Some(settings) = stack.bloom().filter(|settings| settings.enabled) {
+        for entity in camera.iter() {
+            commands.entity(entity).insert(settings.component());
+        }
+    }
+}
+
+// Street, bar and overhead views, switched with tab. Start in the bar with only the interior
+fn setup_cameras(mut commands: Commands, scene_mode: Res<SceneMode>) {
+    let slot_transforms = [
+        Transform::from_xyz(-16., 6., 1.0).looking_at(Vec3::new(0.0, 1., 0.0), Vec3::Y),
+        Transform::from_xyz(2.0, 1.7, 0.0).looking_at(Vec3::new(6.0, 1.5, 2.0), Vec3::Y),
+        Transform::from_xyz(0.0, 60.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ];
+    let active_slot = if *scene_mode == SceneMode::InteriorOnly {
+        1
+    } else {
+        0
+    };
+    for (slot, transform) in slot_transforms.into_iter().enumerate() {
+        let active = slot == active_slot;
+        let mut camera = commands.spawn((
+            Camera3dBundle {
+                camera: Camera {
+                    hdr: true,
+                    is_active: active,
+                    ..default()
+                },
+                transform,
+                // Tonemapped by the post processing stack
+                tonemapping: Tonemapping::Disabled,
+                ..Default::default()
+            },
+            CameraController {
+                enabled: active,
+                ..default()
+            },
+            CameraSlot(slot as u8),
+            SCENE_CAMERA_LAYERS,
+        ));
+        if active {
+            camera.insert(MainCamera);
+        }
+        if cli::flag("--collision") {
+            camera.insert(CollisionAvoidance::default());
+        }
+    }
+}
+
+fn toggle_bloom(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut stack: ResMut<PostProcessingStack>,
+    camera: Query<Entity, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::B) {
+        return;
+    }
+    let bloom_settings = match stack.bloom_mut() {
+        Some(bloom_settings) => bloom_settings,
+        None => {
+            info!("no bloom in the post processing stack");
+            return;
+        }
+    };
+    bloom_settings.enabled = !bloom_settings.enabled;
+    for entity in camera.iter() {
+        if bloom_settings.enabled {
+            commands.entity(entity).insert(bloom_settings.component());
+        } else {
+            commands.entity(entity).remove::<bloom::BloomSettings>();
+        }
+    }
+    info!("bloom: {}", bloom_settings.enabled);
+}
+
+// Print the camera position and the saved bookmarks
+fn camera_info(
+    input: Res<Input<KeyCode>>,
+    bookmarks: Res<CameraBookmarks>,
+    camera: Query<&Transform, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::I) {
+        return;
+    }
+    for transform in camera.iter() {
+        info!("{:?}", transform);
+    }
+    let mut slots: Vec<_> = bookmarks.0.iter().collect();
+    slots.sort_by(|a, b| a.0.cmp(b.0));
+    for (slot, transform) in slots {
+        info!("Camera bookmark {slot} | {:?}", transform);
+    }
+}
@@ -0,0 +1,1049 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    render::primitives::{Frustum, Sphere},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    alpha_sorting::AlphaSortingSystem,
+    color_temperature::{apply_color_temperature, kelvin_to_rgb, ColorTemperature},
+    mesh_utils::{mesh_center_world, BISTRO_SCALE},
+    platform,
+    plugins::{camera::MainCamera, scene::SceneLoadStage},
+    scene_fix::SpawnedFor,
+};
+
+/// Point lights of the scenes by [`LightGroup`], with their settings reloaded from
+/// `assets/bistro.lights.ron` when it changes, and the sign spotlight.
+///
+/// The groups are switched on and off with `1` to `4`, the sign spotlight with `5`, and the point
+/// light shadows with `Space`. Lights fade when switched, streetlights warm up, lanterns flicker
+/// and lights far from the camera are dimmed.
+pub struct LightsPlugin;
+
+impl Plugin for LightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LightSettings>()
+            .register_type::<FlickerLight>()
+            .register_type::<SignLight>()
+            .init_resource::<LightSettings>()
+            .init_resource::<LightTransitionSettings>()
+            .init_resource::<SignLightSettings>()
+            .init_resource::<LightLODSettings>()
+            .init_resource::<FlickerSettings>()
+            .init_resource::<ShadowBiasSettings>()
+            .init_resource::<LightGroupColor>()
+            .add_asset::<LightSettings>()
+            .add_asset_loader(LightSettingsLoader)
+            .add_startup_system(load_light_settings)
+            .add_system(apply_shadow_bias)
+            .add_system(spawn_strip_lights)
+            // Reads the position of the door glass before it is recentered
+            .add_system(sign_spotlight_setup.before(AlphaSortingSystem))
+            // After the fades, so that switching a light instantly isn't overwritten by them
+            .add_system(
+                light_input
+                    .after(animate_light_transitions)
+                    .after(warm_up_system)
+                    .before(flicker_system),
+            )
+            .add_system(lights_info)
+            .add_system(update_light_settings)
+            .add_system(update_color_temperatures.after(update_light_settings))
+            .add_system(animate_light_transitions.after(update_light_settings))
+            .add_system(warm_up_system.after(update_light_settings))
+            .add_system(flicker_system.after(animate_light_transitions))
+            .add_system(
+                light_lod
+                    .after(flicker_system)
+                    .after(warm_up_system)
+                    .after(update_light_settings),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                sync_light_colors.after(apply_color_temperature),
+            );
+    }
+}
+
+// Fixtures longer than this ratio of their width are lit as strips
+pub const STRIP_LIGHT_MIN_RATIO: f32 = 3.0;
+// Distance between the point lights of a strip, in meters
+pub const STRIP_LIGHT_SPACING: f32 = 0.5;
+
+// A long ceiling fixture, lit by a row of point lights along its local X axis
+#[derive(Component)]
+pub struct StripLight {
+    pub length: f32,
+    pub count: usize,
+}
+
+// One of the point lights of a strip, with its share of the group intensity
+#[derive(Component)]
+pub struct StripSegment {
+    pub fraction: f32,
+}
+
+fn spawn_strip_lights(
+    mut commands: Commands,
+    light_settings: Res<LightSettings>,
+    strips: Query<(Entity, &StripLight), Added<StripLight>>,
+) {
+    for (entity, strip) in strips.iter() {
+        let fraction = 1.0 / strip.count as f32;
+        let intensity = light_settings.ceiling * fraction;
+        commands.entity(entity).with_children(|parent| {
+            for i in 0..strip.count {
+                // Evenly spaced, each light at the center of its section of the strip
+                let x = ((i as f32 + 0.5) * fraction - 0.5) * strip.length;
+                parent.spawn((
+                    PointLightBundle {
+                        transform: Transform::from_xyz(x, 0.0, 0.0),
+                        point_light: PointLight {
+                            color: kelvin_to_rgb(light_settings.ceiling_temperature),
+                            intensity,
+                            range: intensity / light_settings.range_ratio,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    LightGroup(LightGroupKind::Ceiling),
+                    LightLOD::new(intensity),
+                    StripSegment { fraction },
+                    ColorTemperature(light_settings.ceiling_temperature),
+                ));
+            }
+        });
+    }
+}
+// Group of a point light, toggled together and sharing their settings
+#[derive(Component, Clone, Copy)]
+pub struct LightGroup(pub LightGroupKind);
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightGroupKind {
+    Ceiling,
+    Wall,
+    Lantern,
+    StreetLight,
+}
+
+impl LightGroupKind {
+    // Wall lights share the intensity of the ceiling lights
+    pub fn intensity(self, light_settings: &LightSettings) -> f32 {
+        match self {
+            LightGroupKind::Ceiling | LightGroupKind::Wall => light_settings.ceiling,
+            LightGroupKind::Lantern => light_settings.lantern,
+            LightGroupKind::StreetLight => light_settings.streetlight,
+        }
+    }
+
+    fn color(self, group_color: &LightGroupColor) -> Color {
+        match self {
+            LightGroupKind::Ceiling => group_color.ceiling,
+            LightGroupKind::Wall => group_color.wall,
+            LightGroupKind::Lantern => group_color.lantern,
+            LightGroupKind::StreetLight => group_color.streetlight,
+        }
+    }
+
+    fn temperature(self, light_settings: &LightSettings) -> f32 {
+        match self {
+            LightGroupKind::Ceiling => light_settings.ceiling_temperature,
+            LightGroupKind::Wall => light_settings.wall_temperature,
+            LightGroupKind::Lantern => light_settings.lantern_temperature,
+            LightGroupKind::StreetLight => light_settings.streetlight_temperature,
+        }
+    }
+}
+
+impl std::fmt::Display for LightGroupKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            LightGroupKind::Ceiling => "Ceiling light",
+            LightGroupKind::Wall => "Wall light",
+            LightGroupKind::Lantern => "Lantern",
+            LightGroupKind::StreetLight => "Street light",
+        })
+    }
+}
+
+pub const LIGHT_SETTINGS_PATH: &str = "bistro.lights.ron";
+
+// Intensities and color temperatures of the light groups and settings of the sun, loaded from
+// `bistro.lights.ron` and reloaded when it changes
+#[derive(Resource, Deserialize, TypeUuid, Clone, Reflect)]
+#[uuid = "830c1fb8-db15-4804-9878-5604d96353f1"]
+#[serde(default)]
+#[reflect(Resource)]
+pub struct LightSettings {
+    pub ceiling: f32,
+    pub lantern: f32,
+    pub streetlight: f32,
+    pub range_ratio: f32,
+    // In Kelvin
+    pub ceiling_temperature: f32,
+    pub wall_temperature: f32,
+    pub lantern_temperature: f32,
+    pub streetlight_temperature: f32,
+    // Illuminance of the sun at the zenith, in lux
+    pub directional_illuminance_max: f32,
+    pub directional_color: Color,
+    // Of the sun and the moon, also toggled with shift + spacebar
+    pub directional_shadows: bool,
+}
+
+impl Default for LightSettings {
+    fn default() -> Self {
+        Self {
+            ceiling: 1000.0,
+            lantern: 200.0,
+            streetlight: 800.0,
+            range_ratio: 50.0,
+            ceiling_temperature: 3000.0,
+            wall_temperature: 3000.0,
+            lantern_temperature: 2200.0,
+            streetlight_temperature: 4000.0,
+            directional_illuminance_max: 210000.0,
+            directional_color: Color::WHITE,
+            directional_shadows: true,
+        }
+    }
+}
+
+// The illuminance is that of the zenith, the day/night cycle changes it every frame
+impl From<&LightSettings> for DirectionalLight {
+    fn from(settings: &LightSettings) -> Self {
+        DirectionalLight {
+            illuminance: settings.directional_illuminance_max,
+            color: settings.directional_color,
+            shadows_enabled: settings.directional_shadows,
+            ..Default::default()
+        }
+    }
+}
+
+struct LightSettingsLoader;
+
+impl AssetLoader for LightSettingsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let settings = ron::de::from_bytes::<LightSettings>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(settings));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lights.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct LightSettingsHandle(Handle<LightSettings>);
+
+/// Color of the lights of each group, multiplied with the color of their temperature. White keeps
+/// the color of the temperature.
+#[derive(Resource)]
+pub struct LightGroupColor {
+    pub ceiling: Color,
+    pub wall: Color,
+    pub lantern: Color,
+    pub streetlight: Color,
+}
+
+impl Default for LightGroupColor {
+    fn default() -> Self {
+        Self {
+            ceiling: Color::WHITE,
+            wall: Color::WHITE,
+            lantern: Color::WHITE,
+            streetlight: Color::WHITE,
+        }
+    }
+}
+
+// Set the color of each light from its temperature and the color of its group
+fn sync_light_colors(
+    group_color: Res<LightGroupColor>,
+    mut lights: Query<(&mut PointLight, &LightGroup, Option<&ColorTemperature>)>,
+) {
+    for (mut light, group, temperature) in lights.iter_mut() {
+        let base = temperature.map_or(Color::WHITE, |temperature| kelvin_to_rgb(temperature.0));
+        let tint = group.0.color(&group_color);
+        let color = Color::rgb(
+            base.r() * tint.r(),
+            base.g() * tint.g(),
+            base.b() * tint.b(),
+        );
+        // Only when it changes, to keep the change detection of the lights
+        if light.color != color {
+            light.color = color;
+        }
+    }
+}
+
+// Apply the color temperature of each group to its lights
+fn update_color_temperatures(
+    light_settings: Res<LightSettings>,
+    mut lights: Query<(&mut ColorTemperature, &LightGroup)>,
+) {
+    if !light_settings.is_changed() {
+        return;
+    }
+    for (mut temperature, group) in lights.iter_mut() {
+        let kelvin = group.0.temperature(&light_settings);
+        if temperature.0 != kelvin {
+            temperature.0 = kelvin;
+        }
+    }
+}
+
+fn load_light_settings(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LightSettingsHandle(asset_server.load(LIGHT_SETTINGS_PATH)));
+}
+
+// Copy the loaded settings in the resource, and update the lights that are on with them
+#[allow(clippy::type_complexity)]
+fn update_light_settings(
+    mut events: EventReader<AssetEvent<LightSettings>>,
+    assets: Res<Assets<LightSettings>>,
+    handle: Res<LightSettingsHandle>,
+    mut light_settings: ResMut<LightSettings>,
+    mut lights: Query<(
+        &mut PointLight,
+        Option<&mut LightTransition>,
+        Option<&mut WarmUpLight>,
+        Option<&mut LightLOD>,
+        Option<&mut FlickerLight>,
+        &LightGroup,
+        Option<&StripSegment>,
+    )>,
+) {
+    let mut changed = false;
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: updated } | AssetEvent::Modified { handle: updated }
+                if *updated == handle.0 =>
+            {
+                if let Some(settings) = assets.get(updated) {
+                    *light_settings = settings.clone();
+                    changed = true;
+                }
+            }
+            _ => (),
+        }
+    }
+    if !changed {
+        return;
+    }
+    info!("light settings updated");
+    for (mut light, transition, warm_up, mut lod, flicker, group, segment) in lights.iter_mut() {
+        let intensity =
+            group.0.intensity(&light_settings) * segment.map_or(1.0, |segment| segment.fraction);
+        if let Some(mut flicker) = flicker {
+            flicker.base_intensity = intensity;
+        }
+        if let Some(mut warm_up) = warm_up {
+            warm_up.target = intensity;
+        } else if let Some(mut transition) = transition {
+            if transition.target_intensity != 0.0 {
+                transition.target_intensity = intensity;
+            }
+        } else if lod
+            .as_deref()
+            .map_or(light.intensity, |lod| lod.nominal_intensity)
+            != 0.0
+        {
+            set_light_intensity(&mut light, lod.as_deref_mut(), intensity, &light_settings);
+        }
+    }
+}
+
+// Glass of the front door of the bistro, in the exterior scene. Keep in sync with `bistro.fixes.ron`
+const FRONT_DOOR_GLASS: &str = "Bistro_Research_Exterior_Paris_Building_01_paris_buildi_19bd23d";
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct SignLight;
+
+#[derive(Resource)]
+pub struct SignLightSettings {
+    pub color: Color,
+    pub intensity: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    // Position of the spotlight, relative to the center of the front door glass
+    pub offset: Vec3,
+    // Point the spotlight is aimed at, relative to the center of the front door glass
+    pub target_offset: Vec3,
+}
+
+impl Default for SignLightSettings {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(1.0, 0.95, 0.8),
+            intensity: 1600.0,
+            inner_angle: 0.2,
+            outer_angle: 0.6,
+            offset: Vec3::new(-3.0, 1.0, 0.0),
+            target_offset: Vec3::new(0.0, 1.5, 0.0),
+        }
+    }
+}
+
+// Once the exterior scene is ready, spawn a spotlight lighting the awning and sign over the entrance.
+// Spawned again each time the exterior is streamed back in, it is despawned with the door
+#[allow(clippy::too_many_arguments)]
+fn sign_spotlight_setup(
+    mut commands: Commands,
+    load_stage: Res<SceneLoadStage>,
+    settings: Res<SignLightSettings>,
+    mut done: Local<bool>,
+    named_entities: Query<(Entity, &Name, &Children)>,
+    has_mesh: Query<&Handle<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    light_settings: Res<LightSettings>,
+) {
+    if !load_stage.exterior_ready {
+        *done = false;
+        return;
+    }
+    if *done {
+        return;
+    }
+    let (door, children) = match named_entities
+        .iter()
+        .find(|(_, name, _)| name.as_str() == FRONT_DOOR_GLASS)
+    {
+        Some((door, _, children)) => (door, children),
+        None => {
+            warn!("no {FRONT_DOOR_GLASS} in the exterior, no sign spotlight");
+            *done = true;
+            return;
+        }
+    };
+    // Like the lights, the door is not transformed to its place, but the mesh is moved. Tried
+    // again next frame until its mesh is loaded
+    let center = match children
+        .first()
+        .and_then(|child| has_mesh.get(*child).ok())
+        .and_then(|mesh_handle| meshes.get(mesh_handle))
+        .and_then(|mesh| mesh_center_world(mesh, BISTRO_SCALE))
+    {
+        Some(center) => center,
+        None => return,
+    };
+    commands.spawn((
+        SpotLightBundle {
+            transform: Transform::from_translation(center + settings.offset)
+                .looking_at(center + settings.target_offset, Vec3::Y),
+            spot_light: SpotLight {
+                color: settings.color,
+                intensity: settings.intensity,
+                range: settings.intensity / light_settings.range_ratio,
+                inner_angle: settings.inner_angle,
+                outer_angle: settings.outer_angle,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        SignLight,
+        SpawnedFor(door),
+    ));
+    *done = true;
+}
+
+// Shadow biases of the sun, the moon and the point lights, tuned from the lights panel. Too low
+// gives shadow acne, too high detaches the shadows from their casters (peter-panning)
+#[derive(Resource)]
+pub struct ShadowBiasSettings {
+    pub depth_bias: f32,
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowBiasSettings {
+    fn default() -> Self {
+        Self {
+            depth_bias: PointLight::DEFAULT_SHADOW_DEPTH_BIAS,
+            normal_bias: PointLight::DEFAULT_SHADOW_NORMAL_BIAS,
+        }
+    }
+}
+
+// Lights are only changed when their biases differ, to keep their change detection quiet
+fn apply_shadow_bias(
+    settings: Res<ShadowBiasSettings>,
+    mut point_lights: Query<&mut PointLight>,
+    mut directional_lights: Query<&mut DirectionalLight>,
+) {
+    for mut light in point_lights.iter_mut() {
+        if light.shadow_depth_bias != settings.depth_bias
+            || light.shadow_normal_bias != settings.normal_bias
+        {
+            light.shadow_depth_bias = settings.depth_bias;
+            light.shadow_normal_bias = settings.normal_bias;
+        }
+    }
+    for mut light in directional_lights.iter_mut() {
+        if light.shadow_depth_bias != settings.depth_bias
+            || light.shadow_normal_bias != settings.normal_bias
+        {
+            light.shadow_depth_bias = settings.depth_bias;
+            light.shadow_normal_bias = settings.normal_bias;
+        }
+    }
+}
+
+#[derive(Resource)]
+struct LightTransitionSettings {
+    // Duration of the fade when toggling a light group, in seconds
+    duration: f32,
+    // Skip the fade and switch lights on or off immediately
+    instant: bool,
+}
+
+impl Default for LightTransitionSettings {
+    fn default() -> Self {
+        Self {
+            duration: 0.5,
+            instant: false,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LightTransition {
+    pub start_intensity: f32,
+    pub target_intensity: f32,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+// Duration of the warm-up of the streetlights, in seconds
+const STREETLIGHT_WARMUP_SECONDS: f32 = 2.0;
+
+// Ramp of a streetlight being switched on, from 0 to 10% of `target` then up to it
+#[derive(Component)]
+pub struct WarmUpLight {
+    pub target: f32,
+    pub elapsed: f32,
+    pub warmup_duration: f32,
+}
+
+// Switch a light on or off, either immediately or by starting a fade
+#[allow(clippy::too_many_arguments)]
+fn toggle_light(
+    commands: &mut Commands,
+    entity: Entity,
+    light: &mut PointLight,
+    transition: Option<&LightTransition>,
+    lod: Option<&mut LightLOD>,
+    intensity: f32,
+    settings: &LightTransitionSettings,
+    light_settings: &LightSettings,
+) {
+    // Distance based LOD may have lowered the light, start from its unscaled intensity
+    let current_intensity = lod
+        .as_deref()
+        .map_or(light.intensity, |lod| lod.nominal_intensity);
+    // If the light is already fading, toggle relative to where it is going
+    let is_on = transition.map_or(current_intensity != 0.0, |transition| {
+        transition.target_intensity != 0.0
+    });
+    let target_intensity = if is_on { 0.0 } else { intensity };
+    if settings.instant {
+        set_light_intensity(light, lod, target_intensity, light_settings);
+        commands.entity(entity).remove::<LightTransition>();
+    } else {
+        commands.entity(entity).insert(LightTransition {
+            start_intensity: current_intensity,
+            target_intensity,
+            elapsed: 0.0,
+            duration: settings.duration,
+        });
+    }
+}
+
+// Streetlights warm up slowly when switched on, like sodium vapor lamps, and go off immediately
+#[allow(clippy::too_many_arguments)]
+fn toggle_streetlight(
+    commands: &mut Commands,
+    entity: Entity,
+    light: &mut PointLight,
+    transition: Option<&LightTransition>,
+    warm_up: Option<&WarmUpLight>,
+    lod: Option<&mut LightLOD>,
+    intensity: f32,
+    settings: &LightTransitionSettings,
+    light_settings: &LightSettings,
+) {
+    let current_intensity = lod
+        .as_deref()
+        .map_or(light.intensity, |lod| lod.nominal_intensity);
+    let is_on = warm_up.is_some()
+        || transition.map_or(current_intensity != 0.0, |transition| {
+            transition.target_intensity != 0.0
+        });
+    let mut entity = commands.entity(entity);
+    entity.remove::<LightTransition>().remove::<WarmUpLight>();
+    let intensity_now = if !is_on && settings.instant {
+        intensity
+    } else {
+        0.0
+    };
+    set_light_intensity(light, lod, intensity_now, light_settings);
+    if !is_on && !settings.instant {
+        entity.insert(WarmUpLight {
+            target: intensity,
+            elapsed: 0.0,
+            warmup_duration: STREETLIGHT_WARMUP_SECONDS,
+        });
+    }
+}
+
+pub fn warm_up_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    light_settings: Res<LightSettings>,
+    mut lights: Query<(
+        Entity,
+        &mut PointLight,
+        &mut WarmUpLight,
+        Option<&mut LightLOD>,
+    )>,
+) {
+    for (entity, mut light, mut warm_up, mut lod) in lights.iter_mut() {
+        warm_up.elapsed += time.delta_seconds();
+        let progress = if warm_up.warmup_duration > 0.0 {
+            (warm_up.elapsed / warm_up.warmup_duration).min(1.0)
+        } else {
+            1.0
+        };
+        // A dim glow for the first half, then up to full intensity
+        let ratio = if progress < 0.5 {
+            0.1 * (progress * 2.0).powi(2)
+        } else {
+            let t = (progress - 0.5) * 2.0;
+            0.1 + 0.9 * t * t * (3.0 - 2.0 * t)
+        };
+        set_light_intensity(
+            &mut light,
+            lod.as_deref_mut(),
+            warm_up.target * ratio,
+            &light_settings,
+        );
+        if progress >= 1.0 {
+            commands.entity(entity).remove::<WarmUpLight>();
+        }
+    }
+}
+
+pub fn animate_light_transitions(
+    mut commands: Commands,
+    time: Res<Time>,
+    light_settings: Res<LightSettings>,
+    mut lights: Query<(
+        Entity,
+        &mut PointLight,
+        &mut LightTransition,
+        Option<&mut LightLOD>,
+    )>,
+) {
+    for (entity, mut light, mut transition, mut lod) in lights.iter_mut() {
+        transition.elapsed += time.delta_seconds();
+        let progress = if transition.duration > 0.0 {
+            (transition.elapsed / transition.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let intensity = transition.start_intensity
+            + (transition.target_intensity - transition.start_intensity) * progress;
+        set_light_intensity(&mut light, lod.as_deref_mut(), intensity, &light_settings);
+        if progress >= 1.0 {
+            commands.entity(entity).remove::<LightTransition>();
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct FlickerSettings {
+    // Disable to get steady lights, for example for screenshots
+    enabled: bool,
+}
+
+impl Default for FlickerSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+// Flame like variation of the intensity of a point light
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct FlickerLight {
+    pub base_intensity: f32,
+    // Maximum variation, as a ratio of `base_intensity`
+    pub amplitude: f32,
+    // Speed of the variation, in Hz
+    pub frequency: f32,
+    pub noise_seed: u64,
+}
+
+impl Default for FlickerLight {
+    fn default() -> Self {
+        Self {
+            base_intensity: 0.0,
+            amplitude: 0.15,
+            frequency: 2.0,
+            noise_seed: 0,
+        }
+    }
+}
+
+// Pseudo random value in [-1, 1] for an integer coordinate, from the splitmix64 hash
+fn hash_noise(seed: u64, x: i64) -> f32 {
+    let mut z = seed.wrapping_add((x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1_u64 << 23) as f32 - 1.0
+}
+
+// Smooth 1D value noise in [-1, 1]
+fn value_noise(seed: u64, x: f32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let a = hash_noise(seed, cell as i64);
+    let b = hash_noise(seed, cell as i64 + 1);
+    a + (b - a) * t
+}
+
+pub fn flicker_system(
+    time: Res<Time>,
+    settings: Res<FlickerSettings>,
+    light_settings: Res<LightSettings>,
+    mut lights: Query<
+        (&mut PointLight, &FlickerLight, Option<&mut LightLOD>),
+        Without<LightTransition>,
+    >,
+) {
+    let elapsed = time.elapsed_seconds();
+    for (mut light, flicker, mut lod) in lights.iter_mut() {
+        // Don't turn back on lights that are switched off
+        let current_intensity = lod
+            .as_deref()
+            .map_or(light.intensity, |lod| lod.nominal_intensity);
+        if current_intensity == 0.0 {
+            continue;
+        }
+        let variation = if settings.enabled {
+            let x = elapsed * flicker.frequency;
+            let phase = (flicker.noise_seed % 1000) as f32;
+            0.5 * (x * std::f32::consts::TAU + phase).sin()
+                + 0.5 * value_noise(flicker.noise_seed, x * 3.0)
+        } else {
+            0.0
+        };
+        set_light_intensity(
+            &mut light,
+            lod.as_deref_mut(),
+            flicker.base_intensity * (1.0 + flicker.amplitude * variation),
+            &light_settings,
+        );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LightLODSettings {
+    // Log every light changing LOD level
+    pub debug: bool,
+    // Only this many of the lights that are on are kept, the nearest to the camera
+    pub max_lights: Option<usize>,
+}
+
+// Reduce the intensity of a point light with its distance to the camera. The intensity of the
+// light is only written by `light_lod`, the other systems change `nominal_intensity`
+#[derive(Component)]
+pub struct LightLOD {
+    // Up to this distance, the light is at full intensity
+    pub full_range_distance: f32,
+    // From this distance, the light is turned off
+    pub disabled_distance: f32,
+    // Intensity of the light before being reduced by distance
+    pub nominal_intensity: f32,
+}
+
+impl LightLOD {
+    pub fn new(nominal_intensity: f32) -> Self {
+        Self {
+            full_range_distance: 30.0,
+            disabled_distance: 60.0,
+            nominal_intensity,
+        }
+    }
+}
+
+/// Sets the intensity of a point light, before its [`LightLOD`] when it has one.
+pub fn set_light_intensity(
+    light: &mut PointLight,
+    lod: Option<&mut LightLOD>,
+    intensity: f32,
+    light_settings: &LightSettings,
+) {
+    match lod {
+        Some(lod) => lod.nominal_intensity = intensity,
+        None => {
+            light.intensity = intensity;
+            light.range = intensity / light_settings.range_ratio;
+        }
+    }
+}
+
+// Ratio of the nominal intensity kept at `distance`, fading linearly between the two distances of
+// the LOD. Lights from `max_distance` are off
+fn lod_factor(lod: &LightLOD, distance: f32, max_distance: f32) -> f32 {
+    if distance >= lod.disabled_distance || distance >= max_distance {
+        0.0
+    } else if distance <= lod.full_range_distance {
+        1.0
+    } else {
+        1.0 - (distance - lod.full_range_distance)
+            / (lod.disabled_distance - lod.full_range_distance)
+    }
+}
+
+pub fn light_lod(
+    settings: Res<LightLODSettings>,
+    light_settings: Res<LightSettings>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut lights: Query<(Entity, &GlobalTransform, &mut PointLight, &LightLOD)>,
+    mut distances: Local<Vec<f32>>,
+) {
+    let camera_transform = match cameras.iter().find(|(camera, _)| camera.is_active) {
+        Some((_, transform)) => transform,
+        None => return,
+    };
+    // Lights further than the last one kept are turned off
+    let max_distance = match settings.max_lights {
+        Some(max_lights) => {
+            distances.clear();
+            distances.extend(
+                lights
+                    .iter()
+                    .filter(|(.., lod)| lod.nominal_intensity != 0.0)
+                    .map(|(_, transform, ..)| {
+                        transform
+                            .translation()
+                            .distance(camera_transform.translation())
+                    }),
+            );
+            if max_lights < distances.len() {
+                distances.select_nth_unstable_by(max_lights, f32::total_cmp);
+                distances[max_lights]
+            } else {
+                f32::INFINITY
+            }
+        }
+        None => f32::INFINITY,
+    };
+    for (entity, transform, mut light, lod) in lights.iter_mut() {
+        let distance = transform
+            .translation()
+            .distance(camera_transform.translation());
+        let intensity = lod.nominal_intensity * lod_factor(lod, distance, max_distance);
+        if light.intensity != intensity {
+            if settings.debug {
+                info!(
+                    "light {entity:?} at {distance:.1}m: intensity {:.1} -> {intensity:.1}",
+                    light.intensity
+                );
+            }
+            light.intensity = intensity;
+            light.range = intensity / light_settings.range_ratio;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn light_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut lights: Query<(
+        Entity,
+        &mut PointLight,
+        Option<&LightTransition>,
+        Option<&mut LightLOD>,
+        &LightGroup,
+        Option<&StripSegment>,
+        Option<&WarmUpLight>,
+    )>,
+    mut shadow_enabled: Local<bool>,
+    transition_settings: Res<LightTransitionSettings>,
+    mut sign_lights: Query<&mut SpotLight, With<SignLight>>,
+    sign_light_settings: Res<SignLightSettings>,
+    light_settings: Res<LightSettings>,
+) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("light_input");
+    // With a modifier, number keys are used for camera bookmarks
+    let number_keys_free = !input.any_pressed(platform::SHORTCUT_MODIFIER)
+        && !input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    // With shift, the sun and moon shadows are toggled instead
+    if input.just_pressed(KeyCode::Space) && !input.any_pressed([KeyCode::LShift, KeyCode::RShift])
+    {
+        *shadow_enabled = !*shadow_enabled;
+        for (_, mut light, ..) in lights.iter_mut() {
+            light.shadows_enabled = *shadow_enabled;
+        }
+    }
+    for (key, kind) in [
+        (KeyCode::Key1, LightGroupKind::Ceiling),
+        (KeyCode::Key2, LightGroupKind::Wall),
+        (KeyCode::Key3, LightGroupKind::Lantern),
+        (KeyCode::Key4, LightGroupKind::StreetLight),
+    ] {
+        if !number_keys_free || !input.just_pressed(key) {
+            continue;
+        }
+        info!("toggling {kind}");
+        for (entity, mut light, transition, mut lod, group, segment, warm_up) in lights.iter_mut() {
+            if group.0 != kind {
+                continue;
+            }
+            let intensity =
+                kind.intensity(&light_settings) * segment.map_or(1.0, |segment| segment.fraction);
+            if kind == LightGroupKind::StreetLight {
+                toggle_streetlight(
+                    &mut commands,
+                    entity,
+                    &mut light,
+                    transition,
+                    warm_up,
+                    lod.as_deref_mut(),
+                    intensity,
+                    &transition_settings,
+                    &light_settings,
+                );
+            } else {
+                toggle_light(
+                    &mut commands,
+                    entity,
+                    &mut light,
+                    transition,
+                    lod.as_deref_mut(),
+                    intensity,
+                    &transition_settings,
+                    &light_settings,
+                );
+            }
+            light.shadows_enabled = *shadow_enabled;
+        }
+    }
+    if number_keys_free && input.just_pressed(KeyCode::Key5) {
+        info!("toggling Sign");
+        for mut light in sign_lights.iter_mut() {
+            if light.intensity == 0.0 {
+                light.intensity = sign_light_settings.intensity;
+            } else {
+                light.intensity = 0.0;
+            }
+            light.range = light.intensity / light_settings.range_ratio;
+        }
+    }
+}
+
+// Print the state of every point light, and how many are in the camera frustum
+fn lights_info(
+    input: Res<Input<KeyCode>>,
+    lights: Query<(&PointLight, &LightGroup, &GlobalTransform)>,
+    camera: Query<&Frustum, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::I) {
+        return;
+    }
+    let count = lights.iter().count();
+    info!("There are {count} lights");
+    for frustum in camera.iter() {
+        let visible = lights
+            .iter()
+            .filter(|(light, _, transform)| {
+                frustum.intersects_sphere(
+                    &Sphere {
+                        center: transform.translation_vec3a(),
+                        radius: light.range,
+                    },
+                    true,
+                )
+            })
+            .count();
+        info!("{visible} of {count} lights are in the camera frustum");
+    }
+    for (light, group, _) in lights.iter() {
+        info!(
+            "{} | status: {} - shadows: {}",
+            group.0,
+            light.intensity != 0.0,
+            light.shadows_enabled
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lod(full_range_distance: f32, disabled_distance: f32) -> LightLOD {
+        LightLOD {
+            full_range_distance,
+            disabled_distance,
+            nominal_intensity: 100.0,
+        }
+    }
+
+    #[test]
+    fn full_intensity_up_to_full_range() {
+        let lod = lod(30.0, 60.0);
+        assert_eq!(lod_factor(&lod, 0.0, f32::INFINITY), 1.0);
+        assert_eq!(lod_factor(&lod, 30.0, f32::INFINITY), 1.0);
+    }
+
+    #[test]
+    fn fades_between_distances() {
+        let lod = lod(30.0, 60.0);
+        assert!((lod_factor(&lod, 45.0, f32::INFINITY) - 0.5).abs() < 1e-6);
+        assert!((lod_factor(&lod, 54.0, f32::INFINITY) - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn off_from_disabled_distance() {
+        let lod = lod(30.0, 60.0);
+        assert_eq!(lod_factor(&lod, 60.0, f32::INFINITY), 0.0);
+        assert_eq!(lod_factor(&lod, 1000.0, f32::INFINITY), 0.0);
+    }
+
+    #[test]
+    fn equal_distances_switch_without_fading() {
+        let lod = lod(40.0, 40.0);
+        assert_eq!(lod_factor(&lod, 39.9, f32::INFINITY), 1.0);
+        assert_eq!(lod_factor(&lod, 40.0, f32::INFINITY), 0.0);
+        assert_eq!(lod_factor(&lod, 40.1, f32::INFINITY), 0.0);
+    }
+
+    #[test]
+    fn off_from_max_distance() {
+        let lod = lod(30.0, 60.0);
+        assert_eq!(lod_factor(&lod, 20.0, 15.0), 0.0);
+        assert_eq!(lod_factor(&lod, 45.0, 45.0), 0.0);
+        // Further than the LOD, it changes nothing
+        assert!((lod_factor(&lod, 45.0, 100.0) - 0.5).abs() < 1e-6);
+    }
+}
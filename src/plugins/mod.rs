@@ -0,0 +1,4 @@
+pub mod camera;
+pub mod lights;
+pub mod scene;
+pub mod sky;
@@ -0,0 +1,446 @@
+use bevy::{
+    asset::HandleId, gltf::GltfExtras, pbr::NotShadowReceiver, prelude::*, scene::InstanceId,
+    utils::HashMap,
+};
+
+use crate::{
+    cli,
+    loading::{self, LoadingState},
+    platform,
+    scene_fix::{SceneFix, SceneFixManifest},
+};
+
+/// Spawns the exterior and interior scenes selected on the command line, and fixes them with the
+/// rules of the [`SceneFixManifest`] while they load.
+///
+/// A scene file missing from the assets folder is replaced by a placeholder. The scene stats and
+/// bounds are printed with `I`, and the wine glasses are shown or hidden with `6`.
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<WineGlass>()
+            .insert_resource(SceneArgs::from_args())
+            .insert_resource(SceneMode::from_args())
+            .insert_resource(SceneFixSettings::from_args())
+            .init_resource::<SceneLoadStage>()
+            .init_resource::<SceneStats>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, asset_ready_checker)
+            .add_startup_system(setup_scenes)
+            .add_system(interior_scene_update)
+            .add_system(exterior_scene_update)
+            .add_system(toggle_wine_glasses)
+            .add_system(update_scene_stats)
+            .add_system(scene_bounds_info.after(update_scene_stats));
+    }
+}
+
+// Scene files that are not in the assets folder, they are replaced by a placeholder
+#[derive(Resource)]
+struct MissingScenes {
+    exterior: bool,
+    interior: bool,
+}
+
+// Check that the scene files exist before spawning them, and show what is missing instead
+fn asset_ready_checker(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scene_args: Res<SceneArgs>,
+    scene_mode: Res<SceneMode>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let is_missing = |path: &str| {
+        // Files fetched over HTTP on the web can't be checked, failing to load is logged instead
+        if cfg!(target_arch = "wasm32") {
+            return false;
+        }
+        // Without the label of the scene in the file
+        let file = path.split('#').next().unwrap_or(path);
+        !asset_server.asset_io().is_file(std::path::Path::new(file))
+    };
+    let missing = MissingScenes {
+        exterior: *scene_mode != SceneMode::InteriorOnly && is_missing(&scene_args.exterior),
+        interior: *scene_mode != SceneMode::ExteriorOnly && is_missing(&scene_args.interior),
+    };
+
+    let placeholders = [
+        (
+            missing.exterior,
+            &scene_args.exterior,
+            Vec3::new(0.0, 1.0, 0.0),
+        ),
+        (
+            missing.interior,
+            &scene_args.interior,
+            Vec3::new(6.0, 1.0, 2.0),
+        ),
+    ];
+    let mut label_offset = 10.0;
+    for (_, path, position) in placeholders.iter().filter(|(missing, ..)| *missing) {
+        warn!("missing scene {path}, showing a placeholder");
+        commands.spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 2.0 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::FUCHSIA,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(*position),
+            ..default()
+        });
+        // A UI label, a `Text2dBundle` would need a 2d camera over the scene
+        commands.spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(label_offset),
+                    left: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ..TextBundle::from_section(
+                format!("Missing: {path}"),
+                TextStyle {
+                    font: asset_server.load("fonts/DejaVuSansMono.ttf"),
+                    font_size: 24.0,
+                    color: Color::FUCHSIA,
+                },
+            )
+        });
+        label_offset += 30.0;
+    }
+    commands.insert_resource(missing);
+}
+
+// Which scenes to load, from the command line
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SceneMode {
+    Both,
+    InteriorOnly,
+    ExteriorOnly,
+}
+
+impl SceneMode {
+    fn from_args() -> Self {
+        match (cli::flag("--interior-only"), cli::flag("--exterior-only")) {
+            (true, true) => {
+                eprintln!("--interior-only and --exterior-only can't be used together");
+                std::process::exit(1);
+            }
+            (true, false) => SceneMode::InteriorOnly,
+            (false, true) => SceneMode::ExteriorOnly,
+            (false, false) => SceneMode::Both,
+        }
+    }
+}
+
+// Changes made to the scenes once they are loaded
+#[derive(Resource)]
+pub struct SceneFixSettings {
+    // Despawn all the wine glasses instead of making them transparent
+    pub remove_glasses: bool,
+}
+
+impl SceneFixSettings {
+    fn from_args() -> Self {
+        Self {
+            remove_glasses: cli::flag("--remove-glasses"),
+        }
+    }
+}
+
+// Paths of the scenes to load, from the command line
+#[derive(Resource)]
+pub struct SceneArgs {
+    pub exterior: String,
+    pub interior: String,
+}
+
+impl SceneArgs {
+    fn from_args() -> Self {
+        // Load the first scene of the file if none was specified
+        let scene_path = |path: String| {
+            if path.contains('#') {
+                path
+            } else {
+                format!("{path}#Scene0")
+            }
+        };
+        Self {
+            exterior: scene_path(
+                cli::value("--exterior").unwrap_or_else(|| "BistroExterior.glb".to_string()),
+            ),
+            interior: scene_path(
+                cli::value("--interior").unwrap_or_else(|| "BistroInterior_Wine.glb".to_string()),
+            ),
+        }
+    }
+}
+
+// The instances are cleared once the scenes are fixed
+#[derive(Resource)]
+pub struct Scenes {
+    pub interior: Option<InstanceId>,
+    pub exterior: Option<InstanceId>,
+    // The exterior once spawned, kept to unload it when the camera is in the interior
+    pub exterior_instance: Option<InstanceId>,
+    pub exterior_streaming: ExteriorStreaming,
+}
+
+/// Where the exterior is in being streamed out and back in, see `SceneStreamingPlugin`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExteriorStreaming {
+    Loaded,
+    // Despawned, and its assets freed
+    Unloaded,
+    // Waiting for the file to be loaded again
+    Reloading,
+    // To black, with the seconds elapsed, before the exterior is spawned
+    FadingOut(f32),
+    // Waiting for the exterior to be fixed again
+    Spawning,
+    // From black, with the seconds elapsed
+    FadingIn(f32),
+}
+
+// Which scenes have been fully loaded and fixed
+#[derive(Resource, Default)]
+pub struct SceneLoadStage {
+    pub interior_ready: bool,
+    pub exterior_ready: bool,
+}
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct WineGlass;
+
+// Those systems will fix the scenes by removing a few items, changing transparency on materials and adding point lights
+// This should be done in Blender by modifying the scenes that are to be imported, but here I am doing it in Bevy to
+// work on the unmodified scenes from nvidia. Nodes with fixes in their extras use those instead of the rules
+#[allow(clippy::too_many_arguments)]
+fn interior_scene_update(
+    scene_spawner: Res<SceneSpawner>,
+    mut scene_instance: ResMut<Scenes>,
+    mut load_stage: ResMut<SceneLoadStage>,
+    mut done: Local<bool>,
+    mut loading: ResMut<LoadingState>,
+    manifest: Option<Res<SceneFixManifest>>,
+    named_entities: Query<(Entity, &Name, &Children, Option<&GltfExtras>)>,
+    mut scene_fix: SceneFix,
+) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("interior_scene_update");
+    if *done {
+        return;
+    }
+    // Wait for the rules to be loaded before fixing the scene
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => return,
+    };
+    if let Some(instance_id) = scene_instance.interior {
+        scene_spawner
+            .iter_instance_entities(instance_id)
+            .for_each(|entity| {
+                if let Ok((entity, name, children, extras)) = named_entities.get(entity) {
+                    loading.processed_entities += 1;
+                    scene_fix.apply(&manifest.interior, entity, name, extras, children);
+                }
+            });
+        scene_fix.fix_materials();
+        scene_fix.generate_tangents();
+        if scene_spawner.instance_is_ready(instance_id) {
+            scene_instance.interior = None;
+            load_stage.interior_ready = true;
+            *done = true;
+        }
+    }
+}
+
+// The exterior is fixed again each time it is streamed back in
+#[allow(clippy::too_many_arguments)]
+fn exterior_scene_update(
+    scene_spawner: Res<SceneSpawner>,
+    mut scene_instance: ResMut<Scenes>,
+    mut load_stage: ResMut<SceneLoadStage>,
+    mut loading: ResMut<LoadingState>,
+    manifest: Option<Res<SceneFixManifest>>,
+    named_entities: Query<(Entity, &Name, &Children, Option<&GltfExtras>)>,
+    mut scene_fix: SceneFix,
+) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("exterior_scene_update");
+    if scene_instance.exterior.is_none() {
+        return;
+    }
+    // Wait for the rules to be loaded before fixing the scene
+    let manifest = match manifest {
+        Some(manifest) => manifest,
+        None => return,
+    };
+    if let Some(instance_id) = scene_instance.exterior {
+        scene_spawner
+            .iter_instance_entities(instance_id)
+            .for_each(|entity| {
+                if let Ok((entity, name, children, extras)) = named_entities.get(entity) {
+                    loading.processed_entities += 1;
+                    scene_fix.apply(&manifest.exterior, entity, name, extras, children);
+                }
+            });
+        scene_fix.fix_materials();
+        scene_fix.generate_tangents();
+        if scene_spawner.instance_is_ready(instance_id) {
+            scene_fix.clear_checked_meshes();
+            scene_instance.exterior = None;
+            load_stage.exterior_ready = true;
+        }
+    }
+}
+
+// Counts of what is in the world, updated every frame
+#[derive(Resource, Default)]
+struct SceneStats {
+    total_entities: u32,
+    total_meshes: u32,
+    total_point_lights: u32,
+    // Vertices of all the loaded mesh assets, whether they are drawn or not
+    total_vertices: u64,
+}
+
+fn update_scene_stats(
+    mut stats: ResMut<SceneStats>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    // Vertex count of each mesh, only counted when it is loaded or modified
+    mut vertex_counts: Local<HashMap<HandleId, u64>>,
+    entities: Query<Entity>,
+    mesh_entities: Query<(), With<Handle<Mesh>>>,
+    point_lights: Query<(), With<PointLight>>,
+) {
+    let mut vertices_changed = false;
+    for event in mesh_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if let Some(mesh) = meshes.get(handle) {
+                    vertex_counts.insert(handle.id(), mesh.count_vertices() as u64);
+                }
+            }
+            AssetEvent::Removed { handle } => {
+                vertex_counts.remove(&handle.id());
+            }
+        }
+        vertices_changed = true;
+    }
+    if vertices_changed {
+        stats.total_vertices = vertex_counts.values().sum();
+    }
+    stats.total_entities = entities.iter().count() as u32;
+    stats.total_meshes = mesh_entities.iter().count() as u32;
+    stats.total_point_lights = point_lights.iter().count() as u32;
+}
+
+// Print the loading state and the bounding box of everything loaded from the scenes
+fn scene_bounds_info(
+    input: Res<Input<KeyCode>>,
+    load_stage: Res<SceneLoadStage>,
+    stats: Res<SceneStats>,
+    meshes: Res<Assets<Mesh>>,
+    // Meshes added by the demo (gizmos, rain, light bulbs, ...) don't receive shadows
+    scene_meshes: Query<(&Handle<Mesh>, &GlobalTransform), Without<NotShadowReceiver>>,
+) {
+    if !input.just_pressed(KeyCode::I) {
+        return;
+    }
+    info!(
+        "Scenes ready | interior: {} - exterior: {}",
+        load_stage.interior_ready, load_stage.exterior_ready
+    );
+    info!(
+        "Scene stats | entities: {} - meshes: {} - point lights: {} - vertices: {}",
+        stats.total_entities, stats.total_meshes, stats.total_point_lights, stats.total_vertices
+    );
+    let mut bounds: Option<(Vec3, Vec3)> = None;
+    for (handle, transform) in scene_meshes.iter() {
+        let aabb = match meshes.get(handle).and_then(|mesh| mesh.compute_aabb()) {
+            Some(aabb) => aabb,
+            None => continue,
+        };
+        let (min, max) = (aabb.min(), aabb.max());
+        for corner in [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(max.x, max.y, max.z),
+        ] {
+            let corner = transform.transform_point(corner);
+            bounds = Some(match bounds {
+                Some((min, max)) => (min.min(corner), max.max(corner)),
+                None => (corner, corner),
+            });
+        }
+    }
+    match bounds {
+        Some((min, max)) => info!(
+            "Scene bounds | min: {min:.2} - max: {max:.2} - size: {:.2}",
+            max - min
+        ),
+        None => info!("Scene bounds | no meshes loaded"),
+    }
+}
+
+fn toggle_wine_glasses(
+    input: Res<Input<KeyCode>>,
+    mut glasses: Query<&mut Visibility, With<WineGlass>>,
+) {
+    // With a modifier, number keys are used for camera bookmarks
+    let number_keys_free = !input.any_pressed(platform::SHORTCUT_MODIFIER)
+        && !input.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    if number_keys_free && input.just_pressed(KeyCode::Key6) {
+        info!("toggling Wine glasses");
+        for mut visibility in glasses.iter_mut() {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup_scenes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    scene_args: Res<SceneArgs>,
+    scene_mode: Res<SceneMode>,
+    missing: Res<MissingScenes>,
+    mut load_stage: ResMut<SceneLoadStage>,
+    mut loading: ResMut<LoadingState>,
+) {
+    let exterior = if *scene_mode == SceneMode::InteriorOnly || missing.exterior {
+        load_stage.exterior_ready = true;
+        None
+    } else {
+        loading.estimated_entities += loading::EXTERIOR_NAMED_ENTITIES;
+        loading.preload(&asset_server, &scene_args.exterior);
+        Some(scene_spawner.spawn(asset_server.load(&scene_args.exterior)))
+    };
+    let interior = if *scene_mode == SceneMode::ExteriorOnly || missing.interior {
+        load_stage.interior_ready = true;
+        None
+    } else {
+        loading.estimated_entities += loading::INTERIOR_NAMED_ENTITIES;
+        loading.preload(&asset_server, &scene_args.interior);
+        Some(scene_spawner.spawn(asset_server.load(&scene_args.interior)))
+    };
+    commands.insert_resource(Scenes {
+        interior,
+        exterior,
+        exterior_instance: exterior,
+        exterior_streaming: ExteriorStreaming::Loaded,
+    });
+}
@@ -0,0 +1,404 @@
+use bevy::{core_pipeline::bloom, ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    hdri_skybox::{HdriSkybox, HDRI_NIGHT_EXPOSURE},
+    platform,
+    plugins::lights::{
+        animate_light_transitions, flicker_system, light_lod, set_light_intensity, warm_up_system,
+        LightGroup, LightLOD, LightSettings, LightTransition, SignLight, SignLightSettings,
+        StripSegment, WarmUpLight,
+    },
+    post_processing::PostProcessingStack,
+    simulation_clock::{ShouldSkipSimulation, SimulationClock, SimulationClockSystem},
+    sun_position::{RealSunMode, SunMode},
+    weather::WeatherState,
+};
+
+/// Day/night cycle, with a [`SunLight`] and a [`MoonLight`] orbiting the scenes and the ambient
+/// light and bloom following them.
+///
+/// The cycle follows the [`SimulationClock`], so it is paused with `T` and slowed down or sped up
+/// with `[` and `]`. `N` and `Shift+N` snap to the night and day presets, `Shift+Space` toggles
+/// the sun and moon shadows, and `Ctrl+Up` and `Ctrl+Down` resize the area they cast shadows in.
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SunLight>()
+            .register_type::<MoonLight>()
+            .init_resource::<DayNightCycleSettings>()
+            .init_resource::<ShadowVolumeSettings>()
+            .add_startup_system(setup_celestial_lights)
+            .add_system(apply_celestial_settings)
+            .add_system(night_and_day.after(SimulationClockSystem))
+            .add_system(celestial_shadows_input)
+            .add_system(shadow_volume_input)
+            .add_system(apply_shadow_volume.after(shadow_volume_input))
+            // After the fades it cancels, and before the systems reading the nominal intensity
+            .add_system(
+                lighting_preset_input
+                    .before(night_and_day)
+                    .after(animate_light_transitions)
+                    .after(warm_up_system)
+                    .before(flicker_system)
+                    .before(light_lod),
+            );
+    }
+}
+
+// A directional light orbiting with the day/night cycle
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SunLight {
+    // Ratio of the day/night cycle speed
+    pub orbit_speed: f32,
+    // Inclination of the orbit, in radians
+    pub tilt_angle: f32,
+    // Illuminance when at the zenith, in lux
+    pub max_illuminance: f32,
+}
+
+impl Default for SunLight {
+    fn default() -> Self {
+        Self {
+            orbit_speed: 1.0,
+            tilt_angle: -std::f32::consts::FRAC_PI_4,
+            max_illuminance: LightSettings::default().directional_illuminance_max,
+        }
+    }
+}
+
+// Like `SunLight`, with an offset along the orbit so that it can rise when the sun sets
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct MoonLight {
+    orbit_speed: f32,
+    tilt_angle: f32,
+    max_illuminance: f32,
+    // Offset along the orbit, in radians
+    phase: f32,
+}
+
+impl Default for MoonLight {
+    fn default() -> Self {
+        Self {
+            orbit_speed: 1.0,
+            tilt_angle: -std::f32::consts::FRAC_PI_4,
+            max_illuminance: 0.25,
+            phase: std::f32::consts::PI,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct DayNightCycleSettings {
+    // Duration of a full day/night cycle in simulated time, in seconds. The sun is at its highest
+    // at the start of each cycle
+    pub cycle_duration_seconds: f32,
+}
+
+impl Default for DayNightCycleSettings {
+    fn default() -> Self {
+        Self {
+            cycle_duration_seconds: 20.0,
+        }
+    }
+}
+
+impl DayNightCycleSettings {
+    // Position in the cycle at the simulated time `elapsed`, in radians
+    fn angle(&self, elapsed: f32) -> f32 {
+        (elapsed / self.cycle_duration_seconds).fract() * std::f32::consts::TAU
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+fn night_and_day(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    cycle: Res<DayNightCycleSettings>,
+    sun_mode: Res<SunMode>,
+    mut real_sun: ResMut<RealSunMode>,
+    mut lights: Query<
+        (
+            &mut Transform,
+            &mut DirectionalLight,
+            Option<&SunLight>,
+            Option<&MoonLight>,
+        ),
+        Or<(With<SunLight>, With<MoonLight>)>,
+    >,
+    mut ambient: ResMut<AmbientLight>,
+    weather: Res<WeatherState>,
+    post_processing: Res<PostProcessingStack>,
+    mut bloom: Query<&mut bloom::BloomSettings>,
+    mut hdri_skybox: ResMut<HdriSkybox>,
+) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_scope!("night_and_day");
+    if skip.0 {
+        return;
+    }
+    let angle = cycle.angle(clock.elapsed);
+    if *sun_mode == SunMode::Real {
+        real_sun.advance(clock.delta);
+    }
+    let sun_position = (*sun_mode == SunMode::Real).then(|| real_sun.sun_position());
+    let mut total_illuminance = 0.0;
+    let mut daylight = 0.0;
+    for (mut transform, mut light, sun, moon) in lights.iter_mut() {
+        let (orbit_speed, tilt_angle, max_illuminance, phase) = match (sun, moon) {
+            (Some(sun), _) => (sun.orbit_speed, sun.tilt_angle, sun.max_illuminance, 0.0),
+            (None, Some(moon)) => (
+                moon.orbit_speed,
+                moon.tilt_angle,
+                moon.max_illuminance,
+                moon.phase,
+            ),
+            (None, None) => continue,
+        };
+        let elevation = match sun_position {
+            Some(position) => {
+                // The moon is kept opposite the sun
+                let direction = if sun.is_some() {
+                    position.direction()
+                } else {
+                    -position.direction()
+                };
+                transform.rotation = Transform::IDENTITY.looking_at(-direction, Vec3::Y).rotation;
+                direction.y.asin()
+            }
+            None => {
+                transform.rotation =
+                    Quat::from_euler(EulerRot::ZYX, angle * orbit_speed + phase, 0.0, tilt_angle);
+                let (angle, _, _) = transform.rotation.to_euler(EulerRot::XYZ);
+                -angle
+            }
+        };
+        // Light ramps up from just above the horizon, to its maximum at the zenith
+        let elevation = ((elevation - 0.1) / (std::f32::consts::FRAC_PI_2 - 0.1)).clamp(0.0, 1.0);
+        light.illuminance = elevation * max_illuminance;
+        if sun.is_some() {
+            daylight = elevation;
+        }
+        total_illuminance += light.illuminance;
+        if weather.rain_enabled {
+            // Overcast sky: the sun is hidden behind clouds
+            light.illuminance = light.illuminance.min(30000.0);
+        }
+    }
+    ambient.brightness = (total_illuminance / 400000.0).max(0.01);
+    if weather.rain_enabled {
+        ambient.brightness *= 0.7;
+    }
+    // Artificial lights stand out at night, bloom fades out as the sun rises
+    if let Some(bloom_settings) = post_processing.bloom() {
+        for mut bloom in bloom.iter_mut() {
+            bloom.intensity = bloom_settings.intensity * (1.0 - daylight);
+        }
+    }
+    // The skybox dims with the sun, so that it doesn't outshine the lights at night
+    hdri_skybox.exposure = daylight.max(HDRI_NIGHT_EXPOSURE);
+}
+
+// Bevy 0.9 has no shadow cascades, the sun and the moon render their shadows in a single box
+// centered on the origin. A smaller box gives sharper shadows over a smaller area
+#[derive(Resource)]
+struct ShadowVolumeSettings {
+    // Half size of the box, in meters
+    max_distance: f32,
+}
+
+impl Default for ShadowVolumeSettings {
+    fn default() -> Self {
+        Self {
+            max_distance: 100.0,
+        }
+    }
+}
+
+fn shadow_volume_input(input: Res<Input<KeyCode>>, mut settings: ResMut<ShadowVolumeSettings>) {
+    if !input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        return;
+    }
+    let step = if input.just_pressed(KeyCode::Up) {
+        10.0
+    } else if input.just_pressed(KeyCode::Down) {
+        -10.0
+    } else {
+        return;
+    };
+    settings.max_distance = (settings.max_distance + step).max(10.0);
+    info!("shadow max distance: {}m", settings.max_distance);
+}
+
+fn apply_shadow_volume(
+    settings: Res<ShadowVolumeSettings>,
+    mut lights: Query<&mut DirectionalLight>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let size = settings.max_distance;
+    for mut light in lights.iter_mut() {
+        let projection = &mut light.shadow_projection;
+        projection.left = -size;
+        projection.right = size;
+        projection.bottom = -size;
+        projection.top = size;
+        projection.near = -size;
+        projection.far = size;
+    }
+}
+
+// Everything changed by the lighting presets, so that any system can apply them
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+struct LightingPresetParams<'w, 's> {
+    commands: Commands<'w, 's>,
+    clock: ResMut<'w, SimulationClock>,
+    cycle: Res<'w, DayNightCycleSettings>,
+    light_settings: Res<'w, LightSettings>,
+    sign_light_settings: Res<'w, SignLightSettings>,
+    lights: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut PointLight,
+            Option<&'static mut LightLOD>,
+            &'static LightGroup,
+            Option<&'static StripSegment>,
+        ),
+    >,
+    sign_lights: Query<'w, 's, &'static mut SpotLight, With<SignLight>>,
+}
+
+// Snap to the middle of the night, with every light on. The clock is paused to hold the preset
+fn apply_night_preset(params: &mut LightingPresetParams) {
+    params.clock.elapsed = params.cycle.cycle_duration_seconds / 2.0;
+    params.clock.paused = true;
+    set_artificial_lights(params, true);
+}
+
+// Snap to noon, with every light off. The clock is paused to hold the preset
+fn apply_day_preset(params: &mut LightingPresetParams) {
+    params.clock.elapsed = 0.0;
+    params.clock.paused = true;
+    set_artificial_lights(params, false);
+}
+
+fn set_artificial_lights(params: &mut LightingPresetParams, on: bool) {
+    let light_settings = &params.light_settings;
+    for (entity, mut light, mut lod, group, segment) in params.lights.iter_mut() {
+        let intensity =
+            group.0.intensity(light_settings) * segment.map_or(1.0, |segment| segment.fraction);
+        // Lights turned off by their level of detail are switched too
+        set_light_intensity(
+            &mut light,
+            lod.as_deref_mut(),
+            if on { intensity } else { 0.0 },
+            light_settings,
+        );
+        // Presets are instant, cancel any fade or warm-up in progress
+        params
+            .commands
+            .entity(entity)
+            .remove::<LightTransition>()
+            .remove::<WarmUpLight>();
+    }
+    for mut light in params.sign_lights.iter_mut() {
+        light.intensity = if on {
+            params.sign_light_settings.intensity
+        } else {
+            0.0
+        };
+        light.range = light.intensity / light_settings.range_ratio;
+    }
+}
+
+fn lighting_preset_input(input: Res<Input<KeyCode>>, mut params: LightingPresetParams) {
+    if !input.just_pressed(KeyCode::N) {
+        return;
+    }
+    // `D` is already used to move the camera, the day preset is on shift + N
+    if input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        info!("applying day preset");
+        apply_day_preset(&mut params);
+    } else {
+        info!("applying night preset");
+        apply_night_preset(&mut params);
+    }
+}
+
+fn setup_celestial_lights(mut commands: Commands, light_settings: Res<LightSettings>) {
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                ..(&*light_settings).into()
+            },
+            ..Default::default()
+        },
+        SunLight {
+            max_illuminance: light_settings.directional_illuminance_max,
+            ..Default::default()
+        },
+        Name::new("Sun"),
+    ));
+
+    // Bluish, its illuminance is from `MoonLight`
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::rgb(0.75, 0.8, 1.0),
+                ..(&*light_settings).into()
+            },
+            ..Default::default()
+        },
+        MoonLight::default(),
+        Name::new("Moon"),
+    ));
+}
+
+// The settings are loaded from `bistro.lights.ron` after the lights are spawned, and reloaded when
+// it changes. Shadows are only set when their setting changes, to keep the shift + spacebar toggle
+#[allow(clippy::type_complexity)]
+fn apply_celestial_settings(
+    light_settings: Res<LightSettings>,
+    mut applied_shadows: Local<Option<bool>>,
+    mut lights: Query<
+        (&mut DirectionalLight, Option<&mut SunLight>),
+        Or<(With<SunLight>, With<MoonLight>)>,
+    >,
+) {
+    if !light_settings.is_changed() {
+        return;
+    }
+    let shadows_changed = *applied_shadows != Some(light_settings.directional_shadows);
+    *applied_shadows = Some(light_settings.directional_shadows);
+    for (mut light, sun) in lights.iter_mut() {
+        // The moon keeps its own color and illuminance
+        if let Some(mut sun) = sun {
+            sun.max_illuminance = light_settings.directional_illuminance_max;
+            light.color = light_settings.directional_color;
+        }
+        if shadows_changed {
+            light.shadows_enabled = light_settings.directional_shadows;
+        }
+    }
+}
+
+// Without shift, only the point light shadows are toggled
+#[allow(clippy::type_complexity)]
+fn celestial_shadows_input(
+    input: Res<Input<KeyCode>>,
+    mut celestial_lights: Query<&mut DirectionalLight, Or<(With<SunLight>, With<MoonLight>)>>,
+) {
+    if input.just_pressed(KeyCode::Space) && input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+        // The sun and the moon each keep their own shadow state
+        for mut light in celestial_lights.iter_mut() {
+            light.shadows_enabled = !light.shadows_enabled;
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::{
+    core_pipeline::{core_3d, tonemapping::Tonemapping},
+    prelude::*,
+    render::{
+        camera::{
+            camera_system, CameraProjection, CameraProjectionPlugin, CameraRenderGraph,
+            RenderTarget,
+        },
+        mesh::VertexAttributeValues,
+        primitives::{Aabb, Frustum},
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        view::{update_frusta, RenderLayers, VisibilitySystems, VisibleEntities},
+    },
+    transform::TransformSystem,
+};
+
+use crate::{
+    alpha_sorting::AlphaSortingSystem,
+    loading::ScenesReady,
+    plugins::camera::{MainCamera, PORTAL_LAYER},
+    scene_fix::SpawnedFor,
+};
+
+// Size of the longest side of the portal textures, in pixels
+const PORTAL_RESOLUTION: u32 = 1024;
+// The portal camera stops rendering when the main camera is closer than this to the portal, in
+// meters
+const MIN_PORTAL_DISTANCE: f32 = 0.01;
+
+/// Shows on each [`Portal`] mesh what is behind it, rendered by a camera of its own.
+///
+/// The portal camera follows the main camera, moved by [`Portal::destination`], with an off-axis
+/// projection through the edges of the mesh. The rendered texture then maps onto the mesh, which
+/// gets UVs spanning its bounds. The portal camera renders only while the mesh is in the view of
+/// the main camera.
+pub struct PortalPlugin;
+
+impl Plugin for PortalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(CameraProjectionPlugin::<PortalProjection>::default())
+            // The blended meshes are moved until the scenes are ready
+            .add_system(setup_portals.after(AlphaSortingSystem))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_portal_cameras
+                    .after(TransformSystem::TransformPropagate)
+                    // After the frustum of the main camera, which follows its projection
+                    .after(VisibilitySystems::UpdateProjectionFrusta)
+                    .before(camera_system::<PortalProjection>),
+            )
+            // Bevy only updates the frusta of its own projections
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_frusta::<PortalProjection>
+                    .after(camera_system::<PortalProjection>)
+                    .after(update_portal_cameras)
+                    .before(VisibilitySystems::CheckVisibility),
+            );
+    }
+}
+
+/// A mesh showing what can be seen through it from [`Portal::destination`].
+#[derive(Component, Default)]
+pub struct Portal {
+    // Moves the view through the portal, the identity shows what is right behind the mesh
+    pub destination: Transform,
+}
+
+// Camera rendering a portal, with the meshes showing its texture from each side
+#[derive(Component)]
+struct PortalView {
+    camera: Entity,
+    // Bounds of the mesh, in its local space
+    aabb: Aabb,
+    // Local axes of the mesh along its width and height, the portal faces `right.cross(up)`
+    right: Vec3,
+    up: Vec3,
+    front_mesh: Handle<Mesh>,
+    // Same mesh with its UVs mirrored, for when the camera is behind the portal
+    back_mesh: Handle<Mesh>,
+}
+
+#[derive(Component)]
+struct PortalCamera;
+
+/// Perspective projection through a rectangle that is not centered on the view direction.
+///
+/// The rectangle is `near` in front of the camera, and the projection has no far plane like
+/// Bevy's [`PerspectiveProjection`].
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component, Default)]
+pub struct PortalProjection {
+    // Edges of the rectangle on the near plane, in view space
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+    pub near: f32,
+}
+
+impl Default for PortalProjection {
+    fn default() -> Self {
+        Self {
+            left: -0.1,
+            right: 0.1,
+            bottom: -0.1,
+            top: 0.1,
+            near: 0.1,
+        }
+    }
+}
+
+impl CameraProjection for PortalProjection {
+    fn get_projection_matrix(&self) -> Mat4 {
+        // Infinite reverse z, like `Mat4::perspective_infinite_reverse_rh` with a shifted center
+        let width = self.right - self.left;
+        let height = self.top - self.bottom;
+        Mat4::from_cols(
+            Vec4::new(2.0 * self.near / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * self.near / height, 0.0, 0.0),
+            Vec4::new(
+                (self.right + self.left) / width,
+                (self.top + self.bottom) / height,
+                0.0,
+                -1.0,
+            ),
+            Vec4::new(0.0, 0.0, self.near, 0.0),
+        )
+    }
+
+    // The rectangle follows the portal mesh, not the size of the texture
+    fn update(&mut self, _width: f32, _height: f32) {}
+
+    fn far(&self) -> f32 {
+        1000.0
+    }
+}
+
+// Mesh with UVs projected on its bounds, `u` along `right` and `v` down along `up`
+fn with_portal_uvs(mesh: &Mesh, aabb: &Aabb, right: Vec3, up: Vec3) -> Option<Mesh> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => positions,
+        _ => return None,
+    };
+    let center = Vec3::from(aabb.center);
+    let half_extents = Vec3::from(aabb.half_extents);
+    let (half_width, half_height) = (half_extents.dot(right.abs()), half_extents.dot(up.abs()));
+    let uvs: Vec<[f32; 2]> = positions
+        .iter()
+        .map(|position| {
+            let offset = Vec3::from(*position) - center;
+            [
+                0.5 + offset.dot(right) / (2.0 * half_width),
+                0.5 - offset.dot(up) / (2.0 * half_height),
+            ]
+        })
+        .collect();
+    let mut mesh = mesh.clone();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    Some(mesh)
+}
+
+#[allow(clippy::type_complexity)]
+fn setup_portals(
+    mut commands: Commands,
+    ready: Res<ScenesReady>,
+    portals: Query<(Entity, &Handle<Mesh>, &GlobalTransform), (With<Portal>, Without<PortalView>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !ready.0 {
+        return;
+    }
+    for (entity, mesh_handle, transform) in portals.iter() {
+        let mesh = match meshes.get(mesh_handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let aabb = match mesh.compute_aabb() {
+            Some(aabb) => aabb,
+            None => continue,
+        };
+        // The portal faces along its thinnest axis, and its height is the axis closest to vertical
+        let half_extents = Vec3::from(aabb.half_extents);
+        let mut axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        axes.sort_by(|a, b| a.dot(half_extents).total_cmp(&b.dot(half_extents)));
+        let [_, a, b] = axes;
+        let verticality = |axis: Vec3| {
+            transform
+                .affine()
+                .transform_vector3(axis)
+                .normalize()
+                .y
+                .abs()
+        };
+        let (right, up) = if verticality(a) > verticality(b) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let (front_mesh, back_mesh) = match (
+            with_portal_uvs(mesh, &aabb, right, up),
+            with_portal_uvs(mesh, &aabb, -right, up),
+        ) {
+            (Some(front), Some(back)) => (meshes.add(front), meshes.add(back)),
+            _ => {
+                warn!("portal mesh without positions, ignoring it");
+                commands.entity(entity).remove::<Portal>();
+                continue;
+            }
+        };
+
+        // Same aspect ratio as the portal
+        let world_size = |axis: Vec3| {
+            let half_size = axis * half_extents.dot(axis);
+            transform.affine().transform_vector3(half_size).length()
+        };
+        let (width, height) = (world_size(right), world_size(up));
+        let size = if width >= height {
+            Extent3d {
+                width: PORTAL_RESOLUTION,
+                height: ((PORTAL_RESOLUTION as f32 * height / width) as u32).max(1),
+                ..default()
+            }
+        } else {
+            Extent3d {
+                width: ((PORTAL_RESOLUTION as f32 * width / height) as u32).max(1),
+                height: PORTAL_RESOLUTION,
+                ..default()
+            }
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: Some("portal"),
+                size,
+                dimension: TextureDimension::D2,
+                // Kept in HDR, the main camera tonemaps it with the rest of its view
+                format: TextureFormat::Rgba16Float,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::RENDER_ATTACHMENT,
+            },
+            ..default()
+        };
+        image.resize(size);
+        let image = images.add(image);
+
+        let camera = commands
+            .spawn((
+                Camera {
+                    // Before the main camera, so that the portal is ready when it is drawn
+                    priority: -2,
+                    is_active: false,
+                    target: RenderTarget::Image(image.clone()),
+                    hdr: true,
+                    ..default()
+                },
+                CameraRenderGraph::new(core_3d::graph::NAME),
+                PortalProjection::default(),
+                VisibleEntities::default(),
+                Frustum::default(),
+                TransformBundle::default(),
+                Camera3d::default(),
+                Tonemapping::Disabled,
+                UiCameraConfig { show_ui: false },
+                // Without the portal layer, so that the portal doesn't render itself
+                RenderLayers::layer(0),
+                PortalCamera,
+                SpawnedFor(entity),
+            ))
+            .id();
+        commands.entity(entity).insert((
+            front_mesh.clone(),
+            materials.add(StandardMaterial {
+                base_color_texture: Some(image),
+                unlit: true,
+                ..default()
+            }),
+            RenderLayers::layer(PORTAL_LAYER),
+            PortalView {
+                camera,
+                aabb,
+                right,
+                up,
+                front_mesh,
+                back_mesh,
+            },
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_portal_cameras(
+    main_camera: Query<(&GlobalTransform, &Frustum), With<MainCamera>>,
+    mut portals: Query<(&Portal, &PortalView, &GlobalTransform, &mut Handle<Mesh>)>,
+    mut cameras: Query<
+        (
+            &mut Camera,
+            &mut PortalProjection,
+            &mut Transform,
+            &mut GlobalTransform,
+        ),
+        (With<PortalCamera>, Without<MainCamera>, Without<PortalView>),
+    >,
+) {
+    let (eye, frustum) = match main_camera.get_single() {
+        Ok((transform, frustum)) => (transform.translation(), frustum),
+        Err(_) => return,
+    };
+    for (portal, view, transform, mut mesh) in portals.iter_mut() {
+        let (mut camera, mut projection, mut camera_transform, mut camera_global) =
+            match cameras.get_mut(view.camera) {
+                Ok(camera) => camera,
+                Err(_) => continue,
+            };
+        let visible = frustum.intersects_obb(&view.aabb, &transform.compute_matrix(), true);
+
+        let affine = transform.affine();
+        let half_extents = Vec3::from(view.aabb.half_extents);
+        let center = affine.transform_point3(view.aabb.center.into());
+        let mut right = affine.transform_vector3(view.right * half_extents.dot(view.right.abs()));
+        let up = affine.transform_vector3(view.up * half_extents.dot(view.up.abs()));
+        // From behind, the mirrored UVs keep the texture the right way around
+        let in_front = (eye - center).dot(right.cross(up)) >= 0.0;
+        if !in_front {
+            right = -right;
+        }
+        let side_mesh = if in_front {
+            &view.front_mesh
+        } else {
+            &view.back_mesh
+        };
+        if *mesh != *side_mesh {
+            *mesh = side_mesh.clone();
+        }
+
+        // Where the main camera would be, relative to the destination of the portal
+        let destination = portal.destination.compute_affine();
+        let eye = destination.transform_point3(eye);
+        let center = destination.transform_point3(center);
+        let right = destination.transform_vector3(right);
+        let up = destination.transform_vector3(up);
+
+        let x_axis = right.normalize();
+        let z_axis = x_axis.cross(up).normalize();
+        let y_axis = z_axis.cross(x_axis);
+        let distance = (eye - center).dot(z_axis);
+        camera.is_active = visible && distance > MIN_PORTAL_DISTANCE;
+        if !camera.is_active {
+            continue;
+        }
+        // The near plane is the plane of the portal, nothing between it and the camera is rendered
+        let offset = center - eye;
+        let (x, y) = (offset.dot(x_axis), offset.dot(y_axis));
+        let (half_width, half_height) = (right.length(), up.length());
+        *projection = PortalProjection {
+            left: x - half_width,
+            right: x + half_width,
+            bottom: y - half_height,
+            top: y + half_height,
+            near: distance,
+        };
+        *camera_transform = Transform {
+            translation: eye,
+            rotation: Quat::from_mat3(&Mat3::from_cols(x_axis, y_axis, z_axis)),
+            ..default()
+        };
+        // Transforms are already propagated for this frame
+        *camera_global = GlobalTransform::from(*camera_transform);
+    }
+}
@@ -0,0 +1,465 @@
+use bevy::{
+    core_pipeline::{core_3d, fullscreen_vertex_shader::fullscreen_shader_vertex_state},
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_resource::{
+            BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType,
+            BufferInitDescriptor, BufferUsages, CachedRenderPipelineId, ColorTargetState,
+            ColorWrites, FragmentState, LoadOp, MultisampleState, Operations, PipelineCache,
+            PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages,
+            TextureSampleType, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::FallbackImage,
+        view::{ExtractedView, ViewTarget},
+        Extract, RenderApp, RenderStage,
+    },
+};
+
+use crate::{
+    color_grading::ColorGradingSettings,
+    plugins::camera::{BloomSettings, MainCamera},
+};
+
+pub const POST_PROCESSING_SHADER_PATH: &str = "shaders/post_processing.wgsl";
+
+const POST_PROCESSING_NODE: &str = "post_processing";
+
+/// Applies the effects of the [`PostProcessingStack`] to the image of the [`MainCamera`], one
+/// after the other in the order of the stack.
+///
+/// A node of the 3d graph draws each effect from the output of the previous one, on the HDR image
+/// once the bloom is added. The bloom is rendered by Bevy before the node, so it always comes
+/// first, and the scene cameras don't use the tonemapping of Bevy as the stack tonemaps. Bevy 0.9
+/// can't sample the depth of the main pass, the fog is the same over the whole image.
+pub struct PostProcessingPlugin;
+
+impl Plugin for PostProcessingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PostProcessingStack>()
+            .add_system(check_post_processing_stack);
+
+        let render_app = match app.get_sub_app_mut(RenderApp) {
+            Ok(render_app) => render_app,
+            Err(_) => return,
+        };
+        render_app
+            .init_resource::<PostProcessingPipeline>()
+            .init_resource::<ExtractedPostProcessing>()
+            .add_system_to_stage(RenderStage::Extract, extract_post_processing);
+
+        let node = PostProcessingNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let draw_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+        draw_3d_graph.add_node(POST_PROCESSING_NODE, node);
+        // After the bloom, in place of the tonemapping
+        draw_3d_graph
+            .add_node_edge(core_3d::graph::node::BLOOM, POST_PROCESSING_NODE)
+            .unwrap();
+        draw_3d_graph
+            .add_node_edge(POST_PROCESSING_NODE, core_3d::graph::node::TONEMAPPING)
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                draw_3d_graph.input_node().unwrap().id,
+                core_3d::graph::input::VIEW_ENTITY,
+                POST_PROCESSING_NODE,
+                PostProcessingNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+pub enum PostProcessEffect {
+    Bloom(BloomSettings),
+    Fog(FogSettings),
+    ColorGrade(ColorGradingSettings),
+    Tonemap(TonemapSettings),
+}
+
+/// The post processing effects, in the order they are applied. An effect missing from the stack
+/// isn't applied.
+#[derive(Resource)]
+pub struct PostProcessingStack {
+    pub effects: Vec<PostProcessEffect>,
+}
+
+impl Default for PostProcessingStack {
+    fn default() -> Self {
+        Self {
+            effects: vec![
+                PostProcessEffect::Bloom(BloomSettings::default()),
+                PostProcessEffect::Tonemap(TonemapSettings::default()),
+                // Graded from the tonemapped colors, as the LUTs only cover 0 to 1
+                PostProcessEffect::ColorGrade(ColorGradingSettings::default()),
+            ],
+        }
+    }
+}
+
+impl PostProcessingStack {
+    pub fn bloom(&self) -> Option<&BloomSettings> {
+        self.effects.iter().find_map(|effect| match effect {
+            PostProcessEffect::Bloom(settings) => Some(settings),
+            _ => None,
+        })
+    }
+
+    pub fn bloom_mut(&mut self) -> Option<&mut BloomSettings> {
+        self.effects.iter_mut().find_map(|effect| match effect {
+            PostProcessEffect::Bloom(settings) => Some(settings),
+            _ => None,
+        })
+    }
+
+    pub fn tonemap_mut(&mut self) -> Option<&mut TonemapSettings> {
+        self.effects.iter_mut().find_map(|effect| match effect {
+            PostProcessEffect::Tonemap(settings) => Some(settings),
+            _ => None,
+        })
+    }
+
+    pub fn color_grading_mut(&mut self) -> Option<&mut ColorGradingSettings> {
+        self.effects.iter_mut().find_map(|effect| match effect {
+            PostProcessEffect::ColorGrade(settings) => Some(settings),
+            _ => None,
+        })
+    }
+}
+
+pub struct FogSettings {
+    pub color: Color,
+    // Of the fog color over the image, from 0 to 1
+    pub opacity: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            color: Color::rgb(0.5, 0.55, 0.6),
+            opacity: 0.2,
+        }
+    }
+}
+
+pub struct TonemapSettings {
+    // The colors are multiplied by it before being tonemapped
+    pub exposure: f32,
+    // Hides the banding in dark gradients, as the tonemapping of Bevy
+    pub deband_dither: bool,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            deband_dither: true,
+        }
+    }
+}
+
+fn check_post_processing_stack(stack: Res<PostProcessingStack>) {
+    if !stack.is_changed() {
+        return;
+    }
+    if stack
+        .effects
+        .iter()
+        .skip(1)
+        .any(|effect| matches!(effect, PostProcessEffect::Bloom(_)))
+    {
+        warn!("the bloom is rendered by Bevy before the other post processing effects");
+    }
+}
+
+// An effect applied by the node, with the values of its uniform
+enum PostProcessPass {
+    Fog { color: Color, opacity: f32 },
+    Tonemap { exposure: f32, deband_dither: bool },
+    ColorGrade { lut: Handle<Image>, strength: f32 },
+}
+
+impl PostProcessPass {
+    // A color, then up to four values
+    fn uniform(&self) -> [f32; 8] {
+        match self {
+            PostProcessPass::Fog { color, opacity } => {
+                let [r, g, b, a] = color.as_linear_rgba_f32();
+                [r, g, b, a, opacity.clamp(0.0, 1.0), 0.0, 0.0, 0.0]
+            }
+            PostProcessPass::Tonemap {
+                exposure,
+                deband_dither,
+            } => {
+                let dither = if *deband_dither { 1.0 } else { 0.0 };
+                [0.0, 0.0, 0.0, 0.0, *exposure, dither, 0.0, 0.0]
+            }
+            PostProcessPass::ColorGrade { strength, .. } => {
+                [0.0, 0.0, 0.0, 0.0, strength.clamp(0.0, 1.0), 0.0, 0.0, 0.0]
+            }
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ExtractedPostProcessing {
+    main_view: Option<Entity>,
+    passes: Vec<PostProcessPass>,
+}
+
+fn extract_post_processing(
+    mut extracted: ResMut<ExtractedPostProcessing>,
+    stack: Extract<Res<PostProcessingStack>>,
+    main_camera: Extract<Query<Entity, With<MainCamera>>>,
+) {
+    // Cameras keep their entity in the render world
+    extracted.main_view = main_camera.get_single().ok();
+    extracted.passes.clear();
+    for effect in stack.effects.iter() {
+        extracted.passes.push(match effect {
+            // Rendered by Bevy
+            PostProcessEffect::Bloom(_) => continue,
+            PostProcessEffect::Fog(settings) => PostProcessPass::Fog {
+                color: settings.color,
+                opacity: settings.opacity,
+            },
+            PostProcessEffect::Tonemap(settings) => PostProcessPass::Tonemap {
+                exposure: settings.exposure,
+                deband_dither: settings.deband_dither,
+            },
+            PostProcessEffect::ColorGrade(settings) => match &settings.lut_texture {
+                Some(lut) => PostProcessPass::ColorGrade {
+                    lut: lut.clone(),
+                    strength: settings.strength,
+                },
+                None => continue,
+            },
+        });
+    }
+}
+
+#[derive(Resource)]
+struct PostProcessingPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    fog: CachedRenderPipelineId,
+    tonemap: CachedRenderPipelineId,
+    color_grade: CachedRenderPipelineId,
+}
+
+impl PostProcessingPipeline {
+    fn pipeline(&self, pass: &PostProcessPass) -> CachedRenderPipelineId {
+        match pass {
+            PostProcessPass::Fog { .. } => self.fog,
+            PostProcessPass::Tonemap { .. } => self.tonemap,
+            PostProcessPass::ColorGrade { .. } => self.color_grade,
+        }
+    }
+}
+
+impl FromWorld for PostProcessingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let texture = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+        // The same for every effect: the image, the uniform of the effect and the LUT
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("post_processing_layout"),
+            entries: &[
+                texture(0),
+                sampler(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture(3),
+                sampler(4),
+            ],
+        });
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+
+        let shader = world
+            .resource::<AssetServer>()
+            .load(POST_PROCESSING_SHADER_PATH);
+        let mut pipeline_cache = world.resource_mut::<PipelineCache>();
+        let mut queue = |entry_point: &'static str| {
+            pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+                label: Some(format!("post_processing_{entry_point}_pipeline").into()),
+                layout: Some(vec![layout.clone()]),
+                vertex: fullscreen_shader_vertex_state(),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    shader: shader.clone(),
+                    shader_defs: Vec::new(),
+                    entry_point: entry_point.into(),
+                    targets: vec![Some(ColorTargetState {
+                        format: ViewTarget::TEXTURE_FORMAT_HDR,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+            })
+        };
+        let fog = queue("fog");
+        let tonemap = queue("tonemap");
+        let color_grade = queue("color_grade");
+        Self {
+            layout,
+            sampler,
+            fog,
+            tonemap,
+            color_grade,
+        }
+    }
+}
+
+// Draws each effect of the stack from the main texture of the view to the other
+struct PostProcessingNode {
+    query: QueryState<&'static ViewTarget, With<ExtractedView>>,
+}
+
+impl PostProcessingNode {
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for PostProcessingNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let extracted = world.resource::<ExtractedPostProcessing>();
+        // The other cameras keep the post processing of Bevy
+        if extracted.passes.is_empty() || extracted.main_view != Some(view_entity) {
+            return Ok(());
+        }
+        let target = match self.query.get_manual(world, view_entity) {
+            Ok(target) if target.is_hdr() => target,
+            _ => return Ok(()),
+        };
+        let pipeline = world.resource::<PostProcessingPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let gpu_images = world.resource::<RenderAssets<Image>>();
+        let fallback_image = world.resource::<FallbackImage>();
+
+        for pass in extracted.passes.iter() {
+            let render_pipeline = match pipeline_cache.get_render_pipeline(pipeline.pipeline(pass))
+            {
+                Some(render_pipeline) => render_pipeline,
+                None => continue,
+            };
+            // Skipped until the LUT is loaded, the other effects don't sample it
+            let lut = match pass {
+                PostProcessPass::ColorGrade { lut, .. } => match gpu_images.get(lut) {
+                    Some(lut) => lut,
+                    None => continue,
+                },
+                _ => &**fallback_image,
+            };
+            let uniform = pass
+                .uniform()
+                .iter()
+                .flat_map(|value| value.to_ne_bytes())
+                .collect::<Vec<_>>();
+            let uniform =
+                render_context
+                    .render_device
+                    .create_buffer_with_data(&BufferInitDescriptor {
+                        label: Some("post_processing_uniform"),
+                        contents: &uniform,
+                        usage: BufferUsages::UNIFORM,
+                    });
+
+            let post_process = target.post_process_write();
+            let bind_group = render_context
+                .render_device
+                .create_bind_group(&BindGroupDescriptor {
+                    label: Some("post_processing_bind_group"),
+                    layout: &pipeline.layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(post_process.source),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&pipeline.sampler),
+                        },
+                        BindGroupEntry {
+                            binding: 2,
+                            resource: uniform.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: BindingResource::TextureView(&lut.texture_view),
+                        },
+                        BindGroupEntry {
+                            binding: 4,
+                            resource: BindingResource::Sampler(&lut.sampler),
+                        },
+                    ],
+                });
+            let pass_descriptor = RenderPassDescriptor {
+                label: Some("post_processing_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: post_process.destination,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK.into()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            };
+            let mut render_pass = render_context
+                .command_encoder
+                .begin_render_pass(&pass_descriptor);
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+
+/// Serves the profiling scopes of the systems to the puffin viewer, on the default port of the
+/// local machine only.
+///
+/// Only available with the `profiling` feature. Connect to it with `puffin_viewer`.
+pub struct ProfilingPlugin;
+
+impl Plugin for ProfilingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(start_puffin_server)
+            .add_system_to_stage(CoreStage::First, new_puffin_frame);
+    }
+}
+
+// Keeps the server running for as long as the app
+#[derive(Resource)]
+struct PuffinServer {
+    _server: puffin_http::Server,
+}
+
+fn start_puffin_server(mut commands: Commands) {
+    let address = format!("127.0.0.1:{}", puffin_http::DEFAULT_PORT);
+    match puffin_http::Server::new(&address) {
+        Ok(server) => {
+            puffin::set_scopes_on(true);
+            info!("puffin server listening on {address}");
+            commands.insert_resource(PuffinServer { _server: server });
+        }
+        Err(err) => error!("failed to start the puffin server: {err}"),
+    }
+}
+
+fn new_puffin_frame() {
+    puffin::GlobalProfiler::lock().new_frame();
+}
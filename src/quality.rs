@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+
+use crate::{
+    cli, light_audit::LightAuditSettings, plugins::lights::LightLODSettings,
+    post_processing::PostProcessingStack, vertex_ao::VertexAoSettings,
+};
+
+/// Applies the [`QualityTier`] selected with `--quality` to the render settings at startup, and
+/// prints it with `I`.
+///
+/// The shadow map sizes are read in `main` before the app is built, the sizes given on the command
+/// line or in the environment take precedence over the ones of the tier.
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(QualityTier::from_args())
+            .add_startup_system(setup_quality_tier)
+            .add_system(quality_tier_info);
+    }
+}
+
+/// Render quality, from integrated GPUs to large discrete GPUs.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QualityTier {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl QualityTier {
+    // WebGL2 usually limits textures to 4096 pixels, and is slower with large shadow maps
+    fn default_tier() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            QualityTier::Medium
+        } else {
+            QualityTier::High
+        }
+    }
+
+    pub fn from_args() -> Self {
+        match cli::value("--quality").as_deref() {
+            None => Self::default_tier(),
+            Some("low") => QualityTier::Low,
+            Some("medium") => QualityTier::Medium,
+            Some("high") => QualityTier::High,
+            Some("ultra") => QualityTier::Ultra,
+            Some(value) => {
+                eprintln!("invalid quality tier: {value:?}, expected low, medium, high or ultra");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn point_shadow_size(self) -> usize {
+        match self {
+            QualityTier::Low => 512,
+            QualityTier::Medium => 1024,
+            QualityTier::High => 2048,
+            QualityTier::Ultra => 4096,
+        }
+    }
+
+    pub fn dir_shadow_size(self) -> usize {
+        match self {
+            QualityTier::Low => 2048,
+            QualityTier::Medium => 4096,
+            QualityTier::High => 8192,
+            QualityTier::Ultra => 16384,
+        }
+    }
+
+    // Above this many shadow casting point lights, the light audit warns
+    fn max_shadow_casters(self) -> usize {
+        match self {
+            QualityTier::Low => 4,
+            QualityTier::Medium => 8,
+            QualityTier::High => 10,
+            QualityTier::Ultra => 16,
+        }
+    }
+
+    // Bloom is disabled on low, and a lower threshold makes more of the scene bloom
+    fn bloom_threshold(self) -> Option<f32> {
+        match self {
+            QualityTier::Low => None,
+            QualityTier::Medium => Some(1.2),
+            QualityTier::High => Some(1.0),
+            QualityTier::Ultra => Some(0.8),
+        }
+    }
+
+    fn vertex_ao(self) -> bool {
+        self != QualityTier::Low
+    }
+
+    // Point lights kept on, the nearest to the camera
+    fn max_point_lights(self) -> Option<usize> {
+        match self {
+            QualityTier::Low => Some(16),
+            QualityTier::Medium => Some(32),
+            QualityTier::High => Some(64),
+            QualityTier::Ultra => None,
+        }
+    }
+}
+
+impl std::fmt::Display for QualityTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            QualityTier::Low => "low",
+            QualityTier::Medium => "medium",
+            QualityTier::High => "high",
+            QualityTier::Ultra => "ultra",
+        })
+    }
+}
+
+fn setup_quality_tier(
+    tier: Res<QualityTier>,
+    post_processing: ResMut<PostProcessingStack>,
+    light_audit: ResMut<LightAuditSettings>,
+    vertex_ao: ResMut<VertexAoSettings>,
+    light_lod: ResMut<LightLODSettings>,
+) {
+    apply_quality_tier(*tier, post_processing, light_audit, vertex_ao, light_lod);
+    info!("quality tier: {}", *tier);
+}
+
+/// Changes the settings affected by the tier, except for the shadow map sizes.
+pub fn apply_quality_tier(
+    tier: QualityTier,
+    mut post_processing: ResMut<PostProcessingStack>,
+    mut light_audit: ResMut<LightAuditSettings>,
+    mut vertex_ao: ResMut<VertexAoSettings>,
+    mut light_lod: ResMut<LightLODSettings>,
+) {
+    if let Some(bloom) = post_processing.bloom_mut() {
+        match tier.bloom_threshold() {
+            Some(threshold) => {
+                bloom.enabled = true;
+                bloom.threshold = threshold;
+            }
+            None => bloom.enabled = false,
+        }
+    }
+    light_audit.max_shadow_casters = tier.max_shadow_casters();
+    vertex_ao.enabled = tier.vertex_ao();
+    light_lod.max_lights = tier.max_point_lights();
+}
+
+fn quality_tier_info(input: Res<Input<KeyCode>>, tier: Res<QualityTier>) {
+    if input.just_pressed(KeyCode::I) {
+        info!("quality tier: {}", *tier);
+    }
+}
@@ -0,0 +1,644 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    gltf::GltfExtras,
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{primitives::Aabb, view::RenderLayers},
+    utils::{BoxedFuture, HashMap, HashSet},
+};
+use serde::Deserialize;
+
+use crate::{
+    color_temperature::{kelvin_to_rgb, ColorTemperature},
+    door::Door,
+    ghost_mode::OriginalAlpha,
+    mesh_utils::{mesh_center_world, mesh_size_world, BISTRO_SCALE},
+    plugins::{
+        camera::DEBUG_LAYER,
+        lights::{
+            FlickerLight, LightGroup, LightGroupKind, LightLOD, LightSettings, StripLight,
+            STRIP_LIGHT_MIN_RATIO, STRIP_LIGHT_SPACING,
+        },
+        scene::{SceneFixSettings, WineGlass},
+    },
+    portal::Portal,
+    shade::SunBlocker,
+    subdivision::SubdivisionLevel,
+    triggers::{DoorBell, TriggerVolume},
+};
+
+pub const SCENE_FIX_MANIFEST_PATH: &str = "bistro.fixes.ron";
+
+/// Loads the rules used to fix the scenes from `bistro.fixes.ron`.
+///
+/// The [`SceneFixManifest`] resource is only inserted once the file is loaded, the scenes are not
+/// fixed before that.
+pub struct SceneFixPlugin;
+
+impl Plugin for SceneFixPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SceneFixManifest>()
+            .add_asset_loader(SceneFixManifestLoader)
+            .add_startup_system(load_scene_fix_manifest)
+            .add_startup_system(setup_light_bulbs)
+            .add_system(update_scene_fix_manifest);
+    }
+}
+
+/// What to do with the entities matching a rule.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum SceneFixAction {
+    // Spawn a point light at the center of the mesh, or a strip for long fixtures
+    SpawnCeilingLight,
+    // Spawn a point light at the center of the mesh
+    SpawnWallLight,
+    // Spawn a point light and a "lightbulb" in the lantern, and make its materials transparent
+    SpawnLantern,
+    // Spawn a point light at the center of the glass, and make it transparent
+    SpawnStreetLight,
+    // Make the mesh transparent, and stop it from casting shadows
+    Transparent,
+    // Stop the mesh from casting shadows
+    NoShadows,
+    // Keep the glass transparent if it can be rendered, see `SceneFixSettings`
+    WineGlass,
+    // Open the door when the camera gets close, with the hinge on a side of the mesh
+    Door,
+    // Ring a bell when the camera enters the area around the mesh, with the `audio` feature
+    DoorBell,
+    // Subdivide the meshes this many times when the camera is close, see `SubdivisionLevel`
+    Subdivide(u8),
+    // Show what is behind the mesh with a camera of its own, see `Portal`
+    Portal,
+    // Shade the ground from the sun, see `ShadeMap`
+    SunBlocker,
+    // Walls made see-through by the ghost mode, see `OriginalAlpha`
+    Ghost,
+    Despawn,
+}
+
+/// A rule matches an entity when its name matches all the conditions that are set.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SceneFixRule {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub contains: Option<String>,
+    pub action: SceneFixAction,
+}
+
+impl SceneFixRule {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.as_ref().is_none_or(|exact| name == exact)
+            && self
+                .prefix
+                .as_ref()
+                .is_none_or(|prefix| name.starts_with(prefix.as_str()))
+            && self
+                .suffix
+                .as_ref()
+                .is_none_or(|suffix| name.ends_with(suffix.as_str()))
+            && self
+                .contains
+                .as_ref()
+                .is_none_or(|contains| name.contains(contains.as_str()))
+    }
+}
+
+/// Fixes set on a node of the glTF files, in the JSON of its `extras`. They are the custom
+/// properties of the object when exporting from Blender, for example `{"light": "Lantern"}`.
+///
+/// Other properties are ignored. A node with extras asking for a fix isn't matched with the rules
+/// of the manifest, the names are only used for the nodes without them.
+#[derive(Deserialize, Default, Debug)]
+pub struct SceneFixExtras {
+    // Spawn the light of this group, as the `Spawn...Light` actions
+    #[serde(default)]
+    pub light: Option<LightGroupKind>,
+    #[serde(default)]
+    pub transparent: bool,
+    #[serde(default)]
+    pub no_shadows: bool,
+    #[serde(default)]
+    pub remove: bool,
+}
+
+impl SceneFixExtras {
+    pub fn parse(extras: &GltfExtras) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(&extras.value)
+    }
+
+    // The actions to apply, in the order the rules would apply them
+    pub fn actions(&self) -> Vec<SceneFixAction> {
+        if self.remove {
+            return vec![SceneFixAction::Despawn];
+        }
+        let mut actions = Vec::new();
+        if let Some(kind) = self.light {
+            actions.push(match kind {
+                LightGroupKind::Ceiling => SceneFixAction::SpawnCeilingLight,
+                LightGroupKind::Wall => SceneFixAction::SpawnWallLight,
+                LightGroupKind::Lantern => SceneFixAction::SpawnLantern,
+                LightGroupKind::StreetLight => SceneFixAction::SpawnStreetLight,
+            });
+        }
+        if self.transparent {
+            actions.push(SceneFixAction::Transparent);
+        }
+        // Already done by the transparent action
+        if self.no_shadows && !self.transparent {
+            actions.push(SceneFixAction::NoShadows);
+        }
+        actions
+    }
+}
+
+/// Rules for each scene, applied in order to every named entity.
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "5a1b7c3e-2f0d-4a8e-9b6c-1d2e3f4a5b6c"]
+pub struct SceneFixManifest {
+    pub interior: Vec<SceneFixRule>,
+    pub exterior: Vec<SceneFixRule>,
+}
+
+struct SceneFixManifestLoader;
+
+impl AssetLoader for SceneFixManifestLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let manifest = ron::de::from_bytes::<SceneFixManifest>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(manifest));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["fixes.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct SceneFixManifestHandle(Handle<SceneFixManifest>);
+
+fn load_scene_fix_manifest(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SceneFixManifestHandle(
+        asset_server.load(SCENE_FIX_MANIFEST_PATH),
+    ));
+}
+
+fn update_scene_fix_manifest(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SceneFixManifest>>,
+    assets: Res<Assets<SceneFixManifest>>,
+    handle: Res<SceneFixManifestHandle>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: updated } | AssetEvent::Modified { handle: updated }
+                if *updated == handle.0 =>
+            {
+                if let Some(manifest) = assets.get(updated) {
+                    commands.insert_resource(manifest.clone());
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Entities spawned by a fix outside of the hierarchy of the scene, with the scene entity they
+/// were spawned for. They are despawned with it when the exterior is streamed out.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpawnedFor(pub Entity);
+
+// Mesh and material of the "lightbulbs" spawned in the lanterns
+#[derive(Resource)]
+pub struct LightBulbAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_light_bulbs(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(LightBulbAssets {
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 5.0,
+            ..Default::default()
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::YELLOW,
+            unlit: true,
+            ..Default::default()
+        }),
+    });
+}
+
+// Everything needed to apply the rules, with the materials to change once all entities are done
+#[derive(SystemParam)]
+pub(crate) struct SceneFix<'w, 's> {
+    commands: Commands<'w, 's>,
+    has_mesh: Query<'w, 's, &'static Handle<Mesh>>,
+    has_material: Query<'w, 's, &'static Handle<StandardMaterial>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    light_settings: Res<'w, LightSettings>,
+    settings: Res<'w, SceneFixSettings>,
+    light_bulbs: Res<'w, LightBulbAssets>,
+    transparent_materials: Local<'s, HashSet<Handle<StandardMaterial>>>,
+    glass_materials: Local<'s, HashSet<Handle<StandardMaterial>>>,
+    // With the name of the first entity using them
+    missing_tangents: Local<'s, HashMap<Handle<Mesh>, String>>,
+    // Meshes are only checked once, the scenes are fixed over several frames
+    checked_meshes: Local<'s, HashSet<Handle<Mesh>>>,
+    // Entities whose extras couldn't be parsed, only warned about once
+    invalid_extras: Local<'s, HashSet<Entity>>,
+}
+
+impl<'w, 's> SceneFix<'w, 's> {
+    // Apply the fixes of the extras, or else the rules matching the name, stopping if the entity
+    // is despawned
+    pub fn apply(
+        &mut self,
+        rules: &[SceneFixRule],
+        entity: Entity,
+        name: &str,
+        extras: Option<&GltfExtras>,
+        children: &Children,
+    ) {
+        let extras_actions = match extras.map(SceneFixExtras::parse) {
+            Some(Ok(extras)) => extras.actions(),
+            Some(Err(err)) => {
+                if self.invalid_extras.insert(entity) {
+                    warn!("invalid extras on {name}, using the rules: {err}");
+                }
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        let actions = if extras_actions.is_empty() {
+            rules
+                .iter()
+                .filter(|rule| rule.matches(name))
+                .map(|rule| rule.action)
+                .collect()
+        } else {
+            extras_actions
+        };
+        for action in actions {
+            self.apply_action(action, entity, children);
+            if matches!(action, SceneFixAction::Despawn) {
+                return;
+            }
+        }
+        for child in children.iter() {
+            let handle = match self.has_mesh.get(*child) {
+                Ok(handle) if !self.checked_meshes.contains(handle) => handle,
+                _ => continue,
+            };
+            // Checked once loaded
+            if let Some(mesh) = self.meshes.get(handle) {
+                if mesh.attribute(Mesh::ATTRIBUTE_TANGENT).is_none() {
+                    self.missing_tangents
+                        .insert(handle.clone(), name.to_string());
+                }
+                self.checked_meshes.insert(handle.clone());
+            }
+        }
+    }
+
+    fn apply_action(&mut self, action: SceneFixAction, entity: Entity, children: &Children) {
+        let light_settings = &self.light_settings;
+        match action {
+            SceneFixAction::SpawnCeilingLight => {
+                // - Spawn a point light
+                // - Make the mesh not casting shadows
+                let child = children[0];
+                self.commands.entity(child).insert(NotShadowCaster);
+                // For those lights, they are not transformed to their place, but the mesh is moved.
+                // We find the center of the mesh, which is where the light should be.
+                let mesh = child_mesh(&self.has_mesh, &self.meshes, child);
+                if let (Some(center), Some(size)) = (
+                    mesh.and_then(|mesh| mesh_center_world(mesh, BISTRO_SCALE)),
+                    mesh.and_then(|mesh| mesh_size_world(mesh, BISTRO_SCALE)),
+                ) {
+                    // Strips are lit along their longest horizontal axis
+                    let (length, width, rotation) = if size.x >= size.z {
+                        (size.x, size.z, Quat::IDENTITY)
+                    } else {
+                        (
+                            size.z,
+                            size.x,
+                            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+                        )
+                    };
+                    let temperature = ColorTemperature(light_settings.ceiling_temperature);
+                    if length > STRIP_LIGHT_MIN_RATIO * width.max(0.05) {
+                        self.commands.spawn((
+                            SpatialBundle::from_transform(
+                                Transform::from_translation(center).with_rotation(rotation),
+                            ),
+                            StripLight {
+                                length,
+                                count: ((length / STRIP_LIGHT_SPACING).round() as usize)
+                                    .clamp(2, 8),
+                            },
+                            SpawnedFor(entity),
+                        ));
+                    } else {
+                        self.commands
+                            .spawn(PointLightBundle {
+                                transform: Transform::from_translation(center)
+                                    .with_scale(Vec3::splat(0.16)),
+                                point_light: PointLight {
+                                    color: kelvin_to_rgb(temperature.0),
+                                    intensity: light_settings.ceiling,
+                                    range: light_settings.ceiling / light_settings.range_ratio,
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            })
+                            .insert((
+                                LightGroup(LightGroupKind::Ceiling),
+                                LightLOD::new(light_settings.ceiling),
+                                temperature,
+                                SpawnedFor(entity),
+                            ));
+                    }
+                }
+            }
+            SceneFixAction::SpawnWallLight => {
+                // - Spawn a point light
+                // - Make the mesh not casting shadows
+                let child = children[0];
+                self.commands.entity(child).insert(NotShadowCaster);
+                // For those lights, they are not transformed to their place, but the mesh is moved.
+                // We find the center of the mesh, which is where the light should be.
+                if let Some(center) = child_mesh(&self.has_mesh, &self.meshes, child)
+                    .and_then(|mesh| mesh_center_world(mesh, BISTRO_SCALE))
+                {
+                    self.commands
+                        .spawn(PointLightBundle {
+                            transform: Transform::from_translation(center)
+                                .with_scale(Vec3::splat(0.16)),
+                            point_light: PointLight {
+                                color: kelvin_to_rgb(light_settings.wall_temperature),
+                                intensity: light_settings.ceiling,
+                                range: light_settings.ceiling / light_settings.range_ratio,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .insert((
+                            LightGroup(LightGroupKind::Wall),
+                            LightLOD::new(light_settings.ceiling),
+                            ColorTemperature(light_settings.wall_temperature),
+                            SpawnedFor(entity),
+                        ));
+                }
+            }
+            SceneFixAction::SpawnLantern => {
+                // - Spawn a point light
+                // - Make the mesh not casting shadows
+                // - Make the material transparent
+                // - Spawn a "lightbulb"
+                let light_bulbs = &self.light_bulbs;
+                self.commands.entity(entity).with_children(|lantern| {
+                    lantern.spawn((
+                        PbrBundle {
+                            mesh: light_bulbs.mesh.clone(),
+                            material: light_bulbs.material.clone(),
+                            transform: Transform::from_xyz(0.0, -80.0, 0.0),
+                            ..Default::default()
+                        },
+                        NotShadowCaster,
+                        NotShadowReceiver,
+                        RenderLayers::layer(DEBUG_LAYER),
+                    ));
+                    lantern
+                        .spawn(PointLightBundle {
+                            transform: Transform::from_xyz(0.0, -80.0, 0.0),
+                            point_light: PointLight {
+                                color: kelvin_to_rgb(light_settings.lantern_temperature),
+                                intensity: light_settings.lantern,
+                                range: light_settings.lantern / light_settings.range_ratio,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .insert((
+                            LightGroup(LightGroupKind::Lantern),
+                            LightLOD::new(light_settings.lantern),
+                            ColorTemperature(light_settings.lantern_temperature),
+                            FlickerLight {
+                                base_intensity: light_settings.lantern,
+                                noise_seed: rand::random(),
+                                ..Default::default()
+                            },
+                        ));
+                });
+                for child in children.iter() {
+                    self.commands.entity(*child).insert(NotShadowCaster);
+                    if let Ok(material) = self.has_material.get(*child) {
+                        self.transparent_materials.insert(material.clone());
+                    }
+                }
+            }
+            SceneFixAction::SpawnStreetLight => {
+                // - Spawn a point light
+                // - Make the mesh not casting shadows
+                // - Make the material transparent
+                let child = children[0];
+                self.commands.entity(child).insert(NotShadowCaster);
+                if let Ok(material) = self.has_material.get(child) {
+                    self.transparent_materials.insert(material.clone());
+                }
+                // For those lights, they are not transformed to their place, but the mesh is moved.
+                // We find the center of the mesh, which is where the light should be.
+                if let Some(center) = child_mesh(&self.has_mesh, &self.meshes, child)
+                    .and_then(|mesh| mesh_center_world(mesh, BISTRO_SCALE))
+                {
+                    self.commands
+                        .spawn(PointLightBundle {
+                            transform: Transform::from_translation(center)
+                                .with_scale(Vec3::splat(0.16)),
+                            point_light: PointLight {
+                                color: kelvin_to_rgb(light_settings.streetlight_temperature),
+                                intensity: light_settings.streetlight,
+                                range: light_settings.streetlight / light_settings.range_ratio,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .insert((
+                            LightGroup(LightGroupKind::StreetLight),
+                            LightLOD::new(light_settings.streetlight),
+                            ColorTemperature(light_settings.streetlight_temperature),
+                            SpawnedFor(entity),
+                        ));
+                }
+            }
+            SceneFixAction::Transparent => {
+                let child = children[0];
+                self.commands.entity(child).insert(NotShadowCaster);
+                if let Ok(material) = self.has_material.get(child) {
+                    self.transparent_materials.insert(material.clone());
+                }
+            }
+            SceneFixAction::NoShadows => {
+                self.commands.entity(children[0]).insert(NotShadowCaster);
+            }
+            SceneFixAction::WineGlass => {
+                // Keep the glasses that can be rendered, and make them transparent
+                let glass_materials: Option<Vec<_>> = children
+                    .iter()
+                    .map(|child| {
+                        let mesh = self.meshes.get(self.has_mesh.get(*child).ok()?)?;
+                        mesh.attribute(Mesh::ATTRIBUTE_NORMAL)?;
+                        let material = self.has_material.get(*child).ok()?;
+                        self.materials.get(material).map(|_| material.clone())
+                    })
+                    .collect();
+                match glass_materials {
+                    Some(glass_materials) if !self.settings.remove_glasses => {
+                        self.commands.entity(entity).insert(WineGlass);
+                        self.glass_materials.extend(glass_materials);
+                    }
+                    _ => self.commands.entity(entity).despawn_recursive(),
+                }
+            }
+            SceneFixAction::Door => {
+                // The hinge is on the vertical edge at one end of its longest horizontal axis
+                if let Some(aabb) = child_mesh(&self.has_mesh, &self.meshes, children[0])
+                    .and_then(|mesh| mesh.compute_aabb())
+                {
+                    let center = Vec3::from(aabb.center);
+                    let half_extents = Vec3::from(aabb.half_extents);
+                    let hinge = if half_extents.x >= half_extents.z {
+                        center - Vec3::X * half_extents.x
+                    } else {
+                        center - Vec3::Z * half_extents.z
+                    };
+                    self.commands
+                        .entity(entity)
+                        .insert(Door::new(hinge, center));
+                }
+            }
+            SceneFixAction::DoorBell => {
+                let mesh = child_mesh(&self.has_mesh, &self.meshes, children[0]);
+                if let (Some(center), Some(size)) = (
+                    mesh.and_then(|mesh| mesh_center_world(mesh, BISTRO_SCALE)),
+                    mesh.and_then(|mesh| mesh_size_world(mesh, BISTRO_SCALE)),
+                ) {
+                    // A meter on each side of the mesh, from the ground
+                    let half_extents = size / 2.0 + Vec3::new(1.0, 0.0, 1.0);
+                    self.commands.spawn((
+                        SpatialBundle::from_transform(Transform::from_xyz(
+                            center.x,
+                            half_extents.y,
+                            center.z,
+                        )),
+                        TriggerVolume::new(Aabb::from_min_max(-half_extents, half_extents)),
+                        DoorBell,
+                        SpawnedFor(entity),
+                    ));
+                }
+            }
+            SceneFixAction::Subdivide(level) => {
+                for child in children.iter() {
+                    if self.has_mesh.contains(*child) {
+                        self.commands.entity(*child).insert(SubdivisionLevel(level));
+                    }
+                }
+            }
+            SceneFixAction::Portal => {
+                self.commands.entity(children[0]).insert(Portal::default());
+            }
+            SceneFixAction::SunBlocker => {
+                for child in children.iter() {
+                    if self.has_mesh.contains(*child) {
+                        self.commands.entity(*child).insert(SunBlocker);
+                    }
+                }
+            }
+            SceneFixAction::Ghost => {
+                // Only the opaque meshes, the transparent ones already show what is behind them
+                for child in children.iter() {
+                    let handle = match self.has_material.get(*child) {
+                        Ok(handle) if !self.transparent_materials.contains(handle) => handle,
+                        _ => continue,
+                    };
+                    if let Some(material) = self.materials.get(handle) {
+                        if material.alpha_mode == AlphaMode::Opaque {
+                            self.commands
+                                .entity(*child)
+                                .insert(OriginalAlpha(material.base_color.a()));
+                        }
+                    }
+                }
+            }
+            SceneFixAction::Despawn => {
+                self.commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    // Change the materials collected while applying the rules
+    pub fn fix_materials(&mut self) {
+        for handle in self.transparent_materials.drain() {
+            if let Some(material) = self.materials.get_mut(&handle) {
+                if material.alpha_mode == AlphaMode::Opaque {
+                    material.base_color.set_a(0.2);
+                    material.alpha_mode = AlphaMode::Blend;
+                }
+            }
+        }
+        for handle in self.glass_materials.drain() {
+            if let Some(material) = self.materials.get_mut(&handle) {
+                material.alpha_mode = AlphaMode::Blend;
+                material.base_color.set_a(0.6);
+            }
+        }
+        for (_, material) in self.materials.iter_mut() {
+            material.flip_normal_map_y = true;
+        }
+    }
+
+    // Normal maps need tangents, generate them for the meshes collected while applying the rules
+    pub fn generate_tangents(&mut self) {
+        for (handle, name) in self.missing_tangents.drain() {
+            if let Some(mesh) = self.meshes.get_mut(&handle) {
+                match mesh.generate_tangents() {
+                    Ok(()) => info!("generated tangents for {name}"),
+                    Err(err) => warn!("could not generate tangents for {name}: {err}"),
+                }
+            }
+        }
+    }
+
+    // Once a scene is fixed, so that its meshes can be freed and are checked again if it is
+    // spawned again
+    pub fn clear_checked_meshes(&mut self) {
+        self.checked_meshes.clear();
+    }
+}
+
+fn child_mesh<'a>(
+    has_mesh: &Query<&Handle<Mesh>>,
+    meshes: &'a Assets<Mesh>,
+    entity: Entity,
+) -> Option<&'a Mesh> {
+    meshes.get(has_mesh.get(entity).ok()?)
+}
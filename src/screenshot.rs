@@ -0,0 +1,494 @@
+use std::{
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bevy::{
+    core_pipeline::{core_3d, fullscreen_vertex_shader::fullscreen_shader_vertex_state},
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_resource::{
+            BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferDescriptor,
+            BufferUsages, CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d,
+            FragmentState, ImageCopyBuffer, ImageDataLayout, LoadOp, MapMode, MultisampleState,
+            Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+            SamplerDescriptor, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureSampleType, TextureUsages, TextureViewDescriptor, TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::{ExtractedView, ViewTarget},
+        Extract, RenderApp, RenderStage,
+    },
+    tasks::IoTaskPool,
+};
+
+use crate::plugins::camera::MainCamera;
+
+pub const SCREENSHOT_SHADER_PATH: &str = "shaders/screenshot.wgsl";
+
+const SCREENSHOT_NODE: &str = "screenshot";
+const SCREENSHOTS_FOLDER: &str = "screenshots";
+// Of the white flash over the screen when taking a screenshot, in seconds
+const FLASH_DURATION: f32 = 0.3;
+// sRGB, the colors of the main texture are linear
+const CAPTURE_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+// Images being encoded at most, the frames of a time-lapse would pile up in memory otherwise
+const MAX_SAVING: usize = 4;
+
+/// Saves the image of the [`MainCamera`] to `screenshots/bistro_YYYY-MM-DD_HH-MM-SS.png` with
+/// `F12`, in local time, behind a white flash fading out.
+///
+/// Bevy 0.9 can't read back the textures of a view. Once the post processing is done, a node of
+/// the 3d graph copies the image of the main camera to a texture that can be read back, then to a
+/// buffer read back a frame or two later. The UI isn't in the screenshots. The image can't be
+/// saved on the web.
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        let saving = SavingScreenshots::default();
+        app.add_event::<CaptureFrame>()
+            .insert_resource(saving.clone())
+            .add_system(screenshot_input)
+            .add_system(fade_screenshot_flash);
+
+        let render_app = match app.get_sub_app_mut(RenderApp) {
+            Ok(render_app) => render_app,
+            Err(_) => return,
+        };
+        render_app
+            .init_resource::<ScreenshotPipeline>()
+            .init_resource::<ExtractedCaptures>()
+            .init_resource::<PendingCaptures>()
+            .insert_resource(saving)
+            .add_system_to_stage(RenderStage::Extract, extract_captures)
+            .add_system_to_stage(RenderStage::Cleanup, save_captures);
+
+        let node = ScreenshotNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let draw_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+        draw_3d_graph.add_node(SCREENSHOT_NODE, node);
+        // After the last effect, before the image is upscaled to the window
+        draw_3d_graph
+            .add_node_edge(core_3d::graph::node::FXAA, SCREENSHOT_NODE)
+            .unwrap();
+        draw_3d_graph
+            .add_node_edge(
+                SCREENSHOT_NODE,
+                core_3d::graph::node::END_MAIN_PASS_POST_PROCESSING,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                draw_3d_graph.input_node().unwrap().id,
+                core_3d::graph::input::VIEW_ENTITY,
+                SCREENSHOT_NODE,
+                ScreenshotNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+/// Saves the image of the [`MainCamera`] of the frame to a PNG file, creating its folder when
+/// missing.
+pub struct CaptureFrame {
+    pub path: PathBuf,
+}
+
+/// Number of images being encoded and saved, shared with the render world.
+#[derive(Resource, Clone, Default)]
+pub struct SavingScreenshots(Arc<AtomicUsize>);
+
+impl SavingScreenshots {
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Component)]
+struct ScreenshotFlash(Timer);
+
+fn screenshot_input(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut captures: EventWriter<CaptureFrame>,
+) {
+    if !input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    if cfg!(target_arch = "wasm32") {
+        warn!("screenshots can't be saved on the web");
+        return;
+    }
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let path = Path::new(SCREENSHOTS_FOLDER).join(format!("bistro_{timestamp}.png"));
+    info!("screenshot: {}", path.display());
+    captures.send(CaptureFrame { path });
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: Color::WHITE.into(),
+            // Over the rest of the UI
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        ScreenshotFlash(Timer::from_seconds(FLASH_DURATION, TimerMode::Once)),
+    ));
+}
+
+fn fade_screenshot_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flashes: Query<(Entity, &mut ScreenshotFlash, &mut BackgroundColor)>,
+) {
+    for (entity, mut flash, mut color) in flashes.iter_mut() {
+        flash.0.tick(time.delta());
+        if flash.0.finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            color.0.set_a(flash.0.percent_left());
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct ExtractedCaptures {
+    main_view: Option<Entity>,
+    paths: Vec<PathBuf>,
+}
+
+fn extract_captures(
+    mut extracted: ResMut<ExtractedCaptures>,
+    mut captures: Extract<EventReader<CaptureFrame>>,
+    main_camera: Extract<Query<Entity, With<MainCamera>>>,
+) {
+    // Cameras keep their entity in the render world
+    extracted.main_view = main_camera.get_single().ok();
+    extracted.paths = captures
+        .iter()
+        .map(|capture| capture.path.clone())
+        .collect();
+}
+
+// An image copied by the node, read back once the frame is submitted
+struct PendingCapture {
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    // The rows of the buffer are aligned
+    padded_bytes_per_row: usize,
+    paths: Vec<PathBuf>,
+}
+
+// Filled by the node, which can't access the render world mutably
+#[derive(Resource, Default)]
+struct PendingCaptures(Mutex<Vec<PendingCapture>>);
+
+#[derive(Resource)]
+struct ScreenshotPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for ScreenshotPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("screenshot_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
+
+        let shader = world.resource::<AssetServer>().load(SCREENSHOT_SHADER_PATH);
+        let pipeline =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("screenshot_pipeline".into()),
+                    layout: Some(vec![layout.clone()]),
+                    vertex: fullscreen_shader_vertex_state(),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: Vec::new(),
+                        entry_point: "copy".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: CAPTURE_FORMAT,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                });
+        Self {
+            layout,
+            sampler,
+            pipeline,
+        }
+    }
+}
+
+// Copies the main texture of the view to a buffer, on the frames with a capture
+struct ScreenshotNode {
+    query: QueryState<(&'static ViewTarget, &'static ExtractedCamera), With<ExtractedView>>,
+}
+
+impl ScreenshotNode {
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for ScreenshotNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let extracted = world.resource::<ExtractedCaptures>();
+        if extracted.paths.is_empty() || extracted.main_view != Some(view_entity) {
+            return Ok(());
+        }
+        let (target, size) = match self.query.get_manual(world, view_entity) {
+            Ok((target, camera)) => match camera.physical_target_size {
+                Some(size) if size.x > 0 && size.y > 0 => (target, size),
+                _ => return Ok(()),
+            },
+            Err(_) => return Ok(()),
+        };
+        let pipeline = world.resource::<ScreenshotPipeline>();
+        let render_pipeline = match world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline.pipeline)
+        {
+            Some(render_pipeline) => render_pipeline,
+            None => {
+                warn!("screenshot skipped, its shader isn't ready yet");
+                return Ok(());
+            }
+        };
+
+        let extent = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+        // The main texture can only be sampled, it is drawn to a texture that can be copied
+        let texture = render_context
+            .render_device
+            .create_texture(&TextureDescriptor {
+                label: Some("screenshot_texture"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: CAPTURE_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            });
+        let texture_view = texture.create_view(&TextureViewDescriptor::default());
+        let bind_group = render_context
+            .render_device
+            .create_bind_group(&BindGroupDescriptor {
+                label: Some("screenshot_bind_group"),
+                layout: &pipeline.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(target.main_texture()),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&pipeline.sampler),
+                    },
+                ],
+            });
+        {
+            let mut render_pass =
+                render_context
+                    .command_encoder
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("screenshot_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &texture_view,
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Clear(Color::BLACK.into()),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+            render_pass.set_pipeline(render_pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(size.x as usize * 4);
+        let buffer = render_context
+            .render_device
+            .create_buffer(&BufferDescriptor {
+                label: Some("screenshot_buffer"),
+                size: (padded_bytes_per_row * size.y as usize) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+        render_context.command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row as u32),
+                    rows_per_image: None,
+                },
+            },
+            extent,
+        );
+        world
+            .resource::<PendingCaptures>()
+            .0
+            .lock()
+            .unwrap()
+            .push(PendingCapture {
+                buffer,
+                width: size.x,
+                height: size.y,
+                padded_bytes_per_row,
+                paths: extracted.paths.clone(),
+            });
+        Ok(())
+    }
+}
+
+// Reads back the buffers once they are mapped, the device being polled when the next frames are
+// submitted
+fn save_captures(
+    pending: Res<PendingCaptures>,
+    render_device: Res<RenderDevice>,
+    saving: Res<SavingScreenshots>,
+    // In a mutex, locals have to be Sync
+    mut mapping: Local<Vec<(PendingCapture, Mutex<mpsc::Receiver<bool>>)>>,
+) {
+    for capture in std::mem::take(&mut *pending.0.lock().unwrap()) {
+        let (sender, receiver) = mpsc::channel();
+        render_device.map_buffer(&capture.buffer.slice(..), MapMode::Read, move |result| {
+            let _ = sender.send(result.is_ok());
+        });
+        mapping.push((capture, Mutex::new(receiver)));
+    }
+    for (capture, mut receiver) in std::mem::take(&mut *mapping) {
+        match receiver.get_mut().unwrap().try_recv() {
+            Ok(true) => {}
+            Err(mpsc::TryRecvError::Empty) => {
+                mapping.push((capture, receiver));
+                continue;
+            }
+            _ => {
+                error!("screenshot couldn't be read back from the GPU");
+                continue;
+            }
+        }
+        let row_bytes = capture.width as usize * 4;
+        let data = capture
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(capture.padded_bytes_per_row)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect::<Vec<_>>();
+        capture.buffer.unmap();
+
+        while saving.count() >= MAX_SAVING {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        saving.0.fetch_add(1, Ordering::AcqRel);
+        let saving = saving.clone();
+        let (width, height, paths) = (capture.width, capture.height, capture.paths);
+        // Encoding the PNG takes longer than a frame
+        IoTaskPool::get()
+            .spawn(async move {
+                save_png(width, height, data, &paths);
+                saving.0.fetch_sub(1, Ordering::AcqRel);
+            })
+            .detach();
+    }
+}
+
+fn save_png(width: u32, height: u32, data: Vec<u8>, paths: &[PathBuf]) {
+    let image = Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        CAPTURE_FORMAT,
+    );
+    let image = match image.try_into_dynamic() {
+        Ok(image) => image,
+        Err(err) => {
+            error!("screenshot couldn't be converted: {err:?}");
+            return;
+        }
+    };
+    for path in paths {
+        if let Some(folder) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(folder) {
+                error!("{} couldn't be created: {err}", folder.display());
+                continue;
+            }
+        }
+        match image.save(path) {
+            Ok(()) => debug!("saved {}", path.display()),
+            Err(err) => error!("{} couldn't be saved: {err}", path.display()),
+        }
+    }
+}
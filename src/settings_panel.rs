@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext, EguiPlugin};
+
+use crate::{
+    camera_controller::{CameraController, MouseLookMode},
+    plugins::{
+        camera::MainCamera,
+        lights::{LightGroupColor, LightSettings, ShadowBiasSettings},
+        sky::DayNightCycleSettings,
+    },
+    simulation_clock::SimulationClock,
+};
+
+/// Panels to tune the [`CameraController`] and the lights while flying around, toggled with `P`.
+pub struct SettingsPanelPlugin;
+
+impl Plugin for SettingsPanelPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugin(EguiPlugin);
+        }
+        app.init_resource::<SettingsPanel>()
+            .add_system(toggle_settings_panel)
+            .add_system(camera_panel.after(toggle_settings_panel))
+            .add_system(lights_panel.after(toggle_settings_panel));
+    }
+}
+
+#[derive(Resource, Default)]
+struct SettingsPanel {
+    visible: bool,
+}
+
+fn toggle_settings_panel(input: Res<Input<KeyCode>>, mut panel: ResMut<SettingsPanel>) {
+    if input.just_pressed(KeyCode::P) {
+        panel.visible = !panel.visible;
+    }
+}
+
+fn camera_panel(
+    panel: Res<SettingsPanel>,
+    mut egui_context: ResMut<EguiContext>,
+    mut camera: Query<(&Transform, &mut CameraController), With<MainCamera>>,
+) {
+    if !panel.visible {
+        return;
+    }
+    let (transform, mut controller) = match camera.get_single_mut() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    egui::Window::new("Camera").show(egui_context.ctx_mut(), |ui| {
+        // Distances are in meters, the scale of the scenes once loaded
+        ui.add(egui::Slider::new(&mut controller.walk_speed, 0.5..=20.0).text("walk speed (m/s)"));
+        ui.add(egui::Slider::new(&mut controller.run_speed, 1.0..=60.0).text("run speed (m/s)"));
+        // Radians of rotation per pixel of mouse motion, multiplied by the frame time
+        ui.add(egui::Slider::new(&mut controller.sensitivity, 0.05..=2.0).text("sensitivity"));
+        // Fraction of the velocity lost each frame once no key is pressed, 1 stops immediately
+        ui.add(egui::Slider::new(&mut controller.friction, 0.0..=1.0).text("friction"));
+        ui.horizontal(|ui| {
+            ui.label("mouse look:");
+            ui.radio_value(
+                &mut controller.mouse_look_mode,
+                MouseLookMode::HoldToLook,
+                "hold click",
+            );
+            ui.radio_value(
+                &mut controller.mouse_look_mode,
+                MouseLookMode::ToggleLock,
+                "click to lock",
+            );
+        });
+
+        ui.separator();
+        let translation = transform.translation;
+        ui.label(format!(
+            "translation: {:.2}, {:.2}, {:.2}",
+            translation.x, translation.y, translation.z
+        ));
+        // Same order as the controller: no roll, yaw around Y then pitch around X
+        let (roll, yaw, pitch) = transform.rotation.to_euler(EulerRot::ZYX);
+        ui.label(format!(
+            "yaw: {:.1}° - pitch: {:.1}° - roll: {:.1}°",
+            yaw.to_degrees(),
+            pitch.to_degrees(),
+            roll.to_degrees()
+        ));
+    });
+}
+
+fn lights_panel(
+    panel: Res<SettingsPanel>,
+    mut egui_context: ResMut<EguiContext>,
+    mut light_settings: ResMut<LightSettings>,
+    mut shadow_bias: ResMut<ShadowBiasSettings>,
+    mut group_color: ResMut<LightGroupColor>,
+    mut clock: ResMut<SimulationClock>,
+    cycle: Res<DayNightCycleSettings>,
+) {
+    if !panel.visible {
+        return;
+    }
+
+    // Work on a copy so that the lights are only updated when a slider moves
+    let mut settings = light_settings.clone();
+    let mut changed = false;
+    egui::Window::new("Lights").show(egui_context.ctx_mut(), |ui| {
+        ui.label("Color temperature (K)");
+        // From a fire at 1800 K to daylight at 6500 K
+        for (temperature, group) in [
+            (&mut settings.ceiling_temperature, "ceiling"),
+            (&mut settings.wall_temperature, "wall"),
+            (&mut settings.lantern_temperature, "lantern"),
+            (&mut settings.streetlight_temperature, "streetlight"),
+        ] {
+            changed |= ui
+                .add(egui::Slider::new(temperature, 1800.0..=6500.0).text(group))
+                .changed();
+        }
+
+        ui.separator();
+        ui.label("Color, over the temperature");
+        let group_color = &mut *group_color;
+        for (color, group) in [
+            (&mut group_color.ceiling, "ceiling"),
+            (&mut group_color.wall, "wall"),
+            (&mut group_color.lantern, "lantern"),
+            (&mut group_color.streetlight, "streetlight"),
+        ] {
+            ui.horizontal(|ui| {
+                let mut rgb = [color.r(), color.g(), color.b()];
+                if ui.color_edit_button_rgb(&mut rgb).changed() {
+                    *color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+                }
+                ui.label(group);
+            });
+        }
+
+        ui.separator();
+        ui.label("Shadow bias");
+        // Bevy defaults to 0.02 and 0.6, the ranges keep them reachable
+        ui.add(
+            egui::Slider::new(&mut shadow_bias.depth_bias, 0.0..=0.1)
+                .step_by(0.001)
+                .text("depth"),
+        );
+        ui.add(
+            egui::Slider::new(&mut shadow_bias.normal_bias, 0.0..=1.0)
+                .step_by(0.001)
+                .text("normal"),
+        );
+
+        ui.separator();
+        ui.label("Day/night cycle");
+        // Changing the speed of the clock instead of the cycle duration keeps the sun in place, and
+        // also speeds up the real sun position
+        let mut day_length = cycle.cycle_duration_seconds / clock.speed;
+        if ui
+            .add(
+                egui::Slider::new(&mut day_length, 5.0..=1200.0)
+                    .logarithmic(true)
+                    .text("day length (s)"),
+            )
+            .changed()
+        {
+            clock.speed = cycle.cycle_duration_seconds / day_length;
+        }
+        ui.checkbox(&mut clock.paused, "paused");
+    });
+    if changed {
+        *light_settings = settings;
+    }
+}
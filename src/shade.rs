@@ -0,0 +1,151 @@
+use bevy::{prelude::*, render::primitives::Aabb, transform::TransformSystem};
+
+use crate::{mesh_utils::world_bounds, plugins::sky::SunLight};
+
+// Size of a tile of the shade map, in meters
+const SHADE_TILE_SIZE: f32 = 0.25;
+// Ground around the sun blockers covered by the shade map, in meters
+const SHADE_MAP_MARGIN: f32 = 4.0;
+// Tiles are sampled just above the ground, so that the ground itself doesn't block the sun
+const SAMPLE_HEIGHT: f32 = 0.01;
+
+/// Finds which tiles of the ground around the [`SunBlocker`] meshes are in their shade, in the
+/// [`ShadeMap`].
+///
+/// Each tile casts a ray towards the sun, and is shaded when the ray hits the bounds of a blocker,
+/// or when the sun is below the horizon. The map covers the ground under the blockers and a few
+/// meters around them, and follows the sun as it moves.
+pub struct ShadePlugin;
+
+impl Plugin for ShadePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SunBlocker>()
+            .init_resource::<ShadeMap>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                shade_detection.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// A mesh shading the ground from the sun, like the awning over the patio.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct SunBlocker;
+
+/// Tiles of the ground in the shade of the [`SunBlocker`] meshes, on a grid along X and Z.
+#[derive(Resource, Default)]
+pub struct ShadeMap {
+    // Corner of the first tile, on the X and Z axes
+    pub min: Vec2,
+    pub tile_size: f32,
+    pub width: usize,
+    pub height: usize,
+    // By row along Z, with `width` tiles along X in each row
+    pub shaded: Vec<bool>,
+}
+
+impl ShadeMap {
+    /// Whether the tile under `position` is shaded. Positions outside of the map are not.
+    pub fn is_shaded(&self, position: Vec3) -> bool {
+        if self.tile_size <= 0.0 {
+            return false;
+        }
+        let tile = (Vec2::new(position.x, position.z) - self.min) / self.tile_size;
+        if tile.x < 0.0 || tile.y < 0.0 {
+            return false;
+        }
+        let (x, z) = (tile.x as usize, tile.y as usize);
+        x < self.width && z < self.height && self.shaded[z * self.width + x]
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn shade_detection(
+    mut shade_map: ResMut<ShadeMap>,
+    sun: Query<(&GlobalTransform, &DirectionalLight), With<SunLight>>,
+    changed_sun: Query<(), (With<SunLight>, Changed<GlobalTransform>)>,
+    blockers: Query<(&GlobalTransform, &Aabb), With<SunBlocker>>,
+    changed_blockers: Query<
+        (),
+        (
+            With<SunBlocker>,
+            Or<(Changed<GlobalTransform>, Changed<Aabb>, Added<SunBlocker>)>,
+        ),
+    >,
+    removed_blockers: RemovedComponents<SunBlocker>,
+) {
+    if changed_sun.is_empty()
+        && changed_blockers.is_empty()
+        && removed_blockers.iter().next().is_none()
+    {
+        return;
+    }
+    let (sun_transform, sun_light) = match sun.get_single() {
+        Ok(sun) => sun,
+        Err(_) => return,
+    };
+
+    let boxes = blockers
+        .iter()
+        .map(|(transform, aabb)| world_bounds(aabb, transform, 0.0))
+        .collect::<Vec<_>>();
+    if boxes.is_empty() {
+        *shade_map = ShadeMap::default();
+        return;
+    }
+    let (min, max) = boxes.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), (box_min, box_max)| (min.min(*box_min), max.max(*box_max)),
+    );
+    let min = Vec2::new(min.x, min.z) - SHADE_MAP_MARGIN;
+    let max = Vec2::new(max.x, max.z) + SHADE_MAP_MARGIN;
+    let size = ((max - min) / SHADE_TILE_SIZE).ceil();
+    let (width, height) = (size.x as usize, size.y as usize);
+
+    // The light shines along its forward axis
+    let to_sun = sun_transform.back();
+    let night = to_sun.y <= 0.0 || sun_light.illuminance <= 0.0;
+    let mut shaded = Vec::with_capacity(width * height);
+    for z in 0..height {
+        for x in 0..width {
+            let tile = min + (Vec2::new(x as f32, z as f32) + 0.5) * SHADE_TILE_SIZE;
+            let origin = Vec3::new(tile.x, SAMPLE_HEIGHT, tile.y);
+            shaded.push(
+                night
+                    || boxes
+                        .iter()
+                        .any(|(box_min, box_max)| ray_hits_box(origin, to_sun, *box_min, *box_max)),
+            );
+        }
+    }
+    *shade_map = ShadeMap {
+        min,
+        tile_size: SHADE_TILE_SIZE,
+        width,
+        height,
+        shaded,
+    };
+}
+
+// Whether the half line from `origin` along `direction` goes through the box
+fn ray_hits_box(origin: Vec3, direction: Vec3, min: Vec3, max: Vec3) -> bool {
+    let mut t_enter = 0.0_f32;
+    let mut t_exit = f32::MAX;
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return false;
+            }
+            continue;
+        }
+        let t_min = (min[axis] - origin[axis]) / direction[axis];
+        let t_max = (max[axis] - origin[axis]) / direction[axis];
+        t_enter = t_enter.max(t_min.min(t_max));
+        t_exit = t_exit.min(t_min.max(t_max));
+        if t_enter > t_exit {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,322 @@
+use bevy::{
+    core_pipeline::core_3d,
+    pbr::{
+        draw_3d_graph, GlobalLightMeta, LightEntity, RenderLightSystems, Shadow, ShadowView,
+        ViewLightEntities, ViewShadowBindings,
+    },
+    prelude::*,
+    render::{
+        primitives::Aabb,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::{DrawFunctions, RenderPhase, TrackedRenderPass},
+        render_resource::{
+            LoadOp, Operations, PipelineCache, RenderPassDepthStencilAttachment,
+            RenderPassDescriptor, TextureId,
+        },
+        renderer::RenderContext,
+        Extract, RenderApp, RenderStage,
+    },
+    transform::TransformSystem,
+    utils::{HashMap, HashSet},
+};
+
+use crate::plugins::lights::{LightGroup, LightGroupKind};
+
+// A face is kept once it has been rendered this many frames with all its pipelines ready
+const BAKE_FRAMES: u32 = 2;
+
+/// Renders the shadow maps of the point lights with a [`StaticShadowCache`] once, and keeps them
+/// instead of rendering them again every frame.
+///
+/// The ceiling and wall lights never move, and get a cache. It is invalidated when the light moves
+/// or changes range, and when a mesh in its range is added, removed, moved or hidden.
+///
+/// Bevy 0.9 renders every shadow map each frame, so the shadow pass node of the 3d graph is
+/// replaced by one that skips the cached faces. The faces are cached per layer of the shadow map
+/// texture, and rendered again when the texture or the layer of the light change.
+pub struct ShadowCachePlugin;
+
+impl Plugin for ShadowCachePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(add_static_shadow_caches)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                invalidate_static_shadow_caches.after(TransformSystem::TransformPropagate),
+            );
+
+        let render_app = match app.get_sub_app_mut(RenderApp) {
+            Ok(render_app) => render_app,
+            Err(_) => return,
+        };
+        render_app
+            .init_resource::<StaticShadowCaches>()
+            .add_system_to_stage(RenderStage::Extract, extract_static_shadow_caches)
+            .add_system_to_stage(
+                RenderStage::Queue,
+                skip_cached_shadow_views.after(RenderLightSystems::QueueShadows),
+            );
+
+        let node = CachedShadowPassNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let draw_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+        // Same node name and edges as the shadow pass of `PbrPlugin`
+        draw_3d_graph
+            .remove_node(draw_3d_graph::node::SHADOW_PASS)
+            .unwrap();
+        draw_3d_graph.add_node(draw_3d_graph::node::SHADOW_PASS, node);
+        draw_3d_graph
+            .add_node_edge(
+                draw_3d_graph::node::SHADOW_PASS,
+                core_3d::graph::node::MAIN_PASS,
+            )
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                draw_3d_graph.input_node().unwrap().id,
+                core_3d::graph::input::VIEW_ENTITY,
+                draw_3d_graph::node::SHADOW_PASS,
+                CachedShadowPassNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+/// Keeps the shadow maps of this point light once rendered, until [`Self::invalidate`] is called.
+#[derive(Component, Default)]
+pub struct StaticShadowCache {
+    // Changed each time the shadow maps must be rendered again
+    version: u32,
+    // Range the shadow maps were rendered with, meshes further away are not in them
+    range: f32,
+}
+
+impl StaticShadowCache {
+    pub fn invalidate(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn add_static_shadow_caches(
+    mut commands: Commands,
+    lights: Query<(Entity, &LightGroup), (With<PointLight>, Without<StaticShadowCache>)>,
+) {
+    for (entity, group) in lights.iter() {
+        if matches!(group.0, LightGroupKind::Ceiling | LightGroupKind::Wall) {
+            commands.entity(entity).insert(StaticShadowCache::default());
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn invalidate_static_shadow_caches(
+    mut lights: Query<(
+        &mut StaticShadowCache,
+        &PointLight,
+        &GlobalTransform,
+        ChangeTrackers<GlobalTransform>,
+    )>,
+    changed_meshes: Query<
+        (&GlobalTransform, Option<&Aabb>),
+        (
+            With<Handle<Mesh>>,
+            Or<(
+                Changed<Handle<Mesh>>,
+                Changed<GlobalTransform>,
+                Changed<Visibility>,
+            )>,
+        ),
+    >,
+    removed_meshes: RemovedComponents<Handle<Mesh>>,
+) {
+    // Removed meshes don't have a position anymore, all the caches could be showing them
+    let removed = removed_meshes.iter().next().is_some();
+    for (mut cache, light, transform, transform_tracker) in lights.iter_mut() {
+        let position = transform.translation();
+        let mesh_changed = removed
+            || changed_meshes.iter().any(|(mesh_transform, aabb)| {
+                let (center, radius) = aabb.map_or((Vec3::ZERO, 0.0), |aabb| {
+                    (Vec3::from(aabb.center), aabb.half_extents.length())
+                });
+                let scale = mesh_transform
+                    .to_scale_rotation_translation()
+                    .0
+                    .max_element();
+                let distance = mesh_transform.transform_point(center).distance(position);
+                distance - radius * scale < light.range
+            });
+        if mesh_changed || transform_tracker.is_changed() || cache.range != light.range {
+            cache.range = light.range;
+            cache.invalidate();
+        }
+    }
+}
+
+// A face of a point light rendered in a layer of a shadow map texture
+struct BakedShadow {
+    light: Entity,
+    version: u32,
+    frames: u32,
+}
+
+#[derive(Resource, Default)]
+struct StaticShadowCaches {
+    // Version of the cache of each light, extracted each frame
+    versions: HashMap<Entity, u32>,
+    // By shadow map texture and layer
+    baked: HashMap<(TextureId, usize), BakedShadow>,
+    // Shadow views that are not rendered this frame, the texture already has their faces
+    skipped: HashSet<Entity>,
+}
+
+fn extract_static_shadow_caches(
+    mut caches: ResMut<StaticShadowCaches>,
+    lights: Extract<Query<(Entity, &StaticShadowCache)>>,
+) {
+    caches.versions.clear();
+    for (entity, cache) in lights.iter() {
+        caches.versions.insert(entity, cache.version);
+    }
+}
+
+fn skip_cached_shadow_views(
+    mut caches: ResMut<StaticShadowCaches>,
+    global_light_meta: Res<GlobalLightMeta>,
+    pipeline_cache: Res<PipelineCache>,
+    views: Query<(&ViewLightEntities, &ViewShadowBindings)>,
+    view_lights: Query<(&LightEntity, &RenderPhase<Shadow>)>,
+) {
+    let caches = &mut *caches;
+    caches.skipped.clear();
+    let mut used = HashSet::new();
+    for (view_light_entities, bindings) in views.iter() {
+        // Each view gets its own shadow map texture
+        let texture = bindings.point_light_depth_texture.id();
+        for view_light in view_light_entities.lights.iter().copied() {
+            let (light, face_index, phase) = match view_lights.get(view_light) {
+                Ok((
+                    LightEntity::Point {
+                        light_entity,
+                        face_index,
+                    },
+                    phase,
+                )) => (*light_entity, *face_index, phase),
+                _ => continue,
+            };
+            let (version, index) = match (
+                caches.versions.get(&light),
+                global_light_meta.entity_to_index.get(&light),
+            ) {
+                (Some(version), Some(index)) => (*version, *index),
+                _ => continue,
+            };
+            let key = (texture, index * 6 + face_index);
+            used.insert(key);
+            // Meshes whose pipeline is still compiling are not rendered
+            let ready = phase
+                .items
+                .iter()
+                .all(|item| pipeline_cache.get_render_pipeline(item.pipeline).is_some());
+            match caches.baked.get_mut(&key) {
+                Some(baked) if baked.light == light && baked.version == version => {
+                    if baked.frames >= BAKE_FRAMES {
+                        caches.skipped.insert(view_light);
+                    } else if ready {
+                        baked.frames += 1;
+                    }
+                }
+                _ => {
+                    caches.baked.insert(
+                        key,
+                        BakedShadow {
+                            light,
+                            version,
+                            frames: u32::from(ready),
+                        },
+                    );
+                }
+            }
+        }
+    }
+    // Textures not used this frame can be given to another view, or dropped
+    caches.baked.retain(|key, _| used.contains(key));
+}
+
+// Like the `ShadowPassNode` of Bevy, without the shadow views skipped by `StaticShadowCaches`
+struct CachedShadowPassNode {
+    main_view_query: QueryState<&'static ViewLightEntities>,
+    view_light_query: QueryState<(&'static ShadowView, &'static RenderPhase<Shadow>)>,
+}
+
+impl CachedShadowPassNode {
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            main_view_query: QueryState::new(world),
+            view_light_query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for CachedShadowPassNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.main_view_query.update_archetypes(world);
+        self.view_light_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let view_lights = match self.main_view_query.get_manual(world, view_entity) {
+            Ok(view_lights) => view_lights,
+            Err(_) => return Ok(()),
+        };
+        let skipped = &world.resource::<StaticShadowCaches>().skipped;
+        for view_light_entity in view_lights.lights.iter().copied() {
+            if skipped.contains(&view_light_entity) {
+                continue;
+            }
+            let (view_light, shadow_phase) =
+                match self.view_light_query.get_manual(world, view_light_entity) {
+                    Ok(view_light) => view_light,
+                    Err(_) => continue,
+                };
+            if shadow_phase.items.is_empty() {
+                continue;
+            }
+
+            let pass_descriptor = RenderPassDescriptor {
+                label: Some(&view_light.pass_name),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &view_light.depth_texture_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(0.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            };
+            let draw_functions = world.resource::<DrawFunctions<Shadow>>();
+            let render_pass = render_context
+                .command_encoder
+                .begin_render_pass(&pass_descriptor);
+            let mut draw_functions = draw_functions.write();
+            let mut tracked_pass = TrackedRenderPass::new(render_pass);
+            for item in &shadow_phase.items {
+                let draw_function = draw_functions.get_mut(item.draw_function).unwrap();
+                draw_function.draw(world, &mut tracked_pass, view_light_entity, item);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,424 @@
+use bevy::{
+    core_pipeline::{core_3d, fullscreen_vertex_shader::fullscreen_shader_vertex_state},
+    pbr::{draw_3d_graph, LightEntity, ShadowView, ViewLightEntities},
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_phase::TrackedRenderPass,
+        render_resource::{
+            BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry, BindingResource, BindingType, CachedRenderPipelineId,
+            ColorTargetState, ColorWrites, Extent3d, FragmentState, LoadOp, MultisampleState,
+            Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+            RenderPassDescriptor, RenderPipelineDescriptor, ShaderStages, TextureDescriptor,
+            TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+            TextureViewDimension,
+        },
+        renderer::{RenderContext, RenderDevice},
+        Extract, RenderApp, RenderStage,
+    },
+    utils::HashMap,
+};
+
+use crate::{platform, plugins::camera::MainCamera};
+
+pub const SHADOW_DEBUG_SHADER_PATH: &str = "shaders/shadow_debug.wgsl";
+
+// Size of each shadow map on screen, in pixels
+const SHADOW_DEBUG_SIZE: u32 = 256;
+const SHADOW_DEBUG_FORMAT: TextureFormat = TextureFormat::Bgra8UnormSrgb;
+const SHADOW_DEBUG_NODE: &str = "shadow_debug";
+
+/// Shows the shadow maps of the directional lights of the [`MainCamera`] in the top left corner,
+/// toggled with `Ctrl+D`, to check the shadow bias and the area covered by the shadows.
+///
+/// Bevy 0.9 has no shadow cascades, each directional light with shadows has a single shadow map,
+/// labeled with its index in the shadow map texture and the distance of its far plane. The shadow
+/// maps only exist in the render world, a node of the 3d graph draws them into the images shown by
+/// the UI once they are rendered.
+pub struct ShadowDebugPlugin;
+
+impl Plugin for ShadowDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowDebugOverlay>()
+            .add_system(toggle_shadow_debug_overlay)
+            .add_system(update_shadow_debug_overlay.after(toggle_shadow_debug_overlay));
+
+        let render_app = match app.get_sub_app_mut(RenderApp) {
+            Ok(render_app) => render_app,
+            Err(_) => return,
+        };
+        render_app
+            .init_resource::<ShadowDebugPipeline>()
+            .init_resource::<ShadowDebugTargets>()
+            .add_system_to_stage(RenderStage::Extract, extract_shadow_debug_targets);
+
+        let node = ShadowDebugNode::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let draw_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+        draw_3d_graph.add_node(SHADOW_DEBUG_NODE, node);
+        // Once the shadow maps are rendered, and before the UI showing them
+        draw_3d_graph
+            .add_node_edge(draw_3d_graph::node::SHADOW_PASS, SHADOW_DEBUG_NODE)
+            .unwrap();
+        draw_3d_graph
+            .add_node_edge(SHADOW_DEBUG_NODE, core_3d::graph::node::MAIN_PASS)
+            .unwrap();
+        draw_3d_graph
+            .add_slot_edge(
+                draw_3d_graph.input_node().unwrap().id,
+                core_3d::graph::input::VIEW_ENTITY,
+                SHADOW_DEBUG_NODE,
+                ShadowDebugNode::IN_VIEW,
+            )
+            .unwrap();
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ShadowDebugOverlay {
+    pub enabled: bool,
+}
+
+#[derive(Component)]
+struct ShadowDebugRoot;
+
+// The image showing the shadow map of a light
+#[derive(Component)]
+struct ShadowDebugImage {
+    light: Entity,
+}
+
+#[derive(Component)]
+struct ShadowDebugLabel {
+    light: Entity,
+    index: usize,
+}
+
+fn toggle_shadow_debug_overlay(
+    input: Res<Input<KeyCode>>,
+    mut overlay: ResMut<ShadowDebugOverlay>,
+) {
+    if input.just_pressed(KeyCode::D) && input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        overlay.enabled = !overlay.enabled;
+        info!("shadow map overlay: {}", overlay.enabled);
+    }
+}
+
+fn shadow_debug_image() -> Image {
+    let size = Extent3d {
+        width: SHADOW_DEBUG_SIZE,
+        height: SHADOW_DEBUG_SIZE,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("shadow_debug"),
+            size,
+            dimension: TextureDimension::D2,
+            format: SHADOW_DEBUG_FORMAT,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+// Spawn a tile for each shadow map when the lights with shadows change, and keep the labels up to
+// date with the shadow projections
+#[allow(clippy::too_many_arguments)]
+fn update_shadow_debug_overlay(
+    mut commands: Commands,
+    overlay: Res<ShadowDebugOverlay>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    lights: Query<(
+        Entity,
+        &DirectionalLight,
+        &ComputedVisibility,
+        Option<&Name>,
+    )>,
+    roots: Query<Entity, With<ShadowDebugRoot>>,
+    mut labels: Query<(&ShadowDebugLabel, &mut Text)>,
+    mut shown: Local<Vec<Entity>>,
+) {
+    let mut shadowed = Vec::new();
+    if overlay.enabled {
+        shadowed.extend(
+            lights
+                .iter()
+                .filter(|(_, light, visibility, _)| {
+                    light.shadows_enabled && visibility.is_visible()
+                })
+                .map(|(entity, ..)| entity),
+        );
+        // Bevy gives the layers of the shadow map texture to the lights in this order
+        shadowed.sort();
+    }
+
+    if *shown != shadowed {
+        // The images are freed with the handles of the despawned entities
+        for entity in roots.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        if !shadowed.is_empty() {
+            let font = asset_server.load("fonts/DejaVuSansMono.ttf");
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Px(5.0),
+                                left: Val::Px(5.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    ShadowDebugRoot,
+                ))
+                .with_children(|root| {
+                    for (index, light) in shadowed.iter().enumerate() {
+                        root.spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                margin: UiRect::right(Val::Px(5.0)),
+                                padding: UiRect::all(Val::Px(5.0)),
+                                ..default()
+                            },
+                            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                            ..default()
+                        })
+                        .with_children(|tile| {
+                            tile.spawn((
+                                TextBundle::from_section(
+                                    "",
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 14.0,
+                                        color: Color::WHITE,
+                                    },
+                                ),
+                                ShadowDebugLabel {
+                                    light: *light,
+                                    index,
+                                },
+                            ));
+                            tile.spawn((
+                                ImageBundle {
+                                    style: Style {
+                                        size: Size::new(
+                                            Val::Px(SHADOW_DEBUG_SIZE as f32),
+                                            Val::Px(SHADOW_DEBUG_SIZE as f32),
+                                        ),
+                                        ..default()
+                                    },
+                                    image: UiImage(images.add(shadow_debug_image())),
+                                    ..default()
+                                },
+                                ShadowDebugImage { light: *light },
+                            ));
+                        });
+                    }
+                });
+        }
+        *shown = shadowed;
+    }
+
+    for (label, mut text) in labels.iter_mut() {
+        if let Ok((_, light, _, name)) = lights.get(label.light) {
+            text.sections[0].value = format!(
+                "{} {}: far {:.0}m",
+                name.map_or("light", |name| name.as_str()),
+                label.index,
+                light.shadow_projection.far
+            );
+        }
+    }
+}
+
+// The images to draw the shadow maps in, and the view whose shadow maps are shown
+#[derive(Resource, Default)]
+struct ShadowDebugTargets {
+    main_view: Option<Entity>,
+    // By directional light
+    images: HashMap<Entity, Handle<Image>>,
+}
+
+fn extract_shadow_debug_targets(
+    mut targets: ResMut<ShadowDebugTargets>,
+    main_camera: Extract<Query<Entity, With<MainCamera>>>,
+    images: Extract<Query<(&ShadowDebugImage, &UiImage)>>,
+) {
+    // Cameras keep their entity in the render world
+    targets.main_view = main_camera.get_single().ok();
+    targets.images.clear();
+    for (image, ui_image) in images.iter() {
+        targets.images.insert(image.light, ui_image.0.clone());
+    }
+}
+
+#[derive(Resource)]
+struct ShadowDebugPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for ShadowDebugPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("shadow_debug_layout"),
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    }],
+                });
+        let shader = world
+            .resource::<AssetServer>()
+            .load(SHADOW_DEBUG_SHADER_PATH);
+        let pipeline =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("shadow_debug_pipeline".into()),
+                    layout: Some(vec![layout.clone()]),
+                    vertex: fullscreen_shader_vertex_state(),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: Vec::new(),
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: SHADOW_DEBUG_FORMAT,
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                });
+        Self { layout, pipeline }
+    }
+}
+
+// Draws the shadow map of each directional light of the main view in its image
+struct ShadowDebugNode {
+    main_view_query: QueryState<&'static ViewLightEntities>,
+    view_light_query: QueryState<(&'static LightEntity, &'static ShadowView)>,
+}
+
+impl ShadowDebugNode {
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            main_view_query: QueryState::new(world),
+            view_light_query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for ShadowDebugNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.main_view_query.update_archetypes(world);
+        self.view_light_query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let targets = world.resource::<ShadowDebugTargets>();
+        // Every camera has its own shadow maps, only those of the main camera are shown
+        if targets.images.is_empty() || targets.main_view != Some(view_entity) {
+            return Ok(());
+        }
+        let view_lights = match self.main_view_query.get_manual(world, view_entity) {
+            Ok(view_lights) => view_lights,
+            Err(_) => return Ok(()),
+        };
+        let pipeline = world.resource::<ShadowDebugPipeline>();
+        let render_pipeline = match world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline.pipeline)
+        {
+            Some(render_pipeline) => render_pipeline,
+            None => return Ok(()),
+        };
+        let gpu_images = world.resource::<RenderAssets<Image>>();
+
+        for view_light_entity in view_lights.lights.iter().copied() {
+            let (light, shadow_view) =
+                match self.view_light_query.get_manual(world, view_light_entity) {
+                    Ok((LightEntity::Directional { light_entity }, shadow_view)) => {
+                        (*light_entity, shadow_view)
+                    }
+                    _ => continue,
+                };
+            let target = match targets
+                .images
+                .get(&light)
+                .and_then(|image| gpu_images.get(image))
+            {
+                Some(target) => target,
+                None => continue,
+            };
+
+            // A view of the layer of the light in the shadow map texture
+            let bind_group = render_context
+                .render_device
+                .create_bind_group(&BindGroupDescriptor {
+                    label: Some("shadow_debug_bind_group"),
+                    layout: &pipeline.layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&shadow_view.depth_texture_view),
+                    }],
+                });
+            let pass_descriptor = RenderPassDescriptor {
+                label: Some("shadow_debug_pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &target.texture_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK.into()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            };
+            let render_pass = render_context
+                .command_encoder
+                .begin_render_pass(&pass_descriptor);
+            let mut tracked_pass = TrackedRenderPass::new(render_pass);
+            tracked_pass.set_render_pipeline(render_pipeline);
+            tracked_pass.set_bind_group(0, &bind_group, &[]);
+            tracked_pass.draw(0..3, 0..1);
+        }
+        Ok(())
+    }
+}
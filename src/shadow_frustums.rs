@@ -0,0 +1,192 @@
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{render_resource::PrimitiveTopology, view::RenderLayers},
+};
+
+use crate::{plugins::camera::DEBUG_LAYER, shadow_debug::ShadowDebugOverlay};
+
+/// Outlines the volumes the lights with shadows cast them in while the shadow maps are shown with
+/// `Ctrl+D`, in the color of their light, to find the lights given a too large or too small volume
+/// for their bias.
+///
+/// The six shadow maps of a point light are drawn as a cube around it, out to its range, with
+/// the edges of each face going back to the light. A directional light has a single shadow map in
+/// Bevy 0.9, drawn as the box of its orthographic projection. Bevy 0.9 has no gizmos, the outlines
+/// are line meshes on the [`DEBUG_LAYER`], only seen by the main camera.
+pub struct ShadowFrustumsPlugin;
+
+impl Plugin for ShadowFrustumsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_shadow_frustums)
+            .add_system(spawn_shadow_frustums)
+            .add_system(update_shadow_frustums.after(spawn_shadow_frustums));
+    }
+}
+
+// The outline of the shadow volume of a light
+#[derive(Component)]
+struct ShadowFrustum {
+    light: Entity,
+}
+
+#[derive(Resource)]
+struct ShadowFrustumMeshes {
+    // Edges of a cube from -1 to 1, and lines from its center to its corners
+    point: Handle<Mesh>,
+    // Edges of a cube from -1 to 1
+    directional: Handle<Mesh>,
+}
+
+// Corners of a cube from -1 to 1, each bit of the index gives the sign of an axis
+fn cube_corner(index: usize) -> Vec3 {
+    let sign = |bit: usize| if index & bit == 0 { -1.0 } else { 1.0 };
+    Vec3::new(sign(1), sign(2), sign(4))
+}
+
+fn line_mesh(lines: &[(Vec3, Vec3)]) -> Mesh {
+    let positions = lines
+        .iter()
+        .flat_map(|(start, end)| [start.to_array(), end.to_array()])
+        .collect::<Vec<_>>();
+    // Unused by the unlit material, but needed by its pipeline
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+fn setup_shadow_frustums(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    // Corners differing by a single axis share an edge
+    let edges = (0..8)
+        .flat_map(|corner| {
+            [1, 2, 4]
+                .into_iter()
+                .filter(move |bit| corner & bit == 0)
+                .map(move |bit| (cube_corner(corner), cube_corner(corner | bit)))
+        })
+        .collect::<Vec<_>>();
+    let mut point = edges.clone();
+    point.extend((0..8).map(|corner| (Vec3::ZERO, cube_corner(corner))));
+    commands.insert_resource(ShadowFrustumMeshes {
+        point: meshes.add(line_mesh(&point)),
+        directional: meshes.add(line_mesh(&edges)),
+    });
+}
+
+#[allow(clippy::type_complexity)]
+fn spawn_shadow_frustums(
+    mut commands: Commands,
+    frustum_meshes: Res<ShadowFrustumMeshes>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    lights: Query<
+        (
+            Entity,
+            Option<&PointLight>,
+            Option<&DirectionalLight>,
+            Option<&Name>,
+        ),
+        Or<(Added<PointLight>, Added<DirectionalLight>)>,
+    >,
+) {
+    for (entity, point, directional, name) in lights.iter() {
+        let (mesh, color) = match (point, directional) {
+            (Some(point), _) => (frustum_meshes.point.clone(), point.color),
+            (None, Some(directional)) => (frustum_meshes.directional.clone(), directional.color),
+            (None, None) => continue,
+        };
+        let name = name.map_or("light", |name| name.as_str());
+        commands.spawn((
+            PbrBundle {
+                mesh,
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            NotShadowCaster,
+            NotShadowReceiver,
+            RenderLayers::layer(DEBUG_LAYER),
+            Name::new(format!("Shadow frustum of {name}")),
+            ShadowFrustum { light: entity },
+        ));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_shadow_frustums(
+    mut commands: Commands,
+    overlay: Res<ShadowDebugOverlay>,
+    lights: Query<(
+        &GlobalTransform,
+        &ComputedVisibility,
+        Option<&PointLight>,
+        Option<&DirectionalLight>,
+    )>,
+    mut frustums: Query<(
+        Entity,
+        &ShadowFrustum,
+        &Handle<StandardMaterial>,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, frustum, material, mut transform, mut visibility) in frustums.iter_mut() {
+        let (light_transform, light_visibility, point, directional) =
+            match lights.get(frustum.light) {
+                Ok(light) => light,
+                Err(_) => {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+            };
+        // Only the lights rendering shadow maps, point lights that are off don't
+        let shadows = match (point, directional) {
+            (Some(point), _) => point.shadows_enabled && point.intensity > 0.0 && point.range > 0.0,
+            (None, Some(directional)) => directional.shadows_enabled,
+            (None, None) => false,
+        };
+        let shown = overlay.enabled && shadows && light_visibility.is_visible();
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+
+        let color = match (point, directional) {
+            (Some(point), _) => {
+                *transform = Transform::from_translation(light_transform.translation())
+                    .with_scale(Vec3::splat(point.range));
+                point.color
+            }
+            (None, Some(directional)) => {
+                // The projection looks down the -Z axis of the light, from near to far
+                let projection = &directional.shadow_projection;
+                let min = Vec3::new(projection.left, projection.bottom, -projection.far);
+                let max = Vec3::new(projection.right, projection.top, -projection.near);
+                let volume = Transform::from_translation((min + max) / 2.0)
+                    .with_scale(((max - min) / 2.0).abs());
+                *transform = light_transform.mul_transform(volume).compute_transform();
+                directional.color
+            }
+            (None, None) => continue,
+        };
+        // Follows the color temperature and the day/night cycle
+        if materials
+            .get(material)
+            .is_some_and(|material| material.base_color != color)
+        {
+            if let Some(material) = materials.get_mut(material) {
+                material.base_color = color;
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use bevy::{pbr::DirectionalLightShadowMap, prelude::*};
+
+// Number of frames used to compute the average frame time
+const FRAME_TIME_HISTORY: usize = 10;
+
+/// Lowers the directional shadow map resolution when frames take too long, and raises it back
+/// when there is headroom, up to the size the app started with.
+pub struct ShadowQualityPlugin;
+
+impl Plugin for ShadowQualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadowQualityController>()
+            .add_system(scale_shadow_quality);
+    }
+}
+
+#[derive(Resource)]
+pub struct ShadowQualityController {
+    pub target_frame_ms: f32,
+    pub min_size: usize,
+    pub max_size: usize,
+    frame_times: VecDeque<f32>,
+    // Consecutive frames with an average above the target, or well below it
+    frames_over: u32,
+    frames_under: u32,
+}
+
+impl FromWorld for ShadowQualityController {
+    fn from_world(world: &mut World) -> Self {
+        let max_size = world
+            .get_resource::<DirectionalLightShadowMap>()
+            .map_or(2048, |shadow_map| shadow_map.size);
+        Self {
+            target_frame_ms: 1000.0 / 60.0,
+            min_size: 512.min(max_size),
+            max_size,
+            frame_times: VecDeque::with_capacity(FRAME_TIME_HISTORY),
+            frames_over: 0,
+            frames_under: 0,
+        }
+    }
+}
+
+fn scale_shadow_quality(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut controller: ResMut<ShadowQualityController>,
+    shadow_map: Res<DirectionalLightShadowMap>,
+) {
+    controller
+        .frame_times
+        .push_back(time.delta_seconds() * 1000.0);
+    if controller.frame_times.len() > FRAME_TIME_HISTORY {
+        controller.frame_times.pop_front();
+    }
+    let average = controller.frame_times.iter().sum::<f32>() / controller.frame_times.len() as f32;
+
+    if average > controller.target_frame_ms {
+        controller.frames_over += 1;
+        controller.frames_under = 0;
+    } else if average < controller.target_frame_ms * 0.7 {
+        controller.frames_under += 1;
+        controller.frames_over = 0;
+    } else {
+        controller.frames_over = 0;
+        controller.frames_under = 0;
+    }
+
+    let size = if controller.frames_over >= 3 {
+        (shadow_map.size / 2).max(controller.min_size)
+    } else if controller.frames_under >= 10 {
+        (shadow_map.size * 2).min(controller.max_size)
+    } else {
+        return;
+    };
+    controller.frames_over = 0;
+    controller.frames_under = 0;
+    if size != shadow_map.size {
+        info!("directional shadow map size: {size}");
+        commands.insert_resource(DirectionalLightShadowMap { size });
+        // Measure again with the new size before changing it further
+        controller.frame_times.clear();
+    }
+}
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use crate::cli;
+
+/// Advances the [`SimulationClock`], paused with `T` and slowed down or sped up with `[` and `]`.
+///
+/// With a [`SimulationPacer`], the simulation only ticks at its rate while the frames are still
+/// rendered as fast as possible. On the other frames [`ShouldSkipSimulation`] is set, and the
+/// systems of the simulated world return early.
+pub struct SimulationClockPlugin;
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>()
+            .insert_resource(SimulationPacer::from_args())
+            .init_resource::<ShouldSkipSimulation>()
+            .add_system_to_stage(CoreStage::First, simulation_pacing)
+            .add_system(simulation_clock_input.before(SimulationClockSystem))
+            .add_system(tick_simulation_clock.label(SimulationClockSystem));
+    }
+}
+
+/// Rate of the simulation ticks, from `--simulation-hz`.
+#[derive(Resource)]
+pub struct SimulationPacer {
+    // Ticks per second of real time, every frame when zero
+    pub target_hz: f32,
+}
+
+impl Default for SimulationPacer {
+    fn default() -> Self {
+        Self { target_hz: 0.0 }
+    }
+}
+
+impl SimulationPacer {
+    fn from_args() -> Self {
+        match cli::value("--simulation-hz") {
+            Some(value) => match value.parse::<f32>() {
+                Ok(target_hz) if target_hz > 0.0 => Self { target_hz },
+                _ => {
+                    eprintln!("invalid --simulation-hz: {value:?}, expected a positive number");
+                    std::process::exit(1);
+                }
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Set on the frames between two simulation ticks.
+#[derive(Resource, Default)]
+pub struct ShouldSkipSimulation(pub bool);
+
+// At the start of the frame, tick when a period of real time has elapsed since the last tick
+fn simulation_pacing(
+    time: Res<Time>,
+    pacer: Res<SimulationPacer>,
+    mut since_tick: Local<f32>,
+    mut skip: ResMut<ShouldSkipSimulation>,
+) {
+    let period = if pacer.target_hz > 0.0 {
+        1.0 / pacer.target_hz
+    } else {
+        0.0
+    };
+    *since_tick += time.delta_seconds();
+    skip.0 = *since_tick < period;
+    if !skip.0 {
+        // Keep the remainder so that the ticks average to the target, without catching up on a
+        // long frame
+        *since_tick = (*since_tick - period).min(period);
+    }
+}
+
+/// Label of the system advancing the [`SimulationClock`], systems reading it should run after it.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationClockSystem;
+
+/// Time of the simulated world, that can be paused and run slower or faster than the real time.
+#[derive(Resource)]
+pub struct SimulationClock {
+    // Simulated time since startup, in seconds
+    pub elapsed: f32,
+    // Simulated seconds per real second
+    pub speed: f32,
+    pub paused: bool,
+    // Simulated time since the last tick, in seconds. Zero while paused and between ticks
+    pub delta: f32,
+    // Real time simulated on each tick in place of the time elapsed, for recordings
+    pub fixed_tick: Option<f32>,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            elapsed: 0.0,
+            speed: 1.0,
+            paused: false,
+            delta: 0.0,
+            fixed_tick: None,
+        }
+    }
+}
+
+// On a tick, the delta covers the real time since the previous one
+fn tick_simulation_clock(
+    time: Res<Time>,
+    skip: Res<ShouldSkipSimulation>,
+    mut since_tick: Local<f32>,
+    mut clock: ResMut<SimulationClock>,
+) {
+    *since_tick += time.delta_seconds();
+    if skip.0 {
+        clock.delta = 0.0;
+        return;
+    }
+    clock.delta = if clock.paused {
+        0.0
+    } else {
+        clock.fixed_tick.unwrap_or(*since_tick) * clock.speed
+    };
+    *since_tick = 0.0;
+    clock.elapsed += clock.delta;
+}
+
+fn simulation_clock_input(input: Res<Input<KeyCode>>, mut clock: ResMut<SimulationClock>) {
+    if input.just_pressed(KeyCode::T) {
+        clock.paused = !clock.paused;
+        info!("simulation paused: {}", clock.paused);
+    }
+    if input.just_pressed(KeyCode::LBracket) {
+        clock.speed /= 2.0;
+        info!("simulation speed: {}x", clock.speed);
+    }
+    if input.just_pressed(KeyCode::RBracket) {
+        clock.speed *= 2.0;
+        info!("simulation speed: {}x", clock.speed);
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+};
+
+use crate::plugins::{camera::MainCamera, sky::SunLight};
+
+// Inside the far plane of the cameras, in meters
+pub const SKY_RADIUS: f32 = 900.0;
+
+pub const SKY_SHADER_PATH: &str = "shaders/sky.wgsl";
+
+/// Procedural sky with Rayleigh and Mie scattering, lit by the [`SunLight`].
+///
+/// Bevy 0.9 has no post processing pass, so the sky is drawn from the inside of a sphere following
+/// the [`MainCamera`], behind the scene. Its brightness follows the illuminance of the sun over the
+/// day/night cycle.
+///
+/// The shader is an asset, reloaded when `assets/shaders/sky.wgsl` changes except on the web.
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<SkyMaterial>::default())
+            .register_type::<SkyDome>()
+            .init_resource::<SkySettings>()
+            .add_startup_system(setup_sky)
+            .add_system(follow_camera)
+            .add_system(update_sky)
+            .add_system(sky_shader_reload);
+    }
+}
+
+/// Scattering coefficients of the atmosphere, per meter.
+#[derive(Resource, Clone, Debug)]
+pub struct SkySettings {
+    // Scattering by the air molecules for red, green and blue, the blue of the sky
+    pub rayleigh_coefficient: Vec3,
+    // Scattering by the aerosols, the haze around the sun
+    pub mie_coefficient: f32,
+}
+
+impl Default for SkySettings {
+    fn default() -> Self {
+        Self {
+            rayleigh_coefficient: Vec3::new(5.8e-6, 13.5e-6, 33.1e-6),
+            mie_coefficient: 21e-6,
+        }
+    }
+}
+
+#[derive(AsBindGroup, TypeUuid, Clone)]
+#[uuid = "4f2c8e1a-6b3d-4d7e-a95f-2c1b8e7d3a60"]
+pub struct SkyMaterial {
+    // Towards the sun
+    #[uniform(0)]
+    sun_direction: Vec3,
+    // In lux
+    #[uniform(1)]
+    sun_illuminance: f32,
+    #[uniform(2)]
+    rayleigh_coefficient: Vec3,
+    #[uniform(3)]
+    mie_coefficient: f32,
+}
+
+impl Material for SkyMaterial {
+    fn fragment_shader() -> ShaderRef {
+        SKY_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Seen from the inside
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+/// The sphere the procedural sky is drawn on.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct SkyDome;
+
+// The same handle as the one used by the material pipeline, kept to notice when it is reloaded
+#[derive(Resource)]
+struct SkyShader(Handle<Shader>);
+
+fn setup_sky(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<SkySettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+) {
+    commands.spawn((
+        MaterialMeshBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: SKY_RADIUS,
+                sectors: 64,
+                stacks: 32,
+            })),
+            material: materials.add(SkyMaterial {
+                sun_direction: Vec3::Y,
+                sun_illuminance: 0.0,
+                rayleigh_coefficient: settings.rayleigh_coefficient,
+                mie_coefficient: settings.mie_coefficient,
+            }),
+            ..default()
+        },
+        NotShadowCaster,
+        NotShadowReceiver,
+        Name::new("Sky"),
+        SkyDome,
+    ));
+    commands.insert_resource(SkyShader(asset_server.load(SKY_SHADER_PATH)));
+}
+
+// Bevy recompiles the pipelines using a shader when it changes, only tell that it happened
+fn sky_shader_reload(mut events: EventReader<AssetEvent<Shader>>, shader: Res<SkyShader>) {
+    for event in events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if *handle == shader.0 {
+                info!("==================================");
+                info!("  sky shader reloaded: {SKY_SHADER_PATH}");
+                info!("==================================");
+            }
+        }
+    }
+}
+
+fn follow_camera(
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut sky: Query<&mut Transform, With<SkyDome>>,
+) {
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    for mut transform in sky.iter_mut() {
+        transform.translation = camera;
+    }
+}
+
+fn update_sky(
+    settings: Res<SkySettings>,
+    sun: Query<(&Transform, &DirectionalLight), With<SunLight>>,
+    sky: Query<&Handle<SkyMaterial>, With<SkyDome>>,
+    mut materials: ResMut<Assets<SkyMaterial>>,
+) {
+    let (sun_transform, sun_light) = match sun.get_single() {
+        Ok(sun) => sun,
+        Err(_) => return,
+    };
+    for handle in sky.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            // The light shines forward, away from the sun
+            material.sun_direction = sun_transform.back();
+            // Already dimmed at night and by the rain
+            material.sun_illuminance = sun_light.illuminance;
+            material.rayleigh_coefficient = settings.rayleigh_coefficient;
+            material.mie_coefficient = settings.mie_coefficient;
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use bevy::{
+    asset::{AssetLoader, AssetPath, HandleId, LoadContext, LoadedAsset},
+    gltf::Gltf,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::Deserialize;
+
+use crate::{
+    loading::{ScenesReady, ScenesReadySystem},
+    plugins::{
+        camera::MainCamera,
+        scene::{ExteriorStreaming, SceneArgs, SceneLoadStage, SceneMode, Scenes},
+    },
+    scene_fix::SpawnedFor,
+};
+
+pub const SCENE_STREAMING_PATH: &str = "bistro.streaming.ron";
+
+/// Unloads the exterior scene while the camera is in the interior, and loads it back when the
+/// camera leaves, with the area of the interior from `bistro.streaming.ron`.
+///
+/// The camera has to go a margin inside the area for the exterior to be unloaded, and a wider
+/// margin outside of it for the exterior to be loaded back, so that moving along a wall doesn't
+/// reload it every frame. The screen fades to black while the exterior is spawned and fixed again.
+pub struct SceneStreamingPlugin;
+
+impl Plugin for SceneStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SceneStreamingDescription>()
+            .add_asset_loader(SceneStreamingLoader)
+            .init_resource::<SceneStreamingSettings>()
+            .add_startup_system(load_scene_streaming)
+            .add_startup_system(setup_streaming_fade)
+            .add_system(update_scene_streaming)
+            .add_system(
+                stream_exterior
+                    .label(ScenesReadySystem)
+                    .after(update_scene_streaming),
+            )
+            .add_system(update_streaming_fade.after(stream_exterior));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SceneStreamingSettings {
+    // Bounds of the interior, nothing is streamed until they are loaded
+    pub interior: Option<(Vec3, Vec3)>,
+    // Distance inside the bounds past which the exterior is unloaded, in meters
+    pub unload_margin: f32,
+    // Distance outside the bounds past which the exterior is loaded back, in meters
+    pub reload_margin: f32,
+    // Of each half of the fade to black, in seconds
+    pub fade_duration: f32,
+    // Keeps the exterior as it is, as while the lights are exported
+    pub paused: bool,
+}
+
+#[derive(Deserialize)]
+struct AreaDescription {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "3c6f1e2a-8d4b-4f7e-a1c9-5b2d7e0f9a34"]
+struct SceneStreamingDescription {
+    interior: AreaDescription,
+    unload_margin: f32,
+    reload_margin: f32,
+    fade_duration: f32,
+}
+
+struct SceneStreamingLoader;
+
+impl AssetLoader for SceneStreamingLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let description = ron::de::from_bytes::<SceneStreamingDescription>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(description));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["streaming.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct SceneStreamingHandle(Handle<SceneStreamingDescription>);
+
+fn load_scene_streaming(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SceneStreamingHandle(
+        asset_server.load(SCENE_STREAMING_PATH),
+    ));
+}
+
+fn update_scene_streaming(
+    mut events: EventReader<AssetEvent<SceneStreamingDescription>>,
+    descriptions: Res<Assets<SceneStreamingDescription>>,
+    handle: Res<SceneStreamingHandle>,
+    mut settings: ResMut<SceneStreamingSettings>,
+) {
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle: updated } | AssetEvent::Modified { handle: updated }
+                if *updated == handle.0 =>
+            {
+                if let Some(description) = descriptions.get(updated) {
+                    let min = Vec3::from(description.interior.min);
+                    let max = Vec3::from(description.interior.max);
+                    *settings = SceneStreamingSettings {
+                        interior: Some((min.min(max), min.max(max))),
+                        unload_margin: description.unload_margin,
+                        reload_margin: description.reload_margin,
+                        fade_duration: description.fade_duration,
+                        paused: settings.paused,
+                    };
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// Whether the point is in the bounds shrunk by the margin, a negative margin grows them
+fn is_inside((min, max): (Vec3, Vec3), margin: f32, point: Vec3) -> bool {
+    point.cmpge(min + margin).all() && point.cmple(max - margin).all()
+}
+
+// From 0 to 1 over the duration, immediately without a fade
+fn fade_progress(elapsed: f32, duration: f32) -> f32 {
+    if duration > 0.0 {
+        (elapsed / duration).min(1.0)
+    } else {
+        1.0
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_exterior(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SceneStreamingSettings>,
+    scene_args: Res<SceneArgs>,
+    scene_mode: Res<SceneMode>,
+    scenes: Option<ResMut<Scenes>>,
+    mut load_stage: ResMut<SceneLoadStage>,
+    mut ready: ResMut<ScenesReady>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut scene_assets: ResMut<Assets<Scene>>,
+    mut gltfs: ResMut<Assets<Gltf>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    spawned_for: Query<(Entity, &SpawnedFor)>,
+) {
+    // Without the interior, there is nothing to hide the exterior
+    let (mut scenes, interior) = match (scenes, settings.interior) {
+        (Some(scenes), Some(interior)) if *scene_mode == SceneMode::Both && !settings.paused => {
+            (scenes, interior)
+        }
+        _ => return,
+    };
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    let scene_handle = asset_server.get_handle::<Scene, _>(scene_args.exterior.as_str());
+    // The file of the scene, without its label
+    let file = scene_args
+        .exterior
+        .split('#')
+        .next()
+        .unwrap_or(&scene_args.exterior);
+
+    scenes.exterior_streaming = match scenes.exterior_streaming {
+        ExteriorStreaming::Loaded => match scenes.exterior_instance {
+            // Only once the scenes are fixed and the loading screen closed
+            Some(instance)
+                if scenes.exterior.is_none()
+                    && ready.0
+                    && is_inside(interior, settings.unload_margin, camera) =>
+            {
+                let entities = scene_spawner
+                    .iter_instance_entities(instance)
+                    .collect::<HashSet<_>>();
+                for (entity, spawned_for) in spawned_for.iter() {
+                    if entities.contains(&spawned_for.0) {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                scene_spawner.despawn_instance(instance);
+                // The scene spawner keeps a handle to the scene, the assets are removed so that
+                // the meshes and textures they hold are freed
+                scene_assets.remove(&scene_handle);
+                gltfs.remove(HandleId::from(AssetPath::from(file)));
+                scenes.exterior_instance = None;
+                load_stage.exterior_ready = false;
+                ready.0 = false;
+                info!("camera in the interior, exterior scene unloaded");
+                ExteriorStreaming::Unloaded
+            }
+            _ => ExteriorStreaming::Loaded,
+        },
+        ExteriorStreaming::Unloaded => {
+            if is_inside(interior, -settings.reload_margin, camera) {
+                ExteriorStreaming::Unloaded
+            } else {
+                info!("camera out of the interior, reloading the exterior scene");
+                asset_server.reload_asset(file);
+                ExteriorStreaming::Reloading
+            }
+        }
+        // Spawned once loaded, the screen is black while it is fixed
+        ExteriorStreaming::Reloading => {
+            if scene_assets.contains(&scene_handle) {
+                ExteriorStreaming::FadingOut(0.0)
+            } else {
+                ExteriorStreaming::Reloading
+            }
+        }
+        ExteriorStreaming::FadingOut(elapsed) => {
+            let elapsed = elapsed + time.delta_seconds();
+            if fade_progress(elapsed, settings.fade_duration) < 1.0 {
+                ExteriorStreaming::FadingOut(elapsed)
+            } else {
+                let instance = scene_spawner.spawn(scene_handle);
+                scenes.exterior = Some(instance);
+                scenes.exterior_instance = Some(instance);
+                ExteriorStreaming::Spawning
+            }
+        }
+        ExteriorStreaming::Spawning => {
+            if load_stage.exterior_ready {
+                info!("exterior scene reloaded");
+                ready.0 = true;
+                ExteriorStreaming::FadingIn(0.0)
+            } else {
+                ExteriorStreaming::Spawning
+            }
+        }
+        ExteriorStreaming::FadingIn(elapsed) => {
+            let elapsed = elapsed + time.delta_seconds();
+            if fade_progress(elapsed, settings.fade_duration) < 1.0 {
+                ExteriorStreaming::FadingIn(elapsed)
+            } else {
+                ExteriorStreaming::Loaded
+            }
+        }
+    };
+}
+
+#[derive(Component)]
+struct StreamingFade;
+
+fn setup_streaming_fade(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            visibility: Visibility { is_visible: false },
+            // Over the rest of the UI
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+        StreamingFade,
+    ));
+}
+
+fn update_streaming_fade(
+    settings: Res<SceneStreamingSettings>,
+    scenes: Option<Res<Scenes>>,
+    mut fade: Query<(&mut BackgroundColor, &mut Visibility), With<StreamingFade>>,
+) {
+    let scenes = match scenes {
+        Some(scenes) => scenes,
+        None => return,
+    };
+    let opacity = match scenes.exterior_streaming {
+        ExteriorStreaming::Loaded | ExteriorStreaming::Unloaded | ExteriorStreaming::Reloading => {
+            0.0
+        }
+        ExteriorStreaming::FadingOut(elapsed) => fade_progress(elapsed, settings.fade_duration),
+        ExteriorStreaming::Spawning => 1.0,
+        ExteriorStreaming::FadingIn(elapsed) => {
+            1.0 - fade_progress(elapsed, settings.fade_duration)
+        }
+    };
+    for (mut color, mut visibility) in fade.iter_mut() {
+        let shown = opacity > 0.0;
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if color.0.a() != opacity {
+            color.0 = Color::rgba(0.0, 0.0, 0.0, opacity);
+        }
+    }
+}
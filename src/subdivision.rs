@@ -0,0 +1,117 @@
+use bevy::{prelude::*, render::primitives::Aabb, utils::HashMap};
+
+use crate::{mesh_utils::subdivide, plugins::camera::MainCamera};
+
+// Each level has four times the triangles of the previous one
+const MAX_SUBDIVISION_LEVEL: u8 = 3;
+// Subdivided meshes are only restored a bit further away, so they don't swap back and forth
+const RESTORE_DISTANCE_RATIO: f32 = 1.25;
+
+/// Subdivides the meshes with a [`SubdivisionLevel`] while the camera is close to them, for
+/// smoother surfaces up close.
+///
+/// Subdivided meshes are computed the first time they are needed, and kept for the next time.
+pub struct SubdivisionPlugin;
+
+impl Plugin for SubdivisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SubdivisionSettings>()
+            .add_system(subdivide_on_approach);
+    }
+}
+
+#[derive(Resource)]
+pub struct SubdivisionSettings {
+    // Meshes closer than this to the camera are subdivided, in meters
+    pub distance: f32,
+}
+
+impl Default for SubdivisionSettings {
+    fn default() -> Self {
+        Self { distance: 3.0 }
+    }
+}
+
+/// Number of times each triangle of the mesh is split in four when the camera is close to it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SubdivisionLevel(pub u8);
+
+// The mesh used when the camera is away
+#[derive(Component)]
+struct Subdivided {
+    original: Handle<Mesh>,
+}
+
+#[allow(clippy::type_complexity)]
+fn subdivide_on_approach(
+    mut commands: Commands,
+    settings: Res<SubdivisionSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    // Subdivided meshes by original mesh and level, `None` for those that can't be subdivided
+    mut cache: Local<HashMap<(Handle<Mesh>, u8), Option<Handle<Mesh>>>>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    entities: Query<(
+        Entity,
+        &SubdivisionLevel,
+        &Handle<Mesh>,
+        &Aabb,
+        &GlobalTransform,
+        Option<&Subdivided>,
+    )>,
+) {
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    for (entity, level, mesh, aabb, transform, subdivided) in entities.iter() {
+        let distance = distance_to_bounds(camera, aabb, transform);
+        match subdivided {
+            None if distance < settings.distance => {
+                let level = level.0.min(MAX_SUBDIVISION_LEVEL);
+                if level == 0 {
+                    continue;
+                }
+                let key = (mesh.clone(), level);
+                if !cache.contains_key(&key) {
+                    let original = match meshes.get(mesh) {
+                        Some(original) => original,
+                        None => continue,
+                    };
+                    let subdivided =
+                        (0..level).try_fold(original.clone(), |mesh, _| subdivide(&mesh));
+                    if subdivided.is_none() {
+                        warn!("could not subdivide the mesh of {entity:?}");
+                    }
+                    cache.insert(key.clone(), subdivided.map(|mesh| meshes.add(mesh)));
+                }
+                if let Some(Some(handle)) = cache.get(&key) {
+                    commands.entity(entity).insert((
+                        handle.clone(),
+                        Subdivided {
+                            original: mesh.clone(),
+                        },
+                    ));
+                }
+            }
+            Some(subdivided) if distance > settings.distance * RESTORE_DISTANCE_RATIO => {
+                commands
+                    .entity(entity)
+                    .insert(subdivided.original.clone())
+                    .remove::<Subdivided>();
+            }
+            _ => (),
+        }
+    }
+}
+
+// Distance from the point to the box containing the bounds once transformed, 0 inside it
+fn distance_to_bounds(point: Vec3, aabb: &Aabb, transform: &GlobalTransform) -> f32 {
+    let affine = transform.affine();
+    let center = affine.transform_point3a(aabb.center);
+    let half_extents = affine.matrix3.x_axis.abs() * aabb.half_extents.x
+        + affine.matrix3.y_axis.abs() * aabb.half_extents.y
+        + affine.matrix3.z_axis.abs() * aabb.half_extents.z;
+    ((Vec3::from(center) - point).abs() - Vec3::from(half_extents))
+        .max(Vec3::ZERO)
+        .length()
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use chrono::{Datelike, NaiveDateTime, Timelike, Utc};
+
+use crate::platform;
+
+/// Switches the sun between the day/night cycle and its real position in the sky, with `Ctrl+R`.
+///
+/// The real position is computed from the [`RealSunMode`] location and date, with the NOAA
+/// approximations of the equation of time and of the solar declination.
+pub struct SunPositionPlugin;
+
+impl Plugin for SunPositionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SunMode>()
+            .init_resource::<RealSunMode>()
+            .add_system(toggle_sun_mode);
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SunMode {
+    // The sun orbits at the speed of the day/night cycle
+    #[default]
+    Cycle,
+    // The sun is where it would be at the `RealSunMode` location and date
+    Real,
+}
+
+#[derive(Resource)]
+pub struct RealSunMode {
+    // In degrees, north and east are positive
+    pub latitude: f32,
+    pub longitude: f32,
+    // In UTC
+    pub date_time: NaiveDateTime,
+    // Simulated seconds per real second
+    pub time_multiplier: f32,
+}
+
+impl Default for RealSunMode {
+    fn default() -> Self {
+        // The bistro is in Paris
+        Self {
+            latitude: 48.8566,
+            longitude: 2.3522,
+            date_time: Utc::now().naive_utc(),
+            time_multiplier: 600.0,
+        }
+    }
+}
+
+/// Position of the sun in the sky, in radians.
+#[derive(Clone, Copy, Debug)]
+pub struct SunPosition {
+    // Clockwise from the north
+    pub azimuth: f32,
+    // Above the horizon
+    pub elevation: f32,
+}
+
+impl SunPosition {
+    // Direction towards the sun, with the north towards -Z and the east towards +X
+    pub fn direction(&self) -> Vec3 {
+        Vec3::new(
+            self.azimuth.sin() * self.elevation.cos(),
+            self.elevation.sin(),
+            -self.azimuth.cos() * self.elevation.cos(),
+        )
+    }
+}
+
+impl RealSunMode {
+    pub fn advance(&mut self, delta_seconds: f32) {
+        let milliseconds = (delta_seconds * self.time_multiplier * 1000.0) as i64;
+        self.date_time += chrono::Duration::milliseconds(milliseconds);
+    }
+
+    pub fn sun_position(&self) -> SunPosition {
+        let date_time = &self.date_time;
+        let minutes = date_time.hour() as f32 * 60.0
+            + date_time.minute() as f32
+            + (date_time.second() as f32 + date_time.nanosecond() as f32 / 1e9) / 60.0;
+        // Fractional year
+        let gamma = std::f32::consts::TAU / 365.0
+            * (date_time.ordinal0() as f32 + (minutes / 60.0 - 12.0) / 24.0);
+        // In minutes
+        let equation_of_time = 229.18
+            * (0.000075 + 0.001868 * gamma.cos()
+                - 0.032077 * gamma.sin()
+                - 0.014615 * (2.0 * gamma).cos()
+                - 0.040849 * (2.0 * gamma).sin());
+        let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+            - 0.006758 * (2.0 * gamma).cos()
+            + 0.000907 * (2.0 * gamma).sin()
+            - 0.002697 * (3.0 * gamma).cos()
+            + 0.00148 * (3.0 * gamma).sin();
+
+        let true_solar_time = minutes + equation_of_time + 4.0 * self.longitude;
+        let hour_angle = (true_solar_time / 4.0 - 180.0).to_radians();
+        let latitude = self.latitude.to_radians();
+
+        let elevation = (latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos())
+        .clamp(-1.0, 1.0)
+        .asin();
+        // From the south towards the west, turned to start from the north
+        let azimuth = hour_angle
+            .sin()
+            .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+            + std::f32::consts::PI;
+        SunPosition { azimuth, elevation }
+    }
+}
+
+fn toggle_sun_mode(
+    input: Res<Input<KeyCode>>,
+    mut mode: ResMut<SunMode>,
+    real_sun: Res<RealSunMode>,
+) {
+    if !(input.just_pressed(KeyCode::R) && input.any_pressed(platform::SHORTCUT_MODIFIER)) {
+        return;
+    }
+    *mode = match *mode {
+        SunMode::Cycle => SunMode::Real,
+        SunMode::Real => SunMode::Cycle,
+    };
+    let position = real_sun.sun_position();
+    info!(
+        "sun mode: {:?} | {} UTC - azimuth: {:.1}° - elevation: {:.1}°",
+        *mode,
+        real_sun.date_time.format("%Y-%m-%d %H:%M:%S"),
+        position.azimuth.to_degrees(),
+        position.elevation.to_degrees()
+    );
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    cli,
+    loading::GameState,
+    screenshot::{CaptureFrame, SavingScreenshots},
+    simulation_clock::{SimulationClock, SimulationPacer},
+};
+
+const TIMELAPSE_FOLDER: &str = "timelapse";
+
+/// With `--timelapse <frames per simulated second>`, every frame is saved to
+/// `timelapse/frame_NNNNNN.png` once the scenes are loaded, and the ffmpeg command encoding them
+/// to a video is printed on exit.
+///
+/// Each frame moves the simulation forward by the same time, however long it took to render and
+/// save, so the recording doesn't depend on the speed of the machine. The `[` and `]` keys still
+/// change the speed of the simulation.
+pub struct TimeLapsePlugin;
+
+impl Plugin for TimeLapsePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TimeLapseMode::from_args())
+            .add_startup_system(setup_timelapse)
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(capture_frames))
+            .add_system_to_stage(CoreStage::Last, print_encoding_command);
+    }
+}
+
+#[derive(Resource)]
+pub struct TimeLapseMode {
+    // Frames saved per second of simulation at a speed of 1
+    pub frames_per_second_of_simulation: f32,
+    // Frame rate of the encoded video
+    pub output_fps: u32,
+}
+
+impl TimeLapseMode {
+    fn from_args() -> Self {
+        if cfg!(target_arch = "wasm32") {
+            eprintln!("time-lapses can't be saved on the web");
+            std::process::exit(1);
+        }
+        let value = cli::value("--timelapse").unwrap_or_default();
+        let frames_per_second_of_simulation = match value.parse::<f32>() {
+            Ok(fps) if fps > 0.0 => fps,
+            _ => {
+                eprintln!("invalid --timelapse: {value:?}, expected a positive number");
+                std::process::exit(1);
+            }
+        };
+        let output_fps = cli::value("--timelapse-output-fps")
+            .map(|value| match value.parse::<u32>() {
+                Ok(fps) if fps > 0 => fps,
+                _ => {
+                    eprintln!(
+                        "invalid --timelapse-output-fps: {value:?}, expected a positive integer"
+                    );
+                    std::process::exit(1);
+                }
+            })
+            .unwrap_or(30);
+        Self {
+            frames_per_second_of_simulation,
+            output_fps,
+        }
+    }
+}
+
+fn setup_timelapse(
+    mode: Res<TimeLapseMode>,
+    mut clock: ResMut<SimulationClock>,
+    mut pacer: ResMut<SimulationPacer>,
+) {
+    if pacer.target_hz > 0.0 {
+        warn!("--simulation-hz is ignored in time-lapses, the simulation ticks on every frame");
+        pacer.target_hz = 0.0;
+    }
+    clock.fixed_tick = Some(1.0 / mode.frames_per_second_of_simulation);
+    info!(
+        "time-lapse: {} frames per second of simulation, saved to {TIMELAPSE_FOLDER}/",
+        mode.frames_per_second_of_simulation
+    );
+}
+
+fn capture_frames(mut frame: Local<u32>, mut captures: EventWriter<CaptureFrame>) {
+    captures.send(CaptureFrame {
+        path: format!("{TIMELAPSE_FOLDER}/frame_{:06}.png", *frame).into(),
+    });
+    *frame += 1;
+}
+
+fn print_encoding_command(
+    mut app_exit: EventReader<AppExit>,
+    mode: Res<TimeLapseMode>,
+    saving: Res<SavingScreenshots>,
+) {
+    if app_exit.iter().last().is_none() {
+        return;
+    }
+    // The last frames are still being encoded
+    while saving.count() > 0 {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    println!(
+        "encode the time-lapse with:\nffmpeg -framerate {} -i {TIMELAPSE_FOLDER}/frame_%06d.png -c:v libx264 -pix_fmt yuv420p timelapse.mp4",
+        mode.output_fps
+    );
+}
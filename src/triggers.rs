@@ -0,0 +1,89 @@
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use crate::{
+    plugins::camera::MainCamera,
+    simulation_clock::{ShouldSkipSimulation, SimulationClock, SimulationClockSystem},
+};
+
+/// Sends a [`TriggerEvent`] when the camera enters a [`TriggerVolume`], checked on the ticks of the
+/// [`SimulationClock`].
+pub struct TriggersPlugin;
+
+impl Plugin for TriggersPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DoorBell>()
+            .add_event::<TriggerEvent>()
+            .add_system(
+                check_trigger_volumes
+                    .label(TriggerSystem)
+                    .after(SimulationClockSystem),
+            );
+    }
+}
+
+/// Label of the system sending the [`TriggerEvent`]s, listeners should run after it.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TriggerSystem;
+
+/// The camera entered the trigger volume of this entity.
+// Only listened to with the `audio` feature for now
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub struct TriggerEvent(pub Entity);
+
+/// A box in the local space of its entity, triggering once each time the camera enters it.
+#[derive(Component)]
+pub struct TriggerVolume {
+    pub aabb: Aabb,
+    // Entering again sooner than this after a trigger doesn't trigger, in simulated seconds
+    pub cooldown_seconds: f32,
+    inside: bool,
+    last_triggered: Option<f32>,
+}
+
+impl TriggerVolume {
+    pub fn new(aabb: Aabb) -> Self {
+        Self {
+            aabb,
+            cooldown_seconds: 5.0,
+            inside: false,
+            last_triggered: None,
+        }
+    }
+}
+
+/// Rings the door bell when the camera enters the [`TriggerVolume`] on the same entity.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct DoorBell;
+
+fn check_trigger_volumes(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    camera: Query<&GlobalTransform, With<MainCamera>>,
+    mut volumes: Query<(Entity, &mut TriggerVolume, &GlobalTransform)>,
+    mut events: EventWriter<TriggerEvent>,
+) {
+    if skip.0 {
+        return;
+    }
+    let camera = match camera.get_single() {
+        Ok(camera) => camera.translation(),
+        Err(_) => return,
+    };
+    let now = clock.elapsed;
+    for (entity, mut volume, transform) in volumes.iter_mut() {
+        let local = transform.affine().inverse().transform_point3(camera);
+        let offset = (local - Vec3::from(volume.aabb.center)).abs();
+        let inside = offset.cmple(volume.aabb.half_extents.into()).all();
+        if inside && !volume.inside {
+            let cooled_down = volume
+                .last_triggered
+                .is_none_or(|last| now - last >= volume.cooldown_seconds);
+            if cooled_down {
+                volume.last_triggered = Some(now);
+                events.send(TriggerEvent(entity));
+            }
+        }
+        volume.inside = inside;
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::{asset::HandleId, prelude::*, render::mesh::VertexAttributeValues, utils::HashMap};
+
+use crate::platform;
+
+/// Toggles the ambient occlusion baked in the vertex colors of the scenes with `Ctrl+A`.
+///
+/// The [`StandardMaterial`] shader already multiplies the base color by the vertex colors of the
+/// meshes that have them. To ignore them, they are removed from the meshes and kept aside until
+/// they are shown again.
+pub struct VertexAoPlugin;
+
+impl Plugin for VertexAoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VertexAoSettings>()
+            .add_system(toggle_vertex_ao)
+            .add_system(apply_vertex_ao.after(toggle_vertex_ao));
+    }
+}
+
+#[derive(Resource)]
+pub struct VertexAoSettings {
+    pub enabled: bool,
+}
+
+impl Default for VertexAoSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+fn toggle_vertex_ao(input: Res<Input<KeyCode>>, mut settings: ResMut<VertexAoSettings>) {
+    if input.just_pressed(KeyCode::A) && input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+fn apply_vertex_ao(
+    settings: Res<VertexAoSettings>,
+    mut events: EventReader<AssetEvent<Mesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    // Vertex colors removed from each mesh while the AO is hidden
+    mut hidden: Local<Option<HashMap<HandleId, VertexAttributeValues>>>,
+) {
+    let has_vertex_colors = |mesh: &Mesh| {
+        matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+            Some(VertexAttributeValues::Float32x4(_))
+        )
+    };
+    let created: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } => Some(handle.id()),
+            _ => None,
+        })
+        .collect();
+    if !settings.is_changed() {
+        // Meshes loaded while the AO is hidden are hidden too
+        if let Some(colors) = hidden.as_mut() {
+            for id in created {
+                if let Some(color) = meshes
+                    .get_mut(&Handle::weak(id))
+                    .filter(|mesh| has_vertex_colors(mesh))
+                    .and_then(|mesh| mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR))
+                {
+                    colors.insert(id, color);
+                }
+            }
+        }
+        return;
+    }
+    match (settings.enabled, hidden.take()) {
+        (true, Some(colors)) => {
+            let count = colors.len();
+            for (id, color) in colors {
+                if let Some(mesh) = meshes.get_mut(&Handle::weak(id)) {
+                    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, color);
+                }
+            }
+            info!("vertex AO: shown on {count} meshes");
+        }
+        (false, None) => {
+            // Only touch the meshes with vertex colors, every modified mesh is uploaded again
+            let ids: Vec<_> = meshes
+                .iter()
+                .filter(|(_, mesh)| has_vertex_colors(mesh))
+                .map(|(id, _)| id)
+                .collect();
+            let mut colors = HashMap::default();
+            for id in ids {
+                if let Some(color) = meshes
+                    .get_mut(&Handle::weak(id))
+                    .and_then(|mesh| mesh.remove_attribute(Mesh::ATTRIBUTE_COLOR))
+                {
+                    colors.insert(id, color);
+                }
+            }
+            info!("vertex AO: hidden on {} meshes", colors.len());
+            *hidden = Some(colors);
+        }
+        (_, colors) => *hidden = colors,
+    }
+}
@@ -0,0 +1,427 @@
+use bevy::prelude::*;
+
+use crate::{
+    plugins::scene::SceneMode,
+    simulation_clock::{ShouldSkipSimulation, SimulationClock, SimulationClockSystem},
+};
+
+// Lengths of the bones of the arms, in meters
+const UPPER_ARM_LENGTH: f32 = 0.3;
+const FOREARM_LENGTH: f32 = 0.3;
+// The elbows start slightly bent forward, so that they don't bend sideways
+const ELBOW_REST_BEND: f32 = 0.5;
+// Radians per second when turning towards the next waypoint
+const TURN_SPEED: f32 = 4.0;
+// Radians of leg swing per meter walked, and largest swing angle
+const STRIDE_FREQUENCY: f32 = 5.0;
+const STRIDE_ANGLE: f32 = 0.4;
+
+/// A placeholder waiter walking through the interior along [`WaiterSettings::waypoints`], with a
+/// [`Tray`] bobbing in their hand.
+///
+/// The right arm reaches for the tray with [`solve_two_bone_ik`], and the legs swing while
+/// walking. The waiter follows the [`SimulationClock`], stops when it is paused, and only moves on
+/// the simulation ticks.
+pub struct WaiterPlugin;
+
+impl Plugin for WaiterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaiterSettings>()
+            .add_startup_system(spawn_waiter)
+            .add_system(walk_waiters.after(SimulationClockSystem))
+            .add_system(bob_trays.after(SimulationClockSystem))
+            .add_system(waiter_arm_ik.after(walk_waiters).after(bob_trays));
+    }
+}
+
+#[derive(Resource)]
+pub struct WaiterSettings {
+    // Points on the floor of the interior visited in a loop
+    pub waypoints: Vec<Vec3>,
+    // In meters per second
+    pub speed: f32,
+}
+
+impl Default for WaiterSettings {
+    fn default() -> Self {
+        Self {
+            waypoints: vec![
+                Vec3::new(3.0, 0.0, -1.0),
+                Vec3::new(7.0, 0.0, -1.0),
+                Vec3::new(7.0, 0.0, 3.0),
+                Vec3::new(3.0, 0.0, 3.0),
+            ],
+            speed: 0.8,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+struct Waiter {
+    // Index of the waypoint walked to
+    next: usize,
+    // Distance walked since spawned, in meters
+    walked: f32,
+}
+
+// Swings around the hip while walking, in the opposite direction of the other leg
+#[derive(Component)]
+struct WaiterLeg {
+    phase: f32,
+}
+
+/// The shoulder of an arm reaching for a target, a child of the same entity as the target.
+#[derive(Component)]
+pub struct WaiterArm {
+    pub forearm: Entity,
+    pub target: Entity,
+}
+
+/// A tray bobbing up and down around its height.
+#[derive(Component)]
+pub struct Tray {
+    // In meters, from the feet of the waiter
+    pub height: f32,
+    pub amplitude: f32,
+    // In bobs per second
+    pub frequency: f32,
+}
+
+// A bone of a limb: a joint entity at the start of the bone, with the mesh going down from it
+fn spawn_bone(
+    commands: &mut Commands,
+    transform: Transform,
+    length: f32,
+    mesh: &Handle<Mesh>,
+    material: &Handle<StandardMaterial>,
+) -> Entity {
+    commands
+        .spawn(SpatialBundle::from_transform(transform))
+        .with_children(|joint| {
+            joint.spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(0.0, -length / 2.0, 0.0),
+                ..default()
+            });
+        })
+        .id()
+}
+
+fn spawn_waiter(
+    mut commands: Commands,
+    settings: Res<WaiterSettings>,
+    scene_mode: Res<SceneMode>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if *scene_mode == SceneMode::ExteriorOnly {
+        return;
+    }
+    let start = settings.waypoints.first().copied().unwrap_or_default();
+
+    let shirt = materials.add(Color::rgb(0.9, 0.9, 0.85).into());
+    let trousers = materials.add(Color::rgb(0.1, 0.1, 0.12).into());
+    let skin = materials.add(Color::rgb(0.8, 0.6, 0.5).into());
+    let metal = materials.add(StandardMaterial {
+        base_color: Color::SILVER,
+        metallic: 1.0,
+        perceptual_roughness: 0.3,
+        ..default()
+    });
+    let leg = meshes.add(Mesh::from(shape::Box::new(0.1, 0.75, 0.1)));
+    let arm = meshes.add(Mesh::from(shape::Box::new(0.07, 0.55, 0.07)));
+    let upper_arm = meshes.add(Mesh::from(shape::Box::new(0.07, UPPER_ARM_LENGTH, 0.07)));
+    let forearm = meshes.add(Mesh::from(shape::Box::new(0.07, FOREARM_LENGTH, 0.07)));
+
+    let mut parts = vec![
+        commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Capsule {
+                    radius: 0.18,
+                    depth: 0.6,
+                    ..default()
+                })),
+                material: shirt.clone(),
+                transform: Transform::from_xyz(0.0, 1.1, 0.0),
+                ..default()
+            })
+            .id(),
+        commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: 0.11,
+                    ..default()
+                })),
+                material: skin,
+                transform: Transform::from_xyz(0.0, 1.6, 0.0),
+                ..default()
+            })
+            .id(),
+        spawn_bone(
+            &mut commands,
+            Transform::from_xyz(-0.25, 1.42, 0.0),
+            0.55,
+            &arm,
+            &shirt,
+        ),
+    ];
+    for (x, phase) in [(-0.09, 0.0), (0.09, std::f32::consts::PI)] {
+        let hip = spawn_bone(
+            &mut commands,
+            Transform::from_xyz(x, 0.75, 0.0),
+            0.75,
+            &leg,
+            &trousers,
+        );
+        commands.entity(hip).insert(WaiterLeg { phase });
+        parts.push(hip);
+    }
+
+    // Held in front of the waiter, at the height of the hand
+    let tray = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(0.4, 0.02, 0.3))),
+                material: metal,
+                transform: Transform::from_xyz(0.3, 1.15, -0.35),
+                ..default()
+            },
+            Tray {
+                height: 1.15,
+                amplitude: 0.04,
+                frequency: 1.5,
+            },
+        ))
+        .id();
+    let elbow = spawn_bone(
+        &mut commands,
+        Transform::from_xyz(0.0, -UPPER_ARM_LENGTH, 0.0)
+            .with_rotation(Quat::from_rotation_x(ELBOW_REST_BEND)),
+        FOREARM_LENGTH,
+        &forearm,
+        &shirt,
+    );
+    let shoulder = spawn_bone(
+        &mut commands,
+        Transform::from_xyz(0.25, 1.42, 0.0),
+        UPPER_ARM_LENGTH,
+        &upper_arm,
+        &shirt,
+    );
+    commands
+        .entity(shoulder)
+        .insert(WaiterArm {
+            forearm: elbow,
+            target: tray,
+        })
+        .add_child(elbow);
+    parts.extend([tray, shoulder]);
+
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_translation(start)),
+            Waiter::default(),
+            Name::new("Waiter"),
+        ))
+        .push_children(&parts);
+}
+
+fn walk_waiters(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    settings: Res<WaiterSettings>,
+    mut waiters: Query<(&mut Waiter, &mut Transform, &Children)>,
+    mut legs: Query<(&WaiterLeg, &mut Transform), Without<Waiter>>,
+) {
+    if skip.0 || settings.waypoints.is_empty() || clock.delta == 0.0 {
+        return;
+    }
+    for (mut waiter, mut transform, children) in waiters.iter_mut() {
+        let next = waiter.next % settings.waypoints.len();
+        let target = settings.waypoints[next];
+        let to_target = target - transform.translation;
+        let step = settings.speed * clock.delta;
+        if to_target.length() <= step {
+            transform.translation = target;
+            waiter.next = (next + 1) % settings.waypoints.len();
+        } else {
+            transform.translation += to_target.normalize() * step;
+        }
+        waiter.walked += step;
+
+        // Turn smoothly, the waypoints are on the floor so the waiter stays upright
+        let direction = Vec3::new(to_target.x, 0.0, to_target.z);
+        if direction.length_squared() > 1e-6 {
+            let facing = Transform::IDENTITY.looking_at(direction, Vec3::Y).rotation;
+            let t = (TURN_SPEED * clock.delta).min(1.0);
+            transform.rotation = transform.rotation.slerp(facing, t);
+        }
+
+        for child in children.iter() {
+            if let Ok((leg, mut leg_transform)) = legs.get_mut(*child) {
+                let swing = (waiter.walked * STRIDE_FREQUENCY + leg.phase).sin() * STRIDE_ANGLE;
+                leg_transform.rotation = Quat::from_rotation_x(swing);
+            }
+        }
+    }
+}
+
+fn bob_trays(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    mut trays: Query<(&Tray, &mut Transform)>,
+) {
+    if skip.0 {
+        return;
+    }
+    for (tray, mut transform) in trays.iter_mut() {
+        let phase = clock.elapsed * tray.frequency * std::f32::consts::TAU;
+        transform.translation.y = tray.height + phase.sin() * tray.amplitude;
+    }
+}
+
+// Rotate the shoulders and elbows so that the hands reach their target. Computed from the local
+// transforms, as the global transforms are only updated at the end of the frame
+fn waiter_arm_ik(
+    skip: Res<ShouldSkipSimulation>,
+    arms: Query<(Entity, &WaiterArm, &Parent)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    if skip.0 {
+        return;
+    }
+    for (shoulder, arm, parent) in arms.iter() {
+        let (root, target) = match (transforms.get(parent.get()), transforms.get(arm.target)) {
+            (Ok(root), Ok(target)) => (*root, target.translation),
+            _ => continue,
+        };
+        let shoulder_position = match transforms.get(shoulder) {
+            Ok(transform) => transform.translation,
+            Err(_) => continue,
+        };
+
+        // Rest pose: upper arm straight down, forearm bent forward, in the space of the waiter
+        let elbow_rest = Quat::from_rotation_x(ELBOW_REST_BEND);
+        let elbow = shoulder_position - Vec3::Y * UPPER_ARM_LENGTH;
+        let hand = elbow + elbow_rest * -Vec3::Y * FOREARM_LENGTH;
+        let (shoulder_rotation, elbow_rotation) = solve_two_bone_ik(
+            root.transform_point(shoulder_position),
+            root.transform_point(elbow),
+            root.transform_point(hand),
+            root.transform_point(target),
+        );
+
+        // From the world space back to the space of the waiter, then to the local joints
+        let to_local = |rotation: Quat| root.rotation.inverse() * rotation * root.rotation;
+        if let Ok(mut transform) = transforms.get_mut(shoulder) {
+            transform.rotation = to_local(shoulder_rotation);
+        }
+        if let Ok(mut transform) = transforms.get_mut(arm.forearm) {
+            transform.rotation = to_local(elbow_rotation) * elbow_rest;
+        }
+    }
+}
+
+/// Rotations bringing the `tip` of a chain of two bones to `target`, or as close as it can reach,
+/// keeping the plane in which the chain bends.
+///
+/// Both rotations are in world space: the first one rotates the whole chain around `root`, the
+/// second one rotates the second bone around `mid`, before the first one is applied.
+pub fn solve_two_bone_ik(root: Vec3, mid: Vec3, tip: Vec3, target: Vec3) -> (Quat, Quat) {
+    let angle = |a: Vec3, b: Vec3| {
+        a.normalize_or_zero()
+            .dot(b.normalize_or_zero())
+            .clamp(-1.0, 1.0)
+            .acos()
+    };
+    let upper = mid.distance(root);
+    let lower = tip.distance(mid);
+    // Out of reach targets are reached for in a straight line, never fully straight so that the
+    // bend plane is kept
+    let reach = target
+        .distance(root)
+        .clamp((upper - lower).abs() + 1e-4, upper + lower - 1e-4);
+
+    // Current angles at the root between the chain and the first bone, and at the mid joint
+    let root_angle = angle(tip - root, mid - root);
+    let mid_angle = angle(root - mid, tip - mid);
+    // Same angles once the chain is as long as the distance to the target, from the law of cosines
+    let cos = |adjacent_a: f32, adjacent_b: f32, opposite: f32| {
+        ((adjacent_a * adjacent_a + adjacent_b * adjacent_b - opposite * opposite)
+            / (2.0 * adjacent_a * adjacent_b))
+            .clamp(-1.0, 1.0)
+            .acos()
+    };
+    let root_target_angle = cos(upper, reach, lower);
+    let mid_target_angle = cos(upper, lower, reach);
+
+    let bend_axis = (tip - root)
+        .cross(mid - root)
+        .try_normalize()
+        .unwrap_or_else(|| (mid - root).any_orthonormal_vector());
+    let bend_root = Quat::from_axis_angle(bend_axis, root_target_angle - root_angle);
+    let bend_mid = Quat::from_axis_angle(bend_axis, mid_target_angle - mid_angle);
+
+    // Then turn the bent chain towards the target
+    let bent_tip = bend_root * ((mid - root) + bend_mid * (tip - mid));
+    let aim = Quat::from_rotation_arc(
+        bent_tip.normalize_or_zero(),
+        (target - root).normalize_or_zero(),
+    );
+    (aim * bend_root, bend_mid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tip of the chain once both rotations of the solver are applied
+    fn solved_tip(root: Vec3, mid: Vec3, tip: Vec3, target: Vec3) -> Vec3 {
+        let (root_rotation, mid_rotation) = solve_two_bone_ik(root, mid, tip, target);
+        assert!(root_rotation.is_finite() && mid_rotation.is_finite());
+        root + root_rotation * ((mid - root) + mid_rotation * (tip - mid))
+    }
+
+    #[test]
+    fn reaches_target_in_reach() {
+        let (root, mid, tip) = (
+            Vec3::ZERO,
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, -1.0, 1.0),
+        );
+        for target in [
+            Vec3::new(0.5, -0.5, 0.8),
+            Vec3::new(0.0, 1.5, 0.0),
+            Vec3::new(-1.2, -0.3, -0.4),
+        ] {
+            let reached = solved_tip(root, mid, tip, target);
+            assert!(reached.abs_diff_eq(target, 1e-3), "{reached} != {target}");
+        }
+    }
+
+    #[test]
+    fn points_at_target_out_of_reach() {
+        let (root, mid, tip) = (
+            Vec3::ZERO,
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, -1.0, 1.0),
+        );
+        let target = Vec3::new(5.0, 1.0, -2.0);
+        let reached = solved_tip(root, mid, tip, target);
+        assert!(reached.normalize().dot(target.normalize()) > 0.9999);
+        assert!((reached.length() - 2.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn straight_chain_bends() {
+        let (root, mid, tip) = (
+            Vec3::ZERO,
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, -2.0, 0.0),
+        );
+        let target = Vec3::new(1.0, -1.0, 0.0);
+        let reached = solved_tip(root, mid, tip, target);
+        assert!(reached.abs_diff_eq(target, 1e-3), "{reached} != {target}");
+    }
+}
@@ -0,0 +1,314 @@
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use rand::Rng;
+
+use crate::{
+    platform,
+    post_processing::{FogSettings, PostProcessEffect, PostProcessingStack},
+    simulation_clock::{ShouldSkipSimulation, SimulationClock, SimulationClockSystem},
+};
+
+/// Rain falling over the scene, toggled with `R`.
+///
+/// Wet spots appear on the ground a while after the rain starts, and dry out after it stops.
+/// Bevy 0.9 has no decals, they are glossy transparent discs lying on the ground. A gray haze is
+/// added to the [`PostProcessingStack`] while it rains. The drops and the spots follow the
+/// [`SimulationClock`].
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherState>()
+            .init_resource::<WeatherSettings>()
+            .add_startup_system(setup_rain)
+            .add_startup_system(setup_wet_decals)
+            .add_system(toggle_rain)
+            .add_system(update_rain_emitter.after(toggle_rain))
+            .add_system(update_rain_fog.after(toggle_rain))
+            .add_system(
+                emit_rain_drops
+                    .after(update_rain_emitter)
+                    .after(SimulationClockSystem),
+            )
+            .add_system(update_rain_drops.after(SimulationClockSystem))
+            .add_system(
+                animate_decals
+                    .after(toggle_rain)
+                    .after(SimulationClockSystem),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct WeatherState {
+    pub rain_enabled: bool,
+}
+
+#[derive(Resource)]
+pub struct WeatherSettings {
+    // Number of wet spots, read when they are spawned at startup
+    pub wet_decal_count: usize,
+    // Seconds of rain before the first spots appear
+    pub wet_decal_delay: f32,
+    // Opacity gained per second while raining, and lost per second once dry
+    pub wet_decal_fade_in_rate: f32,
+    pub wet_decal_fade_out_rate: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            wet_decal_count: 60,
+            wet_decal_delay: 3.0,
+            wet_decal_fade_in_rate: 0.2,
+            wet_decal_fade_out_rate: 0.05,
+        }
+    }
+}
+
+/// A wet spot on the ground, with its own material to fade it.
+#[derive(Component)]
+pub struct WetDecal {
+    pub opacity: f32,
+    // Seconds after `WeatherSettings::wet_decal_delay` before this spot starts to appear
+    onset: f32,
+}
+
+// Spawns rain drops from a rectangle around its position
+#[derive(Component)]
+struct ParticleEmitter {
+    drops_per_second: f32,
+    // Drops not spawned yet, carried over to the next tick
+    pending_drops: f32,
+    // Half extents of the rectangle on the X and Z axes
+    half_extents: Vec2,
+    velocity: Vec3,
+    lifetime: f32,
+}
+
+#[derive(Component)]
+struct RainDrop {
+    velocity: Vec3,
+    lifetime: f32,
+}
+
+#[derive(Resource)]
+struct RainAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_rain(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(RainAssets {
+        mesh: meshes.add(Mesh::from(shape::Capsule {
+            radius: 0.005,
+            depth: 0.15,
+            latitudes: 2,
+            longitudes: 4,
+            ..Default::default()
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.7, 0.75, 0.8, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..Default::default()
+        }),
+    });
+}
+
+fn setup_wet_decals(
+    mut commands: Commands,
+    settings: Res<WeatherSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut rng = rand::thread_rng();
+    let mesh = meshes.add(Mesh::from(shape::Circle::new(1.0)));
+    for _ in 0..settings.wet_decal_count {
+        // Same area as the rain, just above the ground to avoid z-fighting
+        let position = Vec3::new(rng.gen_range(-30.0..30.0), 0.01, rng.gen_range(-30.0..30.0));
+        let size = Vec3::new(rng.gen_range(0.5..2.5), rng.gen_range(0.5..2.5), 1.0);
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                // Dark and glossy where the ground is wet
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(0.02, 0.02, 0.03, 0.0),
+                    perceptual_roughness: 0.05,
+                    reflectance: 0.8,
+                    alpha_mode: AlphaMode::Blend,
+                    ..Default::default()
+                }),
+                // The circle faces Z, lay it on the ground
+                transform: Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2))
+                    .with_scale(size),
+                visibility: Visibility { is_visible: false },
+                ..Default::default()
+            },
+            NotShadowCaster,
+            NotShadowReceiver,
+            WetDecal {
+                opacity: 0.0,
+                onset: rng.gen_range(0.0..10.0),
+            },
+        ));
+    }
+}
+
+// Fade the wet spots in while it rains, each after its onset, and out after the rain stops
+fn animate_decals(
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    weather: Res<WeatherState>,
+    settings: Res<WeatherSettings>,
+    mut rain_time: Local<f32>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut decals: Query<(&mut WetDecal, &Handle<StandardMaterial>, &mut Visibility)>,
+) {
+    if skip.0 {
+        return;
+    }
+    let dt = clock.delta;
+    *rain_time = if weather.rain_enabled {
+        *rain_time + dt
+    } else {
+        0.0
+    };
+    for (mut decal, material, mut visibility) in decals.iter_mut() {
+        let wetting = weather.rain_enabled && *rain_time >= settings.wet_decal_delay + decal.onset;
+        let opacity = if wetting {
+            (decal.opacity + settings.wet_decal_fade_in_rate * dt).min(1.0)
+        } else {
+            (decal.opacity - settings.wet_decal_fade_out_rate * dt).max(0.0)
+        };
+        if opacity == decal.opacity {
+            continue;
+        }
+        decal.opacity = opacity;
+        visibility.is_visible = opacity > 0.0;
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color.set_a(opacity * 0.6);
+        }
+    }
+}
+
+fn toggle_rain(input: Res<Input<KeyCode>>, mut weather: ResMut<WeatherState>) {
+    // Ctrl+R switches the sun mode
+    if input.just_pressed(KeyCode::R) && !input.any_pressed(platform::SHORTCUT_MODIFIER) {
+        weather.rain_enabled = !weather.rain_enabled;
+        info!("rain: {}", weather.rain_enabled);
+    }
+}
+
+// Spawn the emitter above the scene when rain starts, and remove it when it stops
+fn update_rain_emitter(
+    mut commands: Commands,
+    weather: Res<WeatherState>,
+    emitters: Query<Entity, With<ParticleEmitter>>,
+) {
+    if !weather.is_changed() {
+        return;
+    }
+    if weather.rain_enabled {
+        if emitters.is_empty() {
+            commands.spawn((
+                SpatialBundle::from_transform(Transform::from_xyz(0.0, 15.0, 0.0)),
+                ParticleEmitter {
+                    drops_per_second: 1200.0,
+                    pending_drops: 0.0,
+                    half_extents: Vec2::new(30.0, 30.0),
+                    velocity: Vec3::new(0.0, -10.0, 0.0),
+                    lifetime: 2.0,
+                },
+            ));
+        }
+    } else {
+        for entity in emitters.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Before the tonemapping, so that the lights still shine through the haze
+fn update_rain_fog(weather: Res<WeatherState>, mut stack: ResMut<PostProcessingStack>) {
+    if !weather.is_changed() {
+        return;
+    }
+    let is_fog = |effect: &PostProcessEffect| matches!(effect, PostProcessEffect::Fog(_));
+    if !weather.rain_enabled {
+        stack.effects.retain(|effect| !is_fog(effect));
+    } else if !stack.effects.iter().any(is_fog) {
+        let index = stack
+            .effects
+            .iter()
+            .position(|effect| matches!(effect, PostProcessEffect::Tonemap(_)))
+            .unwrap_or(stack.effects.len());
+        stack
+            .effects
+            .insert(index, PostProcessEffect::Fog(FogSettings::default()));
+    }
+}
+
+fn emit_rain_drops(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    rain_assets: Res<RainAssets>,
+    mut emitters: Query<(&GlobalTransform, &mut ParticleEmitter)>,
+) {
+    if skip.0 {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for (transform, mut emitter) in emitters.iter_mut() {
+        emitter.pending_drops += emitter.drops_per_second * clock.delta;
+        let count = emitter.pending_drops.floor();
+        emitter.pending_drops -= count;
+        for _ in 0..count as usize {
+            let offset = Vec3::new(
+                rng.gen_range(-emitter.half_extents.x..emitter.half_extents.x),
+                0.0,
+                rng.gen_range(-emitter.half_extents.y..emitter.half_extents.y),
+            );
+            commands.spawn((
+                PbrBundle {
+                    mesh: rain_assets.mesh.clone(),
+                    material: rain_assets.material.clone(),
+                    transform: Transform::from_translation(transform.translation() + offset),
+                    ..Default::default()
+                },
+                NotShadowCaster,
+                NotShadowReceiver,
+                RainDrop {
+                    velocity: emitter.velocity,
+                    lifetime: emitter.lifetime,
+                },
+            ));
+        }
+    }
+}
+
+fn update_rain_drops(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    skip: Res<ShouldSkipSimulation>,
+    mut drops: Query<(Entity, &mut Transform, &mut RainDrop)>,
+) {
+    if skip.0 {
+        return;
+    }
+    for (entity, mut transform, mut drop) in drops.iter_mut() {
+        transform.translation += drop.velocity * clock.delta;
+        drop.lifetime -= clock.delta;
+        if drop.lifetime <= 0.0 || transform.translation.y <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}